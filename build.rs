@@ -12,29 +12,47 @@ fn main() {
     let layer_shell_capture = cfg!(feature = "layer_shell_capture");
     let libei_capture = cfg!(feature = "libei_capture");
     let x11_capture = cfg!(feature = "x11_capture");
+    let evdev_capture = cfg!(feature = "evdev_capture");
 
     let libei_emulation = cfg!(feature = "libei_emulation");
     let x11_emulation = cfg!(feature = "x11_emulation");
     let wlroots_emulation = cfg!(feature = "wlroots_emulation");
     let rdp_emulation = cfg!(feature = "rdp_emulation");
+    let uinput_emulation = cfg!(feature = "uinput_emulation");
+
+    let data_control_clipboard = cfg!(feature = "data_control_clipboard");
+
+    let dbus = cfg!(feature = "dbus");
 
     let layer_shell_capture = unix && !macos && layer_shell_capture;
     let libei_capture = unix && !macos && libei_capture;
     let x11_capture = unix && !macos && x11_capture;
+    let evdev_capture = unix && !macos && evdev_capture;
 
     let libei_emulation = unix && !macos && libei_emulation;
     let rdp_emulation = unix && !macos && rdp_emulation;
     let wlroots_emulation = unix && !macos && wlroots_emulation;
     let x11_emulation = unix && !macos && x11_emulation;
+    let uinput_emulation = unix && !macos && uinput_emulation;
+
+    let data_control_clipboard = unix && !macos && data_control_clipboard;
+
+    let dbus = unix && !macos && dbus;
 
     println!("cargo::rustc-check-cfg=cfg(layer_shell_capture)");
     println!("cargo::rustc-check-cfg=cfg(libei_capture)");
     println!("cargo::rustc-check-cfg=cfg(x11_capture)");
+    println!("cargo::rustc-check-cfg=cfg(evdev_capture)");
 
     println!("cargo::rustc-check-cfg=cfg(libei_emulation)");
     println!("cargo::rustc-check-cfg=cfg(rdp_emulation)");
     println!("cargo::rustc-check-cfg=cfg(wlroots_emulation)");
     println!("cargo::rustc-check-cfg=cfg(x11_emulation)");
+    println!("cargo::rustc-check-cfg=cfg(uinput_emulation)");
+
+    println!("cargo::rustc-check-cfg=cfg(data_control_clipboard)");
+
+    println!("cargo::rustc-check-cfg=cfg(dbus)");
 
     if layer_shell_capture {
         println!("cargo::rustc-cfg=layer_shell_capture");
@@ -45,6 +63,9 @@ fn main() {
     if x11_capture {
         println!("cargo::rustc-cfg=x11_capture");
     }
+    if evdev_capture {
+        println!("cargo::rustc-cfg=evdev_capture");
+    }
 
     if libei_emulation {
         println!("cargo::rustc-cfg=libei_emulation");
@@ -58,4 +79,15 @@ fn main() {
     if x11_emulation {
         println!("cargo::rustc-cfg=x11_emulation");
     }
+    if uinput_emulation {
+        println!("cargo::rustc-cfg=uinput_emulation");
+    }
+
+    if data_control_clipboard {
+        println!("cargo::rustc-cfg=data_control_clipboard");
+    }
+
+    if dbus {
+        println!("cargo::rustc-cfg=dbus");
+    }
 }
@@ -3,15 +3,18 @@ fn main() {
     let layer_shell = cfg!(feature = "layer_shell");
     let libei = cfg!(feature = "libei");
     let x11 = cfg!(feature = "x11");
+    let evdev = cfg!(feature = "evdev");
     let macos = cfg!(target_os = "macos");
 
     let libei = unix && !macos && libei;
     let layer_shell = unix && !macos && layer_shell;
     let x11 = unix && !macos && x11;
+    let evdev = unix && !macos && evdev;
 
     println!("cargo::rustc-check-cfg=cfg(layer_shell)");
     println!("cargo::rustc-check-cfg=cfg(libei)");
     println!("cargo::rustc-check-cfg=cfg(x11)");
+    println!("cargo::rustc-check-cfg=cfg(evdev)");
 
     if layer_shell {
         println!("cargo::rustc-cfg=layer_shell");
@@ -22,4 +25,7 @@ fn main() {
     if x11 {
         println!("cargo::rustc-cfg=x11");
     }
+    if evdev {
+        println!("cargo::rustc-cfg=evdev");
+    }
 }
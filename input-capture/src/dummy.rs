@@ -1,34 +1,173 @@
+mod script;
+
+use std::collections::VecDeque;
 use std::f64::consts::PI;
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
 use std::pin::Pin;
-use std::task::{Context, Poll, ready};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker, ready};
 use std::time::Duration;
 
 use async_trait::async_trait;
+use futures::FutureExt;
 use futures_core::Stream;
 use input_event::PointerEvent;
-use tokio::time::{self, Instant, Interval};
+use tokio::{
+    task::{JoinHandle, spawn_local},
+    time::{self, Instant, Interval},
+};
+
+use super::{Capture, CaptureCreationError, CaptureError, CaptureEvent, Position};
+
+/// where the events of the dummy capture come from
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum DummySource {
+    /// move the pointer in circles on the left edge
+    #[default]
+    Circle,
+    /// events sent through an [`Injector`].
+    /// Like with a real backend, input is dropped while it is not captured,
+    /// i.e. after the capture is released and before the next [`CaptureEvent::Begin`].
+    Injector(Injector),
+    /// events read from a script: a file, a unix socket or `-` for stdin
+    /// (see the `dummy::script` module for the format).
+    /// Input is dropped while it is not captured, as above.
+    Script(PathBuf),
+}
 
-use super::{Capture, CaptureError, CaptureEvent, Position};
+/// Handle sending events to a dummy capture, e.g. from tests.
+/// Clones share the same queue of events.
+#[derive(Clone, Default)]
+pub struct Injector {
+    inner: Arc<Mutex<InjectorState>>,
+}
+
+#[derive(Default)]
+struct InjectorState {
+    pending: VecDeque<(Position, CaptureEvent)>,
+    waker: Option<Waker>,
+}
+
+impl Injector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// emit the event as if it was captured at the given position
+    pub fn inject(&self, pos: Position, event: CaptureEvent) {
+        let mut state = self.inner.lock().expect("lock");
+        state.pending.push_back((pos, event));
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn poll_event(&self, cx: &mut Context<'_>) -> Poll<(Position, CaptureEvent)> {
+        let mut state = self.inner.lock().expect("lock");
+        match state.pending.pop_front() {
+            Some(event) => Poll::Ready(event),
+            None => {
+                state.waker.replace(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Debug for Injector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Injector").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Injector {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for Injector {}
 
 pub struct DummyInputCapture {
+    source: DummySource,
+    script_task: Option<JoinHandle<Result<(), CaptureError>>>,
+    captured: bool,
     start: Option<Instant>,
     interval: Interval,
     offset: (i32, i32),
 }
 
 impl DummyInputCapture {
-    pub fn new() -> Self {
-        Self {
+    pub async fn new(source: DummySource) -> Result<Self, CaptureCreationError> {
+        // a script is executed by a task feeding the events to an injector
+        let (source, script_task) = match source {
+            DummySource::Script(path) => {
+                let script = script::open(&path)
+                    .await
+                    .map_err(CaptureCreationError::Script)?;
+                log::info!("dummy capture: running script {}", path.display());
+                let injector = Injector::new();
+                let task = spawn_local(script::run(script, injector.clone()));
+                (DummySource::Injector(injector), Some(task))
+            }
+            source => (source, None),
+        };
+        Ok(Self {
+            source,
+            script_task,
+            captured: false,
             start: None,
             interval: time::interval(Duration::from_millis(1)),
             offset: (0, 0),
+        })
+    }
+
+    fn poll_circle(&mut self, cx: &mut Context<'_>) -> Poll<(Position, CaptureEvent)> {
+        let current = ready!(self.interval.poll_tick(cx));
+        let event = match self.start {
+            None => {
+                self.start.replace(current);
+                CaptureEvent::Begin(None)
+            }
+            Some(start) => {
+                let elapsed = start.elapsed();
+                let elapsed_sec_f64 = elapsed.as_secs_f64();
+                let second_fraction = elapsed_sec_f64 - elapsed_sec_f64 as u64 as f64;
+                let radians = second_fraction * 2. * PI * FREQUENCY_HZ;
+                let offset = (radians.cos() * RADIUS * 2., (radians * 2.).sin() * RADIUS);
+                let offset = (offset.0 as i32, offset.1 as i32);
+                let relative_motion = (offset.0 - self.offset.0, offset.1 - self.offset.1);
+                self.offset = offset;
+                let (dx, dy) = (relative_motion.0 as f64, relative_motion.1 as f64);
+                CaptureEvent::Input(input_event::Event::Pointer(PointerEvent::Motion {
+                    time: 0,
+                    dx,
+                    dy,
+                }))
+            }
+        };
+        Poll::Ready((Position::Left, event))
+    }
+
+    fn poll_script_error(&mut self, cx: &mut Context<'_>) -> Poll<CaptureError> {
+        let Some(task) = &mut self.script_task else {
+            return Poll::Pending;
+        };
+        let result = ready!(task.poll_unpin(cx));
+        self.script_task.take();
+        match result.expect("capture script task panic") {
+            Ok(()) => Poll::Pending,
+            Err(e) => Poll::Ready(e),
         }
     }
 }
 
-impl Default for DummyInputCapture {
-    fn default() -> Self {
-        Self::new()
+impl Drop for DummyInputCapture {
+    fn drop(&mut self) {
+        if let Some(task) = &self.script_task {
+            task.abort();
+        }
     }
 }
 
@@ -43,6 +182,12 @@ impl Capture for DummyInputCapture {
     }
 
     async fn release(&mut self) -> Result<(), CaptureError> {
+        self.captured = false;
+        Ok(())
+    }
+
+    async fn capture(&mut self, _pos: Position) -> Result<(), CaptureError> {
+        self.captured = true;
         Ok(())
     }
 
@@ -58,29 +203,22 @@ impl Stream for DummyInputCapture {
     type Item = Result<(Position, CaptureEvent), CaptureError>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let current = ready!(self.interval.poll_tick(cx));
-        let event = match self.start {
-            None => {
-                self.start.replace(current);
-                CaptureEvent::Begin
-            }
-            Some(start) => {
-                let elapsed = start.elapsed();
-                let elapsed_sec_f64 = elapsed.as_secs_f64();
-                let second_fraction = elapsed_sec_f64 - elapsed_sec_f64 as u64 as f64;
-                let radians = second_fraction * 2. * PI * FREQUENCY_HZ;
-                let offset = (radians.cos() * RADIUS * 2., (radians * 2.).sin() * RADIUS);
-                let offset = (offset.0 as i32, offset.1 as i32);
-                let relative_motion = (offset.0 - self.offset.0, offset.1 - self.offset.1);
-                self.offset = offset;
-                let (dx, dy) = (relative_motion.0 as f64, relative_motion.1 as f64);
-                CaptureEvent::Input(input_event::Event::Pointer(PointerEvent::Motion {
-                    time: 0,
-                    dx,
-                    dy,
-                }))
-            }
+        let event = match self.source.clone() {
+            DummySource::Circle => ready!(self.poll_circle(cx)),
+            DummySource::Injector(injector) => loop {
+                let Poll::Ready((pos, event)) = injector.poll_event(cx) else {
+                    // errors of a script are reported once its events are consumed
+                    return self.poll_script_error(cx).map(|e| Some(Err(e)));
+                };
+                match event {
+                    CaptureEvent::Begin(_) => self.captured = true,
+                    CaptureEvent::Input(_) if !self.captured => continue,
+                    CaptureEvent::Input(_) => {}
+                }
+                break (pos, event);
+            },
+            DummySource::Script(_) => unreachable!("scripts are run by an injector"),
         };
-        Poll::Ready(Some(Ok((Position::Left, event))))
+        Poll::Ready(Some(Ok(event)))
     }
 }
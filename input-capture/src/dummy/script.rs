@@ -0,0 +1,311 @@
+//! Scripts driving the dummy capture, one command per line:
+//!
+//! ```text
+//! # comments and empty lines are ignored
+//! begin right           # cross an edge (left | right | top | bottom),
+//! begin left 540 1080   # optionally at an offset on an edge of the given length
+//! motion 10 -5          # relative motion
+//! button left press     # left | right | middle | back | forward or a button code,
+//!                       # press | release
+//! key KeyA press        # key name (see `input_event::scancode::Linux`) or scancode
+//! scroll vertical 120   # discrete scroll (vertical | horizontal), 120 = one notch
+//! axis horizontal 7.5   # smooth scroll
+//! modifiers 1 0 0 0     # depressed, latched and locked modifiers and the layout group
+//! wait 100              # pause for the given number of milliseconds
+//! ```
+//!
+//! Input is emitted at the edge crossed by the last `begin`.
+//! As with a real backend, it is dropped while the capture is released.
+
+use std::{path::Path, str::FromStr, time::Duration};
+
+use input_event::{Button, Event, KeyboardEvent, PointerEvent, scancode};
+use tokio::{
+    fs::File,
+    io::{self, AsyncBufRead, AsyncBufReadExt, AsyncRead, BufReader, Lines},
+};
+
+use super::Injector;
+use crate::{CaptureError, CaptureEvent, Crossing, Position, ScriptError};
+
+pub(super) type Script = Lines<Box<dyn AsyncBufRead + Unpin>>;
+
+/// open the script at `path`: a file, a unix socket or `-` for stdin
+pub(super) async fn open(path: &Path) -> io::Result<Script> {
+    if path == Path::new("-") {
+        return Ok(lines(io::stdin()));
+    }
+    #[cfg(unix)]
+    if is_socket(path) {
+        return Ok(lines(tokio::net::UnixStream::connect(path).await?));
+    }
+    Ok(lines(File::open(path).await?))
+}
+
+fn lines(reader: impl AsyncRead + Unpin + 'static) -> Script {
+    let reader: Box<dyn AsyncBufRead + Unpin> = Box::new(BufReader::new(reader));
+    reader.lines()
+}
+
+#[cfg(unix)]
+fn is_socket(path: &Path) -> bool {
+    use std::os::unix::fs::FileTypeExt;
+    std::fs::metadata(path).is_ok_and(|m| m.file_type().is_socket())
+}
+
+/// execute the script, injecting its events into `injector`
+pub(super) async fn run(mut script: Script, injector: Injector) -> Result<(), CaptureError> {
+    let mut pos = Position::Left;
+    let mut line = 0;
+    while let Some(text) = script.next_line().await? {
+        line += 1;
+        match parse(&text).map_err(|reason| ScriptError { line, reason })? {
+            Some(Command::Begin(edge, crossing)) => {
+                pos = edge;
+                injector.inject(pos, CaptureEvent::Begin(crossing));
+            }
+            Some(Command::Input(event)) => injector.inject(pos, CaptureEvent::Input(event)),
+            Some(Command::Wait(duration)) => tokio::time::sleep(duration).await,
+            None => {}
+        }
+    }
+    log::info!("capture script finished");
+    Ok(())
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Begin(Position, Option<Crossing>),
+    Input(Event),
+    Wait(Duration),
+}
+
+const COMMANDS: [&str; 8] = [
+    "begin",
+    "motion",
+    "button",
+    "key",
+    "modifiers",
+    "scroll",
+    "axis",
+    "wait",
+];
+
+fn parse(line: &str) -> Result<Option<Command>, String> {
+    let line = line.split('#').next().unwrap_or_default();
+    let mut args = line.split_whitespace();
+    let Some(command) = args.next() else {
+        return Ok(None);
+    };
+    let pointer = |event| Command::Input(Event::Pointer(event));
+    let keyboard = |event| Command::Input(Event::Keyboard(event));
+    let command = match (command, args.collect::<Vec<_>>().as_slice()) {
+        ("begin", [pos]) => Command::Begin(position(pos)?, None),
+        ("begin", [pos, offset, length]) => Command::Begin(
+            position(pos)?,
+            Some(Crossing {
+                offset: number(offset)?,
+                length: number(length)?,
+            }),
+        ),
+        ("motion", [dx, dy]) => pointer(PointerEvent::Motion {
+            time: 0,
+            dx: number(dx)?,
+            dy: number(dy)?,
+        }),
+        ("button", [b, s]) => pointer(PointerEvent::Button {
+            time: 0,
+            button: button(b)?,
+            state: state(s)?.into(),
+        }),
+        ("key", [k, s]) => keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: key(k)?,
+            state: state(s)?,
+        }),
+        ("modifiers", [depressed, latched, locked, group]) => keyboard(KeyboardEvent::Modifiers {
+            depressed: number(depressed)?,
+            latched: number(latched)?,
+            locked: number(locked)?,
+            group: number(group)?,
+        }),
+        ("scroll", [a, value]) => pointer(PointerEvent::AxisDiscrete120 {
+            axis: axis(a)?,
+            value: number(value)?,
+        }),
+        ("axis", [a, value]) => pointer(PointerEvent::Axis {
+            time: 0,
+            axis: axis(a)?,
+            value: number(value)?,
+        }),
+        ("wait", [ms]) => Command::Wait(Duration::from_millis(number(ms)?)),
+        (command, _) if COMMANDS.contains(&command) => {
+            return Err(format!("wrong number of arguments for `{command}`"));
+        }
+        (command, _) => return Err(format!("unknown command `{command}`")),
+    };
+    Ok(Some(command))
+}
+
+fn number<T: FromStr>(s: &str) -> Result<T, String> {
+    s.parse().map_err(|_| format!("invalid number `{s}`"))
+}
+
+fn position(s: &str) -> Result<Position, String> {
+    match s {
+        "left" => Ok(Position::Left),
+        "right" => Ok(Position::Right),
+        "top" => Ok(Position::Top),
+        "bottom" => Ok(Position::Bottom),
+        _ => Err(format!("invalid position `{s}`")),
+    }
+}
+
+fn button(s: &str) -> Result<u32, String> {
+    s.parse::<Button>()
+        .map(u32::from)
+        .or_else(|_| s.parse())
+        .map_err(|_| format!("invalid button `{s}`"))
+}
+
+fn key(s: &str) -> Result<u32, String> {
+    s.parse()
+        .or_else(|_| s.parse::<scancode::Linux>().map(|k| k as u32))
+        .map_err(|_| format!("invalid key `{s}`"))
+}
+
+fn state(s: &str) -> Result<u8, String> {
+    match s {
+        "press" => Ok(1),
+        "release" => Ok(0),
+        _ => Err(format!("invalid state `{s}` (press | release)")),
+    }
+}
+
+fn axis(s: &str) -> Result<u8, String> {
+    match s {
+        "vertical" => Ok(0),
+        "horizontal" => Ok(1),
+        _ => Err(format!("invalid axis `{s}` (vertical | horizontal)")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use input_event::{BTN_FORWARD, BTN_LEFT, BTN_MIDDLE};
+
+    use super::*;
+
+    fn pointer(event: PointerEvent) -> Option<Command> {
+        Some(Command::Input(Event::Pointer(event)))
+    }
+
+    fn key_event(key: u32, state: u8) -> Option<Command> {
+        Some(Command::Input(Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key,
+            state,
+        })))
+    }
+
+    fn button_event(button: u32, state: u32) -> Option<Command> {
+        pointer(PointerEvent::Button {
+            time: 0,
+            button,
+            state,
+        })
+    }
+
+    #[test]
+    fn commands() {
+        let crossing = Crossing {
+            offset: 540.,
+            length: 1080.,
+        };
+        let cases = [
+            ("", None),
+            ("   ", None),
+            ("# comment", None),
+            ("  # indented comment", None),
+            ("begin right", Some(Command::Begin(Position::Right, None))),
+            (
+                "begin left 540 1080 # with crossing",
+                Some(Command::Begin(Position::Left, Some(crossing))),
+            ),
+            (
+                "motion 10 -5",
+                pointer(PointerEvent::Motion {
+                    time: 0,
+                    dx: 10.,
+                    dy: -5.,
+                }),
+            ),
+            ("button left press", button_event(BTN_LEFT, 1)),
+            ("button forward release", button_event(BTN_FORWARD, 0)),
+            ("button 274 press", button_event(BTN_MIDDLE, 1)),
+            ("key KeyA press", key_event(scancode::Linux::KeyA as u32, 1)),
+            ("key 30 release", key_event(scancode::Linux::KeyA as u32, 0)),
+            (
+                "key KeyLeftShift press#no space before the comment",
+                key_event(scancode::Linux::KeyLeftShift as u32, 1),
+            ),
+            (
+                "scroll vertical 120",
+                pointer(PointerEvent::AxisDiscrete120 {
+                    axis: 0,
+                    value: 120,
+                }),
+            ),
+            (
+                "axis horizontal 7.5",
+                pointer(PointerEvent::Axis {
+                    time: 0,
+                    axis: 1,
+                    value: 7.5,
+                }),
+            ),
+            (
+                "modifiers 1 0 16 0",
+                Some(Command::Input(Event::Keyboard(KeyboardEvent::Modifiers {
+                    depressed: 1,
+                    latched: 0,
+                    locked: 16,
+                    group: 0,
+                }))),
+            ),
+            ("wait 100", Some(Command::Wait(Duration::from_millis(100)))),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse(line), Ok(expected), "`{line}`");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("begin", "wrong number of arguments for `begin`"),
+            ("begin left 540", "wrong number of arguments for `begin`"),
+            ("motion 10", "wrong number of arguments for `motion`"),
+            ("key KeyA", "wrong number of arguments for `key`"),
+            ("wait 1 2", "wrong number of arguments for `wait`"),
+            (
+                "modifiers 1 0 0",
+                "wrong number of arguments for `modifiers`",
+            ),
+            ("jump 10", "unknown command `jump`"),
+            ("begin center", "invalid position `center`"),
+            ("motion ten 0", "invalid number `ten`"),
+            ("button sideways press", "invalid button `sideways`"),
+            ("key KeyNone press", "invalid key `KeyNone`"),
+            ("key KeyA down", "invalid state `down` (press | release)"),
+            (
+                "scroll diagonal 120",
+                "invalid axis `diagonal` (vertical | horizontal)",
+            ),
+            ("wait -1", "invalid number `-1`"),
+        ];
+        for (line, expected) in cases {
+            assert_eq!(parse(line), Err(expected.to_owned()), "`{line}`");
+        }
+    }
+}
@@ -0,0 +1,288 @@
+use std::time::{Duration, Instant};
+
+use input_event::{Event, KeyboardEvent, Modifier, PointerEvent};
+
+use super::{Crossing, Position};
+
+/// Guards against crossing an edge by accident, e.g. when aiming for
+/// a panel or a hot corner at the edge of the screen.
+/// The default guard lets every crossing pass immediately.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EdgeGuard {
+    /// minimum time the pointer has to be pushed against the edge
+    pub dwell_time: Duration,
+    /// minimum distance (in pixels) the pointer has to be pushed past the edge
+    pub push_distance: f64,
+    /// size (in pixels) of the areas at both ends of an edge,
+    /// in which it can not be crossed
+    pub corner_size: f64,
+    /// modifier that has to be held while crossing
+    pub modifier: Option<Modifier>,
+    /// do not cross while a mouse button is held,
+    /// e.g. when dragging a window or selecting text
+    pub block_drags: bool,
+}
+
+/// decision of an [`EdgeGuard`] about a crossing
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Verdict {
+    /// the crossing is intended, the capture can begin
+    Cross,
+    /// the guard is not satisfied yet
+    Wait,
+    /// the crossing was accidental, the capture should be released
+    Cancel,
+}
+
+/// A crossing held back until its [`EdgeGuard`] is satisfied.
+///
+/// Input events captured in the meantime are fed to [`GuardedCrossing::update`]
+/// and held back, to be replayed once the crossing is allowed
+/// (see [`GuardedCrossing::held_back`]). The crossing is cancelled, if the pointer
+/// moves back, a button or a key other than the modifier is pressed
+/// or, if drags are blocked, a button was held when it began.
+/// The input of a cancelled crossing is lost, as it was grabbed already.
+pub struct GuardedCrossing {
+    guard: EdgeGuard,
+    pos: Position,
+    crossing: Option<Crossing>,
+    start: Instant,
+    /// distance the pointer was pushed past the edge
+    pushed: f64,
+    modifier_held: bool,
+    cancelled: bool,
+    /// input captured while waiting, consecutive motion is merged
+    held_back: Vec<Event>,
+}
+
+impl GuardedCrossing {
+    /// `modifiers` is the (xkb) modifier mask held when the crossing began,
+    /// as far as the backend can tell, see [`crate::InputCapture::modifiers_held`]
+    pub fn new(
+        guard: EdgeGuard,
+        pos: Position,
+        crossing: Option<Crossing>,
+        buttons_held: bool,
+        modifiers: u32,
+    ) -> Self {
+        // the location of the crossing is unknown to some backends
+        let in_corner = crossing.is_some_and(|c| {
+            c.offset < guard.corner_size || c.length - c.offset < guard.corner_size
+        });
+        Self {
+            guard,
+            pos,
+            crossing,
+            start: Instant::now(),
+            pushed: 0.,
+            modifier_held: guard.modifier.is_some_and(|m| modifiers & m.mask() != 0),
+            cancelled: in_corner || (guard.block_drags && buttons_held),
+            held_back: vec![],
+        }
+    }
+
+    /// the input held back while waiting for the guard
+    pub fn held_back(self) -> Vec<Event> {
+        self.held_back
+    }
+
+    /// the location of the held back crossing
+    pub fn crossing(&self) -> Option<Crossing> {
+        self.crossing
+    }
+
+    /// time at which the dwell time is over, if it is not yet
+    pub fn deadline(&self) -> Option<Instant> {
+        let deadline = self.start + self.guard.dwell_time;
+        (Instant::now() < deadline).then_some(deadline)
+    }
+
+    pub fn verdict(&self) -> Verdict {
+        if self.cancelled || self.pushed < 0. {
+            Verdict::Cancel
+        } else if self.start.elapsed() < self.guard.dwell_time
+            || self.pushed < self.guard.push_distance
+            || (self.guard.modifier.is_some() && !self.modifier_held)
+        {
+            Verdict::Wait
+        } else {
+            Verdict::Cross
+        }
+    }
+
+    /// update the guard with an event captured while the crossing is held back
+    pub fn update(&mut self, event: Event) -> Verdict {
+        self.hold_back(event);
+        match event {
+            Event::Pointer(PointerEvent::Motion { dx, dy, .. }) => {
+                self.pushed += match self.pos {
+                    Position::Left => -dx,
+                    Position::Right => dx,
+                    Position::Top => -dy,
+                    Position::Bottom => dy,
+                };
+            }
+            Event::Pointer(PointerEvent::Button { state: 1, .. }) => self.cancelled = true,
+            Event::Keyboard(KeyboardEvent::Key { key, state, .. }) => match self.guard.modifier {
+                Some(modifier) if Modifier::of_key(key) == Some(modifier) => {
+                    self.modifier_held = state != 0
+                }
+                _ if state != 0 => self.cancelled = true,
+                _ => {}
+            },
+            Event::Keyboard(KeyboardEvent::Modifiers { depressed, .. }) => {
+                if let Some(modifier) = self.guard.modifier {
+                    self.modifier_held = depressed & modifier.mask() != 0;
+                }
+            }
+            _ => {}
+        }
+        self.verdict()
+    }
+
+    fn hold_back(&mut self, event: Event) {
+        if let (
+            Some(Event::Pointer(PointerEvent::Motion { dx, dy, .. })),
+            Event::Pointer(PointerEvent::Motion { dx: x, dy: y, .. }),
+        ) = (self.held_back.last_mut(), event)
+        {
+            *dx += x;
+            *dy += y;
+        } else {
+            self.held_back.push(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use input_event::scancode::{
+        self,
+        Linux::{KeyA, KeyLeftCtrl},
+    };
+
+    use super::*;
+
+    fn motion(dx: f64, dy: f64) -> Event {
+        Event::Pointer(PointerEvent::Motion { time: 0, dx, dy })
+    }
+
+    fn key(key: scancode::Linux, state: u8) -> Event {
+        Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: key as u32,
+            state,
+        })
+    }
+
+    fn crossing(guard: EdgeGuard) -> GuardedCrossing {
+        let crossing = Crossing::new(500., (0., 1000.));
+        GuardedCrossing::new(guard, Position::Right, Some(crossing), false, 0)
+    }
+
+    #[test]
+    fn default_guard_crosses_immediately() {
+        let guarded = crossing(EdgeGuard::default());
+        assert_eq!(guarded.verdict(), Verdict::Cross);
+        assert_eq!(guarded.deadline(), None);
+    }
+
+    #[test]
+    fn push_distance() {
+        let guard = EdgeGuard {
+            push_distance: 10.,
+            ..Default::default()
+        };
+        let mut guarded = crossing(guard);
+        assert_eq!(guarded.verdict(), Verdict::Wait);
+        assert_eq!(guarded.update(motion(6., 3.)), Verdict::Wait);
+        assert_eq!(guarded.update(motion(4., -1.)), Verdict::Cross);
+        // consecutive motion is held back as one event
+        assert_eq!(guarded.held_back(), vec![motion(10., 2.)]);
+
+        // moving back cancels the crossing
+        let mut guarded = crossing(guard);
+        assert_eq!(guarded.update(motion(-1., 0.)), Verdict::Cancel);
+    }
+
+    #[test]
+    fn dwell_time() {
+        let guard = EdgeGuard {
+            dwell_time: Duration::from_secs(3600),
+            ..Default::default()
+        };
+        let mut guarded = crossing(guard);
+        assert_eq!(guarded.verdict(), Verdict::Wait);
+        assert!(guarded.deadline().is_some());
+        assert_eq!(guarded.update(motion(1., 0.)), Verdict::Wait);
+    }
+
+    #[test]
+    fn corners() {
+        let guard = EdgeGuard {
+            corner_size: 20.,
+            ..Default::default()
+        };
+        let corner = Crossing::new(990., (0., 1000.));
+        let guarded = GuardedCrossing::new(guard, Position::Right, Some(corner), false, 0);
+        assert_eq!(guarded.verdict(), Verdict::Cancel);
+        assert_eq!(crossing(guard).verdict(), Verdict::Cross);
+        // the location is unknown to some backends
+        let unknown = GuardedCrossing::new(guard, Position::Right, None, false, 0);
+        assert_eq!(unknown.verdict(), Verdict::Cross);
+    }
+
+    #[test]
+    fn drags() {
+        let guard = EdgeGuard {
+            block_drags: true,
+            ..Default::default()
+        };
+        let crossing = Some(Crossing::new(500., (0., 1000.)));
+        let drag = GuardedCrossing::new(guard, Position::Right, crossing, true, 0);
+        assert_eq!(drag.verdict(), Verdict::Cancel);
+        let no_drag = GuardedCrossing::new(guard, Position::Right, crossing, false, 0);
+        assert_eq!(no_drag.verdict(), Verdict::Cross);
+    }
+
+    #[test]
+    fn modifier() {
+        let guard = EdgeGuard {
+            modifier: Some(Modifier::Ctrl),
+            ..Default::default()
+        };
+        let mut guarded = crossing(guard);
+        assert_eq!(guarded.verdict(), Verdict::Wait);
+        assert_eq!(guarded.update(key(KeyLeftCtrl, 1)), Verdict::Cross);
+        assert_eq!(guarded.held_back(), vec![key(KeyLeftCtrl, 1)]);
+
+        // held before the crossing, as reported by the backend
+        let crossing = Some(Crossing::new(500., (0., 1000.)));
+        let ctrl = Modifier::Ctrl.mask();
+        let held = GuardedCrossing::new(guard, Position::Right, crossing, false, ctrl);
+        assert_eq!(held.verdict(), Verdict::Cross);
+
+        // reported through the modifier mask
+        let shift = Modifier::Shift.mask();
+        let mut guarded = GuardedCrossing::new(guard, Position::Right, crossing, false, shift);
+        assert_eq!(guarded.verdict(), Verdict::Wait);
+        let modifiers = Event::Keyboard(KeyboardEvent::Modifiers {
+            depressed: ctrl,
+            latched: 0,
+            locked: 0,
+            group: 0,
+        });
+        assert_eq!(guarded.update(modifiers), Verdict::Cross);
+
+        // other keys and buttons cancel the crossing
+        let mut guarded = GuardedCrossing::new(guard, Position::Right, crossing, false, 0);
+        assert_eq!(guarded.update(key(KeyA, 1)), Verdict::Cancel);
+        let mut guarded = GuardedCrossing::new(guard, Position::Right, crossing, false, 0);
+        let button = Event::Pointer(PointerEvent::Button {
+            time: 0,
+            button: input_event::BTN_LEFT,
+            state: 1,
+        });
+        assert_eq!(guarded.update(button), Verdict::Cancel);
+    }
+}
@@ -31,6 +31,13 @@ pub enum CaptureError {
     EndOfStream,
     #[error("io error: `{0}`")]
     Io(#[from] std::io::Error),
+    #[error("the capture backend can not capture input without crossing an edge")]
+    DirectCaptureUnsupported,
+    #[error("capture script: {0}")]
+    Script(#[from] ScriptError),
+    #[cfg(x11)]
+    #[error("failed to grab the pointer and keyboard")]
+    Grab,
     #[cfg(libei)]
     #[error("libei error: `{0}`")]
     Reis(#[from] reis::Error),
@@ -57,10 +64,20 @@ pub enum CaptureError {
     EventTapDisabled,
 }
 
+/// invalid line in the script of the dummy capture
+#[derive(Debug, Error)]
+#[error("line {line}: {reason}")]
+pub struct ScriptError {
+    pub line: usize,
+    pub reason: String,
+}
+
 #[derive(Debug, Error)]
 pub enum CaptureCreationError {
     #[error("no backend available")]
     NoAvailableBackend,
+    #[error("error opening capture script: `{0}`")]
+    Script(std::io::Error),
     #[cfg(libei)]
     #[error("error creating input-capture-portal backend: `{0}`")]
     Libei(#[from] LibeiCaptureCreationError),
@@ -70,6 +87,9 @@ pub enum CaptureCreationError {
     #[cfg(x11)]
     #[error("error creating x11 capture backend: `{0}`")]
     X11(#[from] X11InputCaptureCreationError),
+    #[cfg(evdev)]
+    #[error("error creating evdev capture backend: `{0}`")]
+    Evdev(#[from] EvdevInputCaptureCreationError),
     #[cfg(windows)]
     #[error("error creating windows capture backend")]
     Windows,
@@ -137,8 +157,27 @@ pub enum LayerShellCaptureCreationError {
 #[cfg(x11)]
 #[derive(Debug, Error)]
 pub enum X11InputCaptureCreationError {
-    #[error("X11 input capture is not yet implemented :(")]
-    NotImplemented,
+    #[error("could not open display")]
+    OpenDisplay,
+    #[error("XInputExtension not available")]
+    XInputMissing,
+    #[error("XInput 2.2 is required (server supports {0}.{1})")]
+    XInputVersion(i32, i32),
+    #[error("could not determine master pointer / keyboard")]
+    NoMasterDevice,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+#[cfg(evdev)]
+#[derive(Debug, Error)]
+pub enum EvdevInputCaptureCreationError {
+    #[error("invalid screen size {0}x{1}")]
+    ScreenSize(u32, u32),
+    #[error("no readable keyboard or pointer in /dev/input (missing permissions?)")]
+    NoDevices,
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
 }
 
 #[cfg(target_os = "macos")]
@@ -0,0 +1,696 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    ops::{Range, RangeInclusive},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU32, Ordering},
+    },
+    task::{Context, Poll},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use evdev::{Device, EventStream, EventType, InputEvent, KeyCode, RelativeAxisCode};
+use futures::FutureExt;
+use futures_core::Stream;
+use tokio::{
+    select,
+    sync::{
+        mpsc::{Receiver, Sender, channel},
+        watch,
+    },
+    task::{JoinHandle, spawn_local},
+};
+use tokio_util::sync::CancellationToken;
+
+use input_event::{Event, KeyboardEvent, PointerEvent, scancode};
+
+use super::{
+    Capture, CaptureError, CaptureEvent, Crossing, Position, error::EvdevInputCaptureCreationError,
+    hotkey::HotkeyTracker,
+};
+
+/// mouse buttons, reported as [`PointerEvent::Button`]
+const BUTTONS: RangeInclusive<u16> = KeyCode::BTN_LEFT.0..=KeyCode::BTN_TASK.0;
+
+/// remaining buttons (joystick, digitizer, ...), which are ignored
+const OTHER_BUTTONS: Range<u16> = KeyCode::BTN_0.0..KeyCode::KEY_OK.0;
+
+/// interval in which `/dev/input` is scanned for new devices
+const SCAN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Input capture reading `/dev/input/event*` devices directly,
+/// which works without any support from the compositor (or on a tty).
+///
+/// As there is no display server to ask for the cursor position,
+/// a virtual cursor is moved by the (unaccelerated) relative motion of
+/// all pointer devices within the configured screen size. When it hits
+/// an active edge, all devices are grabbed exclusively (`EVIOCGRAB`)
+/// until the capture is released.
+pub struct EvdevInputCapture {
+    capture_task: JoinHandle<Result<(), CaptureError>>,
+    event_rx: Receiver<(Position, CaptureEvent)>,
+    hotkey_rx: Receiver<(Position, Vec<scancode::Linux>)>,
+    hotkeys_tx: watch::Sender<Vec<(Position, Vec<scancode::Linux>)>>,
+    /// do not grab while a button is held
+    block_drags: Arc<AtomicBool>,
+    /// whether a button was held when the cursor hit the edge
+    buttons_held: Arc<AtomicBool>,
+    /// modifiers held when the cursor hit the edge
+    modifiers_held: Arc<AtomicU32>,
+    request_tx: Sender<Request>,
+    cancellation_token: CancellationToken,
+}
+
+enum Request {
+    Create(Position),
+    Destroy(Position),
+    Release,
+    Capture(Position),
+}
+
+/// events of a single device
+enum DeviceEvent {
+    /// relative pointer motion, accumulated up to a `SYN_REPORT`
+    Motion {
+        time: u32,
+        dx: f64,
+        dy: f64,
+    },
+    Input(Event),
+    /// the device was removed or could not be read anymore
+    Closed(PathBuf),
+}
+
+impl EvdevInputCapture {
+    pub fn new(width: u32, height: u32) -> Result<Self, EvdevInputCaptureCreationError> {
+        Self::with_filter(width, height, is_input_device)
+    }
+
+    /// capture reading only the devices accepted by `filter`
+    fn with_filter(
+        width: u32,
+        height: u32,
+        filter: fn(&Device) -> bool,
+    ) -> Result<Self, EvdevInputCaptureCreationError> {
+        if width == 0 || height == 0 {
+            return Err(EvdevInputCaptureCreationError::ScreenSize(width, height));
+        }
+        let (event_tx, event_rx) = channel(32);
+        let (hotkey_tx, hotkey_rx) = channel(1);
+        let (hotkeys_tx, hotkeys_rx) = watch::channel(vec![]);
+        let block_drags = Arc::new(AtomicBool::new(false));
+        let buttons_held = Arc::new(AtomicBool::new(false));
+        let modifiers_held = Arc::new(AtomicU32::new(0));
+        let (request_tx, request_rx) = channel(32);
+        let (device_tx, device_rx) = channel(32);
+        let (grab_tx, _) = watch::channel(false);
+        let cancellation_token = CancellationToken::new();
+        let size = (width as f64, height as f64);
+        let mut task = CaptureTask {
+            size,
+            cursor: (size.0 / 2., size.1 / 2.),
+            active_positions: Default::default(),
+            grabbed: None,
+            armed: true,
+            pressed_buttons: Default::default(),
+            block_drags: block_drags.clone(),
+            buttons_held: buttons_held.clone(),
+            modifiers_held: modifiers_held.clone(),
+            hotkeys: Default::default(),
+            hotkeys_rx,
+            devices: Default::default(),
+            filter,
+            grab_tx,
+            device_tx,
+            device_rx,
+            event_tx,
+            hotkey_tx,
+            request_rx,
+            cancellation_token: cancellation_token.clone(),
+        };
+        task.scan_devices()?;
+        if task.devices.is_empty() {
+            return Err(EvdevInputCaptureCreationError::NoDevices);
+        }
+        log::info!(
+            "evdev capture: screen size {width}x{height}, {} devices",
+            task.devices.len()
+        );
+        let capture_task = spawn_local(task.run());
+        Ok(Self {
+            capture_task,
+            event_rx,
+            hotkey_rx,
+            hotkeys_tx,
+            block_drags,
+            buttons_held,
+            modifiers_held,
+            request_tx,
+            cancellation_token,
+        })
+    }
+
+    async fn request(&self, request: Request) {
+        let _ = self.request_tx.send(request).await;
+    }
+}
+
+struct CaptureTask {
+    size: (f64, f64),
+    /// virtual cursor position
+    cursor: (f64, f64),
+    active_positions: HashSet<Position>,
+    grabbed: Option<Position>,
+    /// the cursor needs to leave the edge before a capture
+    /// can begin again, otherwise releasing the capture at the
+    /// edge would immediately trigger a new one
+    armed: bool,
+    /// buttons pressed while the devices are not grabbed
+    pressed_buttons: HashSet<u32>,
+    block_drags: Arc<AtomicBool>,
+    buttons_held: Arc<AtomicBool>,
+    modifiers_held: Arc<AtomicU32>,
+    /// hotkeys pressed while the devices are not grabbed
+    hotkeys: HotkeyTracker,
+    hotkeys_rx: watch::Receiver<Vec<(Position, Vec<scancode::Linux>)>>,
+    /// devices currently read from
+    devices: HashSet<PathBuf>,
+    /// devices to read from
+    filter: fn(&Device) -> bool,
+    /// whether devices should be grabbed
+    grab_tx: watch::Sender<bool>,
+    device_tx: Sender<DeviceEvent>,
+    device_rx: Receiver<DeviceEvent>,
+    event_tx: Sender<(Position, CaptureEvent)>,
+    hotkey_tx: Sender<(Position, Vec<scancode::Linux>)>,
+    request_rx: Receiver<Request>,
+    cancellation_token: CancellationToken,
+}
+
+impl CaptureTask {
+    async fn run(mut self) -> Result<(), CaptureError> {
+        let mut scan = tokio::time::interval(SCAN_INTERVAL);
+        loop {
+            select! {
+                _ = self.cancellation_token.cancelled() => break,
+                event = self.device_rx.recv() => {
+                    self.handle_device_event(event.expect("channel closed")).await;
+                }
+                request = self.request_rx.recv() => match request {
+                    Some(request) => self.handle_request(request),
+                    None => break,
+                },
+                _ = scan.tick() => if let Err(e) = self.scan_devices() {
+                    // keep capturing from the devices already opened
+                    log::warn!("evdev capture: failed to scan /dev/input: {e}");
+                },
+                Ok(()) = self.hotkeys_rx.changed() => {
+                    let hotkeys = self.hotkeys_rx.borrow_and_update().clone();
+                    self.hotkeys.set_hotkeys(hotkeys);
+                }
+            }
+        }
+        self.set_grab(None);
+        Ok(())
+    }
+
+    /// open all keyboard and pointer devices not yet read from
+    fn scan_devices(&mut self) -> io::Result<()> {
+        for entry in fs::read_dir("/dev/input")? {
+            let path = match entry {
+                Ok(entry) => entry.path(),
+                Err(e) => {
+                    log::debug!("/dev/input: {e}");
+                    continue;
+                }
+            };
+            let is_event_device = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|n| n.starts_with("event"));
+            if !is_event_device || self.devices.contains(&path) {
+                continue;
+            }
+            let device = match Device::open(&path) {
+                Ok(d) => d,
+                Err(e) => {
+                    log::debug!("{}: {e}", path.display());
+                    continue;
+                }
+            };
+            if !(self.filter)(&device) {
+                continue;
+            }
+            log::info!(
+                "evdev capture: using {} ({})",
+                path.display(),
+                device.name().unwrap_or("unknown")
+            );
+            let stream = match device.into_event_stream() {
+                Ok(s) => s,
+                Err(e) => {
+                    log::warn!("{}: {e}", path.display());
+                    continue;
+                }
+            };
+            let grab_rx = self.grab_tx.subscribe();
+            let device_tx = self.device_tx.clone();
+            let token = self.cancellation_token.clone();
+            spawn_local(read_device(path.clone(), stream, grab_rx, device_tx, token));
+            self.devices.insert(path);
+        }
+        Ok(())
+    }
+
+    fn handle_request(&mut self, request: Request) {
+        match request {
+            Request::Create(pos) => {
+                self.active_positions.insert(pos);
+            }
+            Request::Destroy(pos) => {
+                self.active_positions.remove(&pos);
+                if self.grabbed == Some(pos) {
+                    self.set_grab(None);
+                }
+            }
+            Request::Release => {
+                log::debug!("releasing devices");
+                self.set_grab(None);
+            }
+            Request::Capture(pos) => {
+                log::debug!("grabbing devices for {pos} edge");
+                self.set_grab(Some(pos));
+            }
+        }
+    }
+
+    async fn handle_device_event(&mut self, event: DeviceEvent) {
+        let (pos, event) = match (self.grabbed, event) {
+            (_, DeviceEvent::Closed(path)) => {
+                log::info!("evdev capture: {} removed", path.display());
+                self.devices.remove(&path);
+                return;
+            }
+            (None, DeviceEvent::Motion { dx, dy, .. }) => {
+                let (width, height) = self.size;
+                let (x, y) = self.cursor;
+                self.cursor = (
+                    (x + dx).clamp(0., width - 1.),
+                    (y + dy).clamp(0., height - 1.),
+                );
+                self.check_edge().await;
+                return;
+            }
+            (None, DeviceEvent::Input(Event::Keyboard(KeyboardEvent::Key { key, state, .. }))) => {
+                if let Some((pos, keys)) = self.hotkeys.key(key, state) {
+                    log::debug!("hotkey pressed, grabbing devices for {pos} edge");
+                    self.set_grab(Some(pos));
+                    let _ = self.hotkey_tx.send((pos, keys)).await;
+                }
+                return;
+            }
+            (
+                None,
+                DeviceEvent::Input(Event::Pointer(PointerEvent::Button { button, state, .. })),
+            ) => {
+                match state {
+                    1 => self.pressed_buttons.insert(button),
+                    _ => self.pressed_buttons.remove(&button),
+                };
+                return;
+            }
+            (None, DeviceEvent::Input(_)) => return,
+            (Some(pos), DeviceEvent::Motion { time, dx, dy }) => {
+                (pos, Event::Pointer(PointerEvent::Motion { time, dx, dy }))
+            }
+            (Some(pos), DeviceEvent::Input(event)) => (pos, event),
+        };
+        let _ = self.event_tx.send((pos, CaptureEvent::Input(event))).await;
+    }
+
+    /// the active edge the cursor is currently located at, if any
+    fn edge_at(&self, (x, y): (f64, f64)) -> Option<Position> {
+        let (width, height) = self.size;
+        [
+            (Position::Left, x <= 0.),
+            (Position::Right, x >= width - 1.),
+            (Position::Top, y <= 0.),
+            (Position::Bottom, y >= height - 1.),
+        ]
+        .into_iter()
+        .find(|&(pos, hit)| hit && self.active_positions.contains(&pos))
+        .map(|(pos, _)| pos)
+    }
+
+    async fn check_edge(&mut self) {
+        match self.edge_at(self.cursor) {
+            None => self.armed = true,
+            Some(pos) if self.armed => {
+                log::debug!("cursor hit {pos} edge at {:?}", self.cursor);
+                let buttons_held = !self.pressed_buttons.is_empty();
+                self.buttons_held.store(buttons_held, Ordering::Relaxed);
+                if buttons_held && self.block_drags.load(Ordering::Relaxed) {
+                    // the cursor needs to leave the edge before crossing it
+                    log::debug!("button held, not grabbing the devices");
+                    self.armed = false;
+                    return;
+                }
+                let modifiers = self.hotkeys.modifiers();
+                self.modifiers_held.store(modifiers, Ordering::Relaxed);
+                self.set_grab(Some(pos));
+                let crossing = Crossing::new(pos.along(self.cursor), (0., pos.along(self.size)));
+                let begin = CaptureEvent::Begin(Some(crossing));
+                let _ = self.event_tx.send((pos, begin)).await;
+            }
+            Some(_) => {}
+        }
+    }
+
+    fn set_grab(&mut self, pos: Option<Position>) {
+        if pos.is_some() {
+            self.armed = false;
+            self.hotkeys.reset();
+            // releases of held buttons are sent to the client
+            self.pressed_buttons.clear();
+        }
+        self.grabbed = pos;
+        self.grab_tx.send_replace(pos.is_some());
+    }
+}
+
+/// keyboards and relative pointer devices,
+/// except for the virtual devices created by lan-mouse itself
+fn is_input_device(device: &Device) -> bool {
+    if device.name().is_some_and(|n| n.starts_with("lan-mouse")) {
+        return false;
+    }
+    let is_pointer = device.supported_relative_axes().is_some_and(|axes| {
+        axes.contains(RelativeAxisCode::REL_X) && axes.contains(RelativeAxisCode::REL_Y)
+    });
+    let is_keyboard = device
+        .supported_keys()
+        .is_some_and(|keys| keys.contains(KeyCode::KEY_A) && keys.contains(KeyCode::KEY_ENTER));
+    is_pointer || is_keyboard
+}
+
+/// forward the events of a single device and (un)grab it on request
+async fn read_device(
+    path: PathBuf,
+    mut stream: EventStream,
+    mut grab_rx: watch::Receiver<bool>,
+    device_tx: Sender<DeviceEvent>,
+    cancellation_token: CancellationToken,
+) {
+    let hi_res_scroll = stream
+        .device()
+        .supported_relative_axes()
+        .is_some_and(|axes| {
+            axes.contains(RelativeAxisCode::REL_WHEEL_HI_RES)
+                || axes.contains(RelativeAxisCode::REL_HWHEEL_HI_RES)
+        });
+    let mut motion = (0., 0.);
+    set_grab(&path, &mut stream, *grab_rx.borrow_and_update());
+    loop {
+        select! {
+            _ = cancellation_token.cancelled() => break,
+            changed = grab_rx.changed() => match changed {
+                Ok(()) => set_grab(&path, &mut stream, *grab_rx.borrow_and_update()),
+                Err(_) => break,
+            },
+            event = stream.next_event() => match event {
+                Ok(event) => {
+                    let event = translate_event(event, hi_res_scroll, &mut motion);
+                    if let Some(event) = event {
+                        if device_tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    log::debug!("{}: {e}", path.display());
+                    let _ = device_tx.send(DeviceEvent::Closed(path)).await;
+                    break;
+                }
+            },
+        }
+    }
+}
+
+fn set_grab(path: &Path, stream: &mut EventStream, grab: bool) {
+    let device = stream.device_mut();
+    if device.is_grabbed() == grab {
+        return;
+    }
+    let res = if grab { device.grab() } else { device.ungrab() };
+    if let Err(e) = res {
+        log::warn!("{}: failed to (un)grab device: {e}", path.display());
+    }
+}
+
+fn translate_event(
+    event: InputEvent,
+    hi_res_scroll: bool,
+    motion: &mut (f64, f64),
+) -> Option<DeviceEvent> {
+    let time = event
+        .timestamp()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u32;
+    let (code, value) = (event.code(), event.value());
+    let event = match event.event_type() {
+        EventType::SYNCHRONIZATION => {
+            let (dx, dy) = std::mem::take(motion);
+            if dx == 0. && dy == 0. {
+                return None;
+            }
+            return Some(DeviceEvent::Motion { time, dx, dy });
+        }
+        EventType::RELATIVE => match RelativeAxisCode(code) {
+            RelativeAxisCode::REL_X => {
+                motion.0 += value as f64;
+                return None;
+            }
+            RelativeAxisCode::REL_Y => {
+                motion.1 += value as f64;
+                return None;
+            }
+            // evdev scrolls up for positive values, wayland scrolls down
+            RelativeAxisCode::REL_WHEEL_HI_RES => scroll(0, -value),
+            RelativeAxisCode::REL_HWHEEL_HI_RES => scroll(1, value),
+            RelativeAxisCode::REL_WHEEL if !hi_res_scroll => scroll(0, -value * 120),
+            RelativeAxisCode::REL_HWHEEL if !hi_res_scroll => scroll(1, value * 120),
+            _ => return None,
+        },
+        EventType::KEY if BUTTONS.contains(&code) => Event::Pointer(PointerEvent::Button {
+            time,
+            button: code as u32,
+            state: value as u32,
+        }),
+        EventType::KEY if OTHER_BUTTONS.contains(&code) => return None,
+        // key repeat is handled by the receiving side
+        EventType::KEY if value == 2 => return None,
+        EventType::KEY => Event::Keyboard(KeyboardEvent::Key {
+            time,
+            key: code as u32,
+            state: value as u8,
+        }),
+        _ => return None,
+    };
+    Some(DeviceEvent::Input(event))
+}
+
+fn scroll(axis: u8, value: i32) -> Event {
+    Event::Pointer(PointerEvent::AxisDiscrete120 { axis, value })
+}
+
+#[async_trait]
+impl Capture for EvdevInputCapture {
+    async fn create(&mut self, pos: Position) -> Result<(), CaptureError> {
+        self.request(Request::Create(pos)).await;
+        Ok(())
+    }
+
+    async fn destroy(&mut self, pos: Position) -> Result<(), CaptureError> {
+        self.request(Request::Destroy(pos)).await;
+        Ok(())
+    }
+
+    async fn release(&mut self) -> Result<(), CaptureError> {
+        self.request(Request::Release).await;
+        Ok(())
+    }
+
+    async fn capture(&mut self, pos: Position) -> Result<(), CaptureError> {
+        self.request(Request::Capture(pos)).await;
+        Ok(())
+    }
+
+    fn set_hotkeys(&mut self, hotkeys: Vec<(Position, Vec<scancode::Linux>)>) {
+        self.hotkeys_tx.send_replace(hotkeys);
+    }
+
+    fn set_block_drags(&mut self, block: bool) {
+        self.block_drags.store(block, Ordering::Relaxed);
+    }
+
+    fn buttons_held(&self) -> bool {
+        self.buttons_held.load(Ordering::Relaxed)
+    }
+
+    fn modifiers_held(&self) -> u32 {
+        self.modifiers_held.load(Ordering::Relaxed)
+    }
+
+    fn poll_hotkey(&mut self, cx: &mut Context<'_>) -> Poll<(Position, Vec<scancode::Linux>)> {
+        match self.hotkey_rx.poll_recv(cx) {
+            Poll::Ready(Some(hotkey)) => Poll::Ready(hotkey),
+            // the capture task exited, which ends the stream
+            _ => Poll::Pending,
+        }
+    }
+
+    async fn terminate(&mut self) -> Result<(), CaptureError> {
+        self.cancellation_token.cancel();
+        let task = &mut self.capture_task;
+        if task.is_finished() {
+            return Ok(());
+        }
+        task.await.expect("evdev capture task panic")
+    }
+}
+
+impl Drop for EvdevInputCapture {
+    fn drop(&mut self) {
+        // devices are ungrabbed when closed
+        self.cancellation_token.cancel();
+    }
+}
+
+impl Stream for EvdevInputCapture {
+    type Item = Result<(Position, CaptureEvent), CaptureError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.capture_task.poll_unpin(cx) {
+            Poll::Ready(r) => match r.expect("failed to join") {
+                Ok(()) => Poll::Ready(None),
+                Err(e) => Poll::Ready(Some(Err(e))),
+            },
+            Poll::Pending => self.event_rx.poll_recv(cx).map(|e| e.map(Result::Ok)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use evdev::{AttributeSet, uinput::VirtualDevice};
+    use futures::StreamExt;
+
+    use super::*;
+
+    const NAME: &str = "lan-mouse evdev capture test";
+
+    fn test_device(device: &Device) -> bool {
+        device.name() == Some(NAME)
+    }
+
+    fn pointer() -> io::Result<VirtualDevice> {
+        let mut axes = AttributeSet::<RelativeAxisCode>::new();
+        axes.insert(RelativeAxisCode::REL_X);
+        axes.insert(RelativeAxisCode::REL_Y);
+        let mut buttons = AttributeSet::<KeyCode>::new();
+        buttons.insert(KeyCode::BTN_LEFT);
+        VirtualDevice::builder()?
+            .name(NAME)
+            .with_relative_axes(&axes)?
+            .with_keys(&buttons)?
+            .build()
+    }
+
+    async fn next(capture: &mut EvdevInputCapture) -> (Position, CaptureEvent) {
+        let event = tokio::time::timeout(Duration::from_secs(5), capture.next());
+        event
+            .await
+            .expect("timeout")
+            .expect("stream")
+            .expect("event")
+    }
+
+    /// feeds a virtual pointer (uinput) to the capture,
+    /// skipped if `/dev/uinput` is not accessible
+    #[tokio::test]
+    async fn capture_uinput_pointer() {
+        let mut pointer = match pointer() {
+            Ok(pointer) => pointer,
+            Err(e) => {
+                eprintln!("skipping evdev capture test, no uinput device: {e}");
+                return;
+            }
+        };
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                // the device node shows up asynchronously
+                let mut capture = None;
+                for _ in 0..50 {
+                    match EvdevInputCapture::with_filter(100, 100, test_device) {
+                        Ok(c) => {
+                            capture = Some(c);
+                            break;
+                        }
+                        Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+                    }
+                }
+                let mut capture = capture.expect("virtual device not found");
+                capture.create(Position::Left).await.unwrap();
+
+                let syn = InputEvent::new(EventType::SYNCHRONIZATION.0, 0, 0);
+                let motion =
+                    InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, -100);
+                let press = InputEvent::new(EventType::KEY.0, KeyCode::BTN_LEFT.0, 1);
+                let release = InputEvent::new(EventType::KEY.0, KeyCode::BTN_LEFT.0, 0);
+
+                // the devices are not grabbed while dragging
+                capture.set_block_drags(true);
+                pointer.emit(&[press, syn]).unwrap();
+                // the virtual cursor starts at the center of the screen
+                pointer.emit(&[motion, syn]).unwrap();
+                let event = tokio::time::timeout(Duration::from_millis(300), capture.next());
+                assert!(event.await.is_err(), "captured while dragging");
+                // leave the edge and hit it again
+                let back = InputEvent::new(EventType::RELATIVE.0, RelativeAxisCode::REL_X.0, 10);
+                pointer
+                    .emit(&[release, syn, back, syn, motion, syn])
+                    .unwrap();
+                let (pos, event) = next(&mut capture).await;
+                assert_eq!(pos, Position::Left);
+                let crossing = Crossing::new(50., (0., 100.));
+                assert_eq!(event, CaptureEvent::Begin(Some(crossing)));
+
+                pointer.emit(&[press, syn, release, syn]).unwrap();
+                for state in [1, 0] {
+                    match next(&mut capture).await {
+                        (
+                            Position::Left,
+                            CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
+                                button,
+                                state: s,
+                                ..
+                            })),
+                        ) => {
+                            assert_eq!(button, KeyCode::BTN_LEFT.0 as u32);
+                            assert_eq!(s, state);
+                        }
+                        e => panic!("unexpected event {e:?}"),
+                    }
+                }
+
+                capture.release().await.unwrap();
+                capture.terminate().await.unwrap();
+            })
+            .await;
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::HashSet;
+
+use input_event::scancode;
+
+use super::{Modifier, Position};
+
+/// Detects hotkeys among the keys pressed while no capture is active.
+///
+/// A hotkey only triggers once all of its keys are released again.
+/// Grabbing the devices while the keys are still held would otherwise
+/// keep them pressed on this device, as the release events would be
+/// captured.
+#[derive(Default)]
+#[cfg_attr(not(any(x11, evdev)), allow(dead_code))]
+pub(crate) struct HotkeyTracker {
+    hotkeys: Vec<(Position, Vec<scancode::Linux>)>,
+    pressed: HashSet<scancode::Linux>,
+    /// index of the hotkey waiting for its keys to be released
+    triggered: Option<usize>,
+}
+
+#[cfg_attr(not(any(x11, evdev)), allow(dead_code))]
+impl HotkeyTracker {
+    pub(crate) fn set_hotkeys(&mut self, hotkeys: Vec<(Position, Vec<scancode::Linux>)>) {
+        self.hotkeys = hotkeys;
+        self.triggered = None;
+    }
+
+    /// (xkb) modifier mask of the pressed keys
+    #[cfg_attr(not(evdev), allow(dead_code))]
+    pub(crate) fn modifiers(&self) -> u32 {
+        self.pressed
+            .iter()
+            .filter_map(|&k| Modifier::of_key(k as u32))
+            .fold(0, |mask, m| mask | m.mask())
+    }
+
+    /// forget the pressed keys, e.g. when a capture begins
+    pub(crate) fn reset(&mut self) {
+        self.pressed.clear();
+        self.triggered = None;
+    }
+
+    /// update the pressed keys and return the position and keys of
+    /// the hotkey that was pressed and is now released, if any
+    pub(crate) fn key(&mut self, key: u32, state: u8) -> Option<(Position, Vec<scancode::Linux>)> {
+        let key = scancode::Linux::try_from(key).ok()?;
+        if state == 0 {
+            self.pressed.remove(&key);
+            if !self.pressed.is_empty() {
+                return None;
+            }
+            let idx = self.triggered.take()?;
+            return self.hotkeys.get(idx).cloned();
+        }
+        self.pressed.insert(key);
+        if let Some(idx) = self.hotkeys.iter().position(|(_, keys)| {
+            keys.contains(&key) && keys.iter().all(|k| self.pressed.contains(k))
+        }) {
+            self.triggered = Some(idx);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use scancode::Linux::{KeyA, KeyB, KeyLeftCtrl};
+
+    #[test]
+    fn triggers_on_release() {
+        let mut tracker = HotkeyTracker::default();
+        let hotkey = (Position::Left, vec![KeyLeftCtrl, KeyA]);
+        tracker.set_hotkeys(vec![(Position::Right, vec![KeyB]), hotkey.clone()]);
+        assert_eq!(tracker.key(KeyLeftCtrl as u32, 1), None);
+        assert_eq!(tracker.key(KeyA as u32, 1), None);
+        assert_eq!(tracker.key(KeyA as u32, 0), None);
+        assert_eq!(tracker.key(KeyLeftCtrl as u32, 0), Some(hotkey));
+        // other keys released without a hotkey
+        assert_eq!(tracker.key(KeyA as u32, 1), None);
+        assert_eq!(tracker.key(KeyA as u32, 0), None);
+    }
+
+    #[test]
+    fn changed_hotkeys_cancel_a_trigger() {
+        let mut tracker = HotkeyTracker::default();
+        tracker.set_hotkeys(vec![(Position::Left, vec![KeyA])]);
+        assert_eq!(tracker.key(KeyA as u32, 1), None);
+        tracker.set_hotkeys(vec![(Position::Right, vec![KeyB])]);
+        assert_eq!(tracker.key(KeyA as u32, 0), None);
+    }
+}
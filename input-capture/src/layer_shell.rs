@@ -66,7 +66,7 @@ use input_event::{Event, KeyboardEvent, PointerEvent};
 use crate::{CaptureError, CaptureEvent};
 
 use super::{
-    Capture, Position,
+    Capture, Crossing, Position,
     error::{LayerShellCaptureCreationError, WaylandBindError},
 };
 
@@ -149,6 +149,8 @@ struct Window {
     surface: WlSurface,
     layer_surface: ZwlrLayerSurfaceV1,
     pos: Position,
+    /// global position of the output
+    origin: (i32, i32),
 }
 
 impl Window {
@@ -157,6 +159,7 @@ impl Window {
         qh: &QueueHandle<State>,
         output: &WlOutput,
         pos: Position,
+        origin: (i32, i32),
         size: (i32, i32),
     ) -> Window {
         log::debug!("creating window output: {output:?}, size: {size:?}");
@@ -205,6 +208,7 @@ impl Window {
         surface.commit();
         Window {
             pos,
+            origin,
             buffer,
             surface,
             layer_surface,
@@ -254,6 +258,19 @@ fn get_output_configuration(state: &State, pos: Position) -> Vec<Output> {
         .collect()
 }
 
+/// start and end of the edge at `pos` spanned by all outputs bordering on it
+fn get_edge_extent(state: &State, pos: Position) -> Option<(f64, f64)> {
+    get_output_configuration(state, pos)
+        .iter()
+        .filter_map(|o| o.info.as_ref())
+        .map(|info| {
+            let start = pos.along(info.position);
+            (start, start + pos.along(info.size))
+        })
+        .reduce(|(s1, e1), (s2, e2)| (s1.min(s2), e1.max(e2)))
+        .map(|(start, end)| (start as f64, end as f64))
+}
+
 fn draw(f: &mut File, (width, height): (u32, u32)) {
     let mut buf = BufWriter::new(f);
     for _ in 0..height {
@@ -525,7 +542,8 @@ impl State {
         );
         outputs.iter().for_each(|o| {
             if let Some(info) = o.info.as_ref() {
-                let window = Window::new(self, &self.qh, &o.wl_output, pos, info.size);
+                let window =
+                    Window::new(self, &self.qh, &o.wl_output, pos, info.position, info.size);
                 let window = Arc::new(window);
                 self.active_windows.push(window);
             }
@@ -635,6 +653,15 @@ impl Capture for LayerShellInputCapture {
         Ok(inner.flush_events()?)
     }
 
+    async fn capture(&mut self, _pos: Position) -> Result<(), CaptureError> {
+        Err(CaptureError::DirectCaptureUnsupported)
+    }
+
+    fn set_block_drags(&mut self, _block: bool) {
+        // the compositor keeps the pointer on the dragged surface,
+        // so the edge surfaces are never entered while dragging
+    }
+
     async fn terminate(&mut self) -> Result<(), CaptureError> {
         Ok(())
     }
@@ -735,8 +762,8 @@ impl Dispatch<WlPointer, ()> for State {
             wl_pointer::Event::Enter {
                 serial,
                 surface,
-                surface_x: _,
-                surface_y: _,
+                surface_x,
+                surface_y,
             } => {
                 // get client corresponding to the focused surface
                 {
@@ -747,13 +774,16 @@ impl Dispatch<WlPointer, ()> for State {
                         return;
                     }
                 }
-                let pos = app
+                let (pos, origin) = app
                     .active_windows
                     .iter()
                     .find(|w| w.surface == surface)
-                    .map(|w| w.pos)
+                    .map(|w| (w.pos, w.origin))
                     .unwrap();
-                app.pending_events.push_back((pos, CaptureEvent::Begin));
+                let location = pos.along(origin) as f64 + pos.along((surface_x, surface_y));
+                let crossing = get_edge_extent(app, pos).map(|edge| Crossing::new(location, edge));
+                app.pending_events
+                    .push_back((pos, CaptureEvent::Begin(crossing)));
             }
             wl_pointer::Event::Leave { .. } => {
                 /* There are rare cases, where when a window is opened in
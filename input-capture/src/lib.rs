@@ -1,18 +1,21 @@
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     fmt::Display,
-    mem::swap,
-    task::{Poll, ready},
+    task::{Context, Poll, ready},
 };
 
 use async_trait::async_trait;
 use futures::StreamExt;
 use futures_core::Stream;
 
-use input_event::{Event, KeyboardEvent, scancode};
+use input_event::{Event, KeyboardEvent, PointerEvent, scancode};
 
-pub use error::{CaptureCreationError, CaptureError, InputCaptureError};
+pub use dummy::{DummySource, Injector};
+pub use edge_guard::{EdgeGuard, GuardedCrossing, Verdict};
+pub use error::{CaptureCreationError, CaptureError, InputCaptureError, ScriptError};
+pub use input_event::Modifier;
 
+mod edge_guard;
 pub mod error;
 
 #[cfg(libei)]
@@ -30,15 +33,21 @@ mod windows;
 #[cfg(x11)]
 mod x11;
 
-/// fallback input capture (does not produce events)
+#[cfg(evdev)]
+mod evdev;
+
+/// input capture for demos and tests
 mod dummy;
 
+mod hotkey;
+
 pub type CaptureHandle = u64;
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum CaptureEvent {
-    /// capture on this capture handle is now active
-    Begin,
+    /// capture on this capture handle is now active,
+    /// includes the location of the crossing, if known
+    Begin(Option<Crossing>),
     /// input event coming from capture handle
     Input(Event),
 }
@@ -46,12 +55,70 @@ pub enum CaptureEvent {
 impl Display for CaptureEvent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            CaptureEvent::Begin => write!(f, "begin capture"),
+            CaptureEvent::Begin(_) => write!(f, "begin capture"),
             CaptureEvent::Input(e) => write!(f, "{e}"),
         }
     }
 }
 
+/// Location along an edge at which the pointer crossed into a capture.
+/// An edge spans all outputs bordering on it and offsets are measured from
+/// its start, i.e. its top end (left / right) or its left end (top / bottom).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Crossing {
+    /// distance from the start of the edge in pixels
+    pub offset: f64,
+    /// length of the edge in pixels
+    pub length: f64,
+}
+
+impl Crossing {
+    /// crossing at `location` of the edge spanning `start..end`,
+    /// both given in global coordinates along the edge
+    #[cfg(any(libei, layer_shell, x11, evdev, windows, target_os = "macos", test))]
+    pub(crate) fn new(location: f64, (start, end): (f64, f64)) -> Self {
+        Self {
+            offset: location - start,
+            length: end - start,
+        }
+    }
+
+    /// position of the crossing relative to the length of the edge (`0.0..=1.0`)
+    pub fn fraction(&self) -> f64 {
+        if self.length > 0. {
+            (self.offset / self.length).clamp(0., 1.)
+        } else {
+            0.5
+        }
+    }
+}
+
+/// Part of an edge claimed by a capture handle,
+/// measured in pixels like [`Crossing::offset`]
+#[derive(Copy, Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Segment {
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl Segment {
+    /// whether the segment contains the given crossing
+    pub fn contains(&self, crossing: &Crossing) -> bool {
+        let start = self.offset as f64;
+        let end = start + self.length as f64;
+        (start..end).contains(&crossing.offset)
+    }
+
+    /// position of the crossing relative to the segment (`0.0..=1.0`)
+    pub fn fraction(&self, crossing: &Crossing) -> f64 {
+        Crossing {
+            offset: crossing.offset - self.offset as f64,
+            length: self.length as f64,
+        }
+        .fraction()
+    }
+}
+
 #[derive(Debug, Clone, Copy, Eq, Hash, PartialEq)]
 pub enum Position {
     Left,
@@ -69,6 +136,15 @@ impl Position {
             Position::Bottom => Self::Top,
         }
     }
+
+    /// the coordinate of `(x, y)` along an edge at this position
+    #[cfg(any(libei, layer_shell, x11, evdev, windows, target_os = "macos", test))]
+    pub(crate) fn along<T>(&self, (x, y): (T, T)) -> T {
+        match self {
+            Position::Left | Position::Right => y,
+            Position::Top | Position::Bottom => x,
+        }
+    }
 }
 
 impl Display for Position {
@@ -83,7 +159,7 @@ impl Display for Position {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Backend {
     #[cfg(libei)]
     InputCapturePortal,
@@ -91,11 +167,17 @@ pub enum Backend {
     LayerShell,
     #[cfg(x11)]
     X11,
+    /// evdev devices, tracking the cursor on a screen of the given size
+    #[cfg(evdev)]
+    Evdev {
+        width: u32,
+        height: u32,
+    },
     #[cfg(windows)]
     Windows,
     #[cfg(target_os = "macos")]
     MacOs,
-    Dummy,
+    Dummy(DummySource),
 }
 
 impl Display for Backend {
@@ -107,11 +189,13 @@ impl Display for Backend {
             Backend::LayerShell => write!(f, "layer-shell"),
             #[cfg(x11)]
             Backend::X11 => write!(f, "X11"),
+            #[cfg(evdev)]
+            Backend::Evdev { .. } => write!(f, "evdev"),
             #[cfg(windows)]
             Backend::Windows => write!(f, "windows"),
             #[cfg(target_os = "macos")]
             Backend::MacOs => write!(f, "MacOS"),
-            Backend::Dummy => write!(f, "dummy"),
+            Backend::Dummy(_) => write!(f, "dummy"),
         }
     }
 }
@@ -121,20 +205,37 @@ pub struct InputCapture {
     capture: Box<dyn Capture>,
     /// keys pressed by active capture
     pressed_keys: HashSet<scancode::Linux>,
+    /// mouse buttons pressed by active capture
+    pressed_buttons: HashSet<u32>,
     /// map from position to ids
     position_map: HashMap<Position, Vec<CaptureHandle>>,
-    /// map from id to position
-    id_map: HashMap<CaptureHandle, Position>,
+    /// map from id to position and claimed segment of the edge
+    id_map: HashMap<CaptureHandle, (Position, Option<Segment>)>,
+    /// position of the last [`CaptureEvent::Begin`] and
+    /// the ids selected as its targets
+    active: Option<(Position, Vec<CaptureHandle>)>,
+    /// whether input is currently captured
+    captured: bool,
+    /// whether the capture began without crossing an edge
+    direct: bool,
+    /// key chords capturing input for an id directly
+    hotkeys: Vec<(CaptureHandle, Vec<scancode::Linux>)>,
     /// pending events
     pending: VecDeque<(CaptureHandle, CaptureEvent)>,
 }
 
 impl InputCapture {
-    /// create a new client with the given id
-    pub async fn create(&mut self, id: CaptureHandle, pos: Position) -> Result<(), CaptureError> {
+    /// create a new client with the given id, claiming the given
+    /// segment of the edge at `pos` or the whole edge if `segment` is `None`
+    pub async fn create(
+        &mut self,
+        id: CaptureHandle,
+        pos: Position,
+        segment: Option<Segment>,
+    ) -> Result<(), CaptureError> {
         assert!(!self.id_map.contains_key(&id));
 
-        self.id_map.insert(id, pos);
+        self.id_map.insert(id, (pos, segment));
 
         if let Some(v) = self.position_map.get_mut(&pos) {
             v.push(id);
@@ -147,10 +248,17 @@ impl InputCapture {
 
     /// destroy the client with the given id, if it exists
     pub async fn destroy(&mut self, id: CaptureHandle) -> Result<(), CaptureError> {
-        let pos = self
+        let (pos, _) = self
             .id_map
             .remove(&id)
             .expect("no position for this handle");
+        if let Some((_, targets)) = self.active.as_mut() {
+            targets.retain(|&i| i != id);
+        }
+        if self.hotkeys.iter().any(|&(i, _)| i == id) {
+            self.hotkeys.retain(|&(i, _)| i != id);
+            self.update_hotkeys();
+        }
 
         log::debug!("destroying capture {id} @ {pos}");
         let remaining = self.position_map.get_mut(&pos).expect("id vector");
@@ -160,6 +268,9 @@ impl InputCapture {
         if remaining.is_empty() {
             log::debug!("destroying capture @ {pos} - no remaining ids");
             self.position_map.remove(&pos);
+            if matches!(self.active, Some((p, _)) if p == pos) {
+                self.captured = false;
+            }
             self.capture.destroy(pos).await?;
         }
         Ok(())
@@ -168,9 +279,65 @@ impl InputCapture {
     /// release mouse
     pub async fn release(&mut self) -> Result<(), CaptureError> {
         self.pressed_keys.clear();
+        self.pressed_buttons.clear();
+        self.captured = false;
         self.capture.release().await
     }
 
+    /// Capture input for the given id without the pointer crossing its
+    /// edge, emitting a [`CaptureEvent::Begin`] for it.
+    /// If input is already captured, events are redirected to the id.
+    pub async fn capture(&mut self, id: CaptureHandle) -> Result<(), CaptureError> {
+        let &(pos, _) = self.id_map.get(&id).expect("no position for this handle");
+        let pos = match &self.active {
+            Some((p, _)) if self.captured => *p,
+            _ => {
+                self.capture.capture(pos).await?;
+                pos
+            }
+        };
+        self.active.replace((pos, vec![id]));
+        self.captured = true;
+        self.direct = true;
+        self.pending.push_back((id, CaptureEvent::Begin(None)));
+        Ok(())
+    }
+
+    /// set the key chord capturing input for the given id directly.
+    /// While no input is captured, hotkeys are only detected by backends
+    /// that can observe the keyboard, i.e. X11 and evdev.
+    pub fn set_hotkey(&mut self, id: CaptureHandle, hotkey: Option<Vec<scancode::Linux>>) {
+        assert!(self.id_map.contains_key(&id));
+        self.hotkeys.retain(|&(i, _)| i != id);
+        if let Some(keys) = hotkey.filter(|k| !k.is_empty()) {
+            self.hotkeys.push((id, keys));
+        }
+        self.update_hotkeys();
+    }
+
+    /// whether the active capture began by the pointer crossing an edge,
+    /// rather than through a hotkey or [`InputCapture::capture`]
+    pub fn crossed_edge(&self) -> bool {
+        !self.direct
+    }
+
+    /// the id whose hotkey is currently pressed in the active capture, if any
+    pub fn pressed_hotkey(&self) -> Option<CaptureHandle> {
+        self.hotkeys
+            .iter()
+            .find(|(_, keys)| self.keys_pressed(keys))
+            .map(|&(id, _)| id)
+    }
+
+    fn update_hotkeys(&mut self) {
+        let hotkeys = self
+            .hotkeys
+            .iter()
+            .map(|(id, keys)| (self.id_map[id].0, keys.clone()))
+            .collect();
+        self.capture.set_hotkeys(hotkeys);
+    }
+
     /// Drain and return every key the capture has forwarded as
     /// down-but-not-up. The caller is expected to synthesize key-up
     /// events to the remote peer for each — otherwise the peer
@@ -184,6 +351,31 @@ impl InputCapture {
         std::mem::take(&mut self.pressed_keys)
     }
 
+    /// Drain and return every mouse button the capture has forwarded
+    /// as down-but-not-up, like [`InputCapture::take_pressed_keys`].
+    pub fn take_pressed_buttons(&mut self) -> HashSet<u32> {
+        std::mem::take(&mut self.pressed_buttons)
+    }
+
+    /// Do not begin a capture while a mouse button is held, e.g. when
+    /// dragging a window. Backends that can not tell whether a button
+    /// is held (libei) log a warning and ignore it.
+    pub fn set_block_drags(&mut self, block: bool) {
+        self.capture.set_block_drags(block);
+    }
+
+    /// whether a mouse button is held, either in the active capture or,
+    /// if the backend can tell, locally when the capture began
+    pub fn buttons_held(&self) -> bool {
+        !self.pressed_buttons.is_empty() || self.capture.buttons_held()
+    }
+
+    /// (xkb) modifier mask held locally when the capture began,
+    /// if the backend can tell
+    pub fn modifiers_held(&self) -> u32 {
+        self.capture.modifiers_held()
+    }
+
     /// destroy the input capture
     pub async fn terminate(&mut self) -> Result<(), CaptureError> {
         self.capture.terminate().await
@@ -193,11 +385,16 @@ impl InputCapture {
     pub async fn new(backend: Option<Backend>) -> Result<Self, CaptureCreationError> {
         let capture = create(backend).await?;
         Ok(Self {
+            active: None,
             capture,
+            captured: false,
+            direct: false,
+            hotkeys: Default::default(),
             id_map: Default::default(),
             pending: Default::default(),
             position_map: Default::default(),
             pressed_keys: HashSet::new(),
+            pressed_buttons: HashSet::new(),
         })
     }
 
@@ -206,6 +403,48 @@ impl InputCapture {
         keys.iter().all(|k| self.pressed_keys.contains(k))
     }
 
+    /// ids at the given position, whose segment contains the crossing.
+    /// Falls back to all ids at this position, if there are none.
+    fn targets(&self, pos: Position, crossing: Option<Crossing>) -> Vec<CaptureHandle> {
+        let ids = self.position_map.get(&pos).cloned().unwrap_or_default();
+        let Some(crossing) = crossing else {
+            return ids;
+        };
+        let targets = ids
+            .iter()
+            .copied()
+            .filter(|id| match self.id_map.get(id) {
+                Some((_, Some(segment))) => segment.contains(&crossing),
+                _ => true,
+            })
+            .collect::<Vec<_>>();
+        if targets.is_empty() { ids } else { targets }
+    }
+
+    /// a hotkey detected by the backend began a capture
+    fn poll_hotkey(&mut self, cx: &mut Context<'_>) -> Poll<Option<<Self as Stream>::Item>> {
+        let (id, pos) = loop {
+            let (pos, keys) = ready!(self.capture.poll_hotkey(cx));
+            // the hotkeys may have changed in the meantime
+            let id = self
+                .hotkeys
+                .iter()
+                .filter(|(_, k)| *k == keys)
+                .map(|&(id, _)| id)
+                .find(|id| self.id_map.get(id).is_some_and(|&(p, _)| p == pos));
+            if let Some(id) = id {
+                break (id, pos);
+            }
+        };
+        log::debug!("hotkey of {id} pressed");
+        self.pressed_keys.clear();
+        self.pressed_buttons.clear();
+        self.active.replace((pos, vec![id]));
+        self.captured = true;
+        self.direct = true;
+        Poll::Ready(Some(Ok((id, CaptureEvent::Begin(None)))))
+    }
+
     fn update_pressed_keys(&mut self, key: u32, state: u8) {
         if let Ok(scancode) = scancode::Linux::try_from(key) {
             log::debug!("key: {key}, state: {state}, scancode: {scancode:?}");
@@ -228,8 +467,11 @@ impl Stream for InputCapture {
             return Poll::Ready(Some(Ok(e)));
         }
 
-        // ready
-        let event = ready!(self.capture.poll_next_unpin(cx));
+        let event = match self.capture.poll_next_unpin(cx) {
+            Poll::Ready(e) => e,
+            // backends detect hotkeys while dispatching their events
+            Poll::Pending => return self.poll_hotkey(cx),
+        };
 
         // stream closed
         let event = match event {
@@ -247,30 +489,41 @@ impl Stream for InputCapture {
         if let CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key { key, state, .. })) = event {
             self.update_pressed_keys(key, state);
         }
+        if let CaptureEvent::Input(Event::Pointer(PointerEvent::Button { button, state, .. })) =
+            event
+        {
+            match state {
+                1 => self.pressed_buttons.insert(button),
+                _ => self.pressed_buttons.remove(&button),
+            };
+        }
+
+        // select the clients whose segment of the edge was crossed
+        if let CaptureEvent::Begin(crossing) = event {
+            let targets = self.targets(pos, crossing);
+            self.active.replace((pos, targets));
+            self.captured = true;
+            self.direct = false;
+        }
 
-        let len = self
-            .position_map
-            .get(&pos)
-            .map(|ids| ids.len())
-            .unwrap_or(0);
+        let this = &mut *self;
+        let ids = match &this.active {
+            Some((p, targets)) if *p == pos => targets.as_slice(),
+            _ => this
+                .position_map
+                .get(&pos)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+        };
 
-        match len {
+        match ids.len() {
             0 => Poll::Pending,
-            1 => Poll::Ready(Some(Ok((
-                self.position_map.get(&pos).expect("no id")[0],
-                event,
-            )))),
+            1 => Poll::Ready(Some(Ok((ids[0], event)))),
             _ => {
-                let mut position_map = HashMap::new();
-                swap(&mut self.position_map, &mut position_map);
-                {
-                    for &id in position_map.get(&pos).expect("position") {
-                        self.pending.push_back((id, event));
-                    }
+                for &id in ids {
+                    this.pending.push_back((id, event));
                 }
-                swap(&mut self.position_map, &mut position_map);
-
-                Poll::Ready(Some(Ok(self.pending.pop_front().expect("event"))))
+                Poll::Ready(Some(Ok(this.pending.pop_front().expect("event"))))
             }
         }
     }
@@ -287,6 +540,47 @@ trait Capture: Stream<Item = Result<(Position, CaptureEvent), CaptureError>> + U
     /// release mouse
     async fn release(&mut self) -> Result<(), CaptureError>;
 
+    /// Capture input at the given position without the pointer
+    /// crossing the edge. No [`CaptureEvent::Begin`] is emitted.
+    /// Backends that can not grab input on their own, i.e. layer-shell
+    /// and libei, return [`CaptureError::DirectCaptureUnsupported`].
+    async fn capture(&mut self, pos: Position) -> Result<(), CaptureError>;
+
+    /// Key chords that begin a capture at the given position while
+    /// no input is captured. Backends that can not observe the keyboard
+    /// in this case ignore them.
+    fn set_hotkeys(&mut self, _hotkeys: Vec<(Position, Vec<scancode::Linux>)>) {}
+
+    /// Do not grab input or emit a [`CaptureEvent::Begin`] while a mouse
+    /// button is held. Backends that can not tell whether a button is held
+    /// ignore it.
+    fn set_block_drags(&mut self, block: bool) {
+        if block {
+            log::warn!(
+                "the capture backend can not tell whether a mouse button is held, \
+                 `block_drags` has no effect"
+            );
+        }
+    }
+
+    /// whether a mouse button was held locally when the last capture began.
+    /// Always `false` for backends that can not tell.
+    fn buttons_held(&self) -> bool {
+        false
+    }
+
+    /// (xkb) modifier mask held locally when the last capture began.
+    /// Always `0` for backends that can not tell, these report
+    /// the modifiers through events once the capture began.
+    fn modifiers_held(&self) -> u32 {
+        0
+    }
+
+    /// position and keys of a hotkey that began a capture
+    fn poll_hotkey(&mut self, _cx: &mut Context<'_>) -> Poll<(Position, Vec<scancode::Linux>)> {
+        Poll::Pending
+    }
+
     /// destroy the input capture
     async fn terminate(&mut self) -> Result<(), CaptureError>;
 }
@@ -304,11 +598,15 @@ async fn create_backend(
         Backend::LayerShell => Ok(Box::new(layer_shell::LayerShellInputCapture::new()?)),
         #[cfg(x11)]
         Backend::X11 => Ok(Box::new(x11::X11InputCapture::new()?)),
+        #[cfg(evdev)]
+        Backend::Evdev { width, height } => {
+            Ok(Box::new(evdev::EvdevInputCapture::new(width, height)?))
+        }
         #[cfg(windows)]
         Backend::Windows => Ok(Box::new(windows::WindowsInputCapture::new())),
         #[cfg(target_os = "macos")]
         Backend::MacOs => Ok(Box::new(macos::MacOSInputCapture::new().await?)),
-        Backend::Dummy => Ok(Box::new(dummy::DummyInputCapture::new())),
+        Backend::Dummy(source) => Ok(Box::new(dummy::DummyInputCapture::new(source).await?)),
     }
 }
 
@@ -319,7 +617,7 @@ async fn create(
     CaptureCreationError,
 > {
     if let Some(backend) = backend {
-        let b = create_backend(backend).await;
+        let b = create_backend(backend.clone()).await;
         if b.is_ok() {
             log::info!("using capture backend: {backend}");
         }
@@ -338,7 +636,7 @@ async fn create(
         #[cfg(target_os = "macos")]
         Backend::MacOs,
     ] {
-        match create_backend(backend).await {
+        match create_backend(backend.clone()).await {
             Ok(b) => {
                 log::info!("using capture backend: {backend}");
                 return Ok(b);
@@ -349,3 +647,87 @@ async fn create(
     }
     Err(CaptureCreationError::NoAvailableBackend)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(offset: u32, length: u32) -> Option<Segment> {
+        Some(Segment { offset, length })
+    }
+
+    fn crossing(offset: f64) -> Option<Crossing> {
+        Some(Crossing::new(offset, (0., 1000.)))
+    }
+
+    #[test]
+    fn crossing_relative_to_edge() {
+        let crossing = Crossing::new(1500., (1000., 2000.));
+        assert_eq!(crossing.offset, 500.);
+        assert_eq!(crossing.length, 1000.);
+        assert_eq!(crossing.fraction(), 0.5);
+        assert_eq!(Crossing::new(0., (0., 0.)).fraction(), 0.5);
+        assert_eq!(Position::Left.along((1, 2)), 2);
+        assert_eq!(Position::Top.along((1, 2)), 1);
+    }
+
+    #[test]
+    fn segment_contains() {
+        let segment = Segment {
+            offset: 100,
+            length: 50,
+        };
+        assert!(!segment.contains(&Crossing::new(99.9, (0., 1000.))));
+        assert!(segment.contains(&Crossing::new(100., (0., 1000.))));
+        assert!(segment.contains(&Crossing::new(149.9, (0., 1000.))));
+        // segments are half-open, so adjacent segments do not overlap
+        assert!(!segment.contains(&Crossing::new(150., (0., 1000.))));
+    }
+
+    #[test]
+    fn segment_fraction() {
+        let segment = Segment {
+            offset: 100,
+            length: 50,
+        };
+        assert_eq!(segment.fraction(&Crossing::new(100., (0., 1000.))), 0.);
+        assert_eq!(segment.fraction(&Crossing::new(125., (0., 1000.))), 0.5);
+        assert_eq!(segment.fraction(&Crossing::new(500., (0., 1000.))), 1.);
+    }
+
+    #[tokio::test]
+    async fn targets() {
+        let source = DummySource::Injector(Injector::default());
+        let mut capture = InputCapture::new(Some(Backend::Dummy(source)))
+            .await
+            .expect("dummy capture");
+        capture
+            .create(0, Position::Left, segment(0, 500))
+            .await
+            .unwrap();
+        capture
+            .create(1, Position::Left, segment(500, 500))
+            .await
+            .unwrap();
+        capture.create(2, Position::Right, None).await.unwrap();
+        capture
+            .create(3, Position::Right, segment(0, 100))
+            .await
+            .unwrap();
+
+        // the segment containing the crossing
+        assert_eq!(capture.targets(Position::Left, crossing(10.)), [0]);
+        assert_eq!(capture.targets(Position::Left, crossing(500.)), [1]);
+        // all ids at the position, if the crossing is unknown
+        assert_eq!(capture.targets(Position::Left, None), [0, 1]);
+        // or no segment contains it
+        assert_eq!(capture.targets(Position::Left, crossing(1000.)), [0, 1]);
+        // ids claiming the whole edge are always targeted
+        assert_eq!(capture.targets(Position::Right, crossing(50.)), [2, 3]);
+        assert_eq!(capture.targets(Position::Right, crossing(500.)), [2]);
+        assert!(capture.targets(Position::Top, crossing(0.)).is_empty());
+
+        capture.destroy(0).await.unwrap();
+        assert_eq!(capture.targets(Position::Left, crossing(10.)), [1]);
+    }
+}
@@ -42,7 +42,7 @@ use input_event::Event;
 use crate::CaptureEvent;
 
 use super::{
-    Capture as LanMouseInputCapture, Position,
+    Capture as LanMouseInputCapture, Crossing, Position,
     error::{CaptureError, LibeiCaptureCreationError},
 };
 
@@ -425,7 +425,8 @@ async fn do_capture_session(
                     current_pos.replace(Some(pos));
 
                     // client entered => send event
-                    event_tx.send((pos, CaptureEvent::Begin)).await.expect("no channel");
+                    let crossing = activated.cursor_position().map(|p| crossing(&barriers, &pos_for_barrier_id, pos, p));
+                    event_tx.send((pos, CaptureEvent::Begin(crossing))).await.expect("no channel");
 
                     tokio::select! {
                         _ = notify_release.notified() => { /* capture release */
@@ -522,6 +523,26 @@ fn find_corresponding_client(barriers: &[ICBarrier], pos: (f32, f32)) -> Barrier
         .barrier_id
 }
 
+/// location along the edge (spanned by all barriers at `pos`) at which it was crossed
+fn crossing(
+    barriers: &[ICBarrier],
+    pos_for_barrier_id: &HashMap<BarrierID, Position>,
+    pos: Position,
+    (x, y): (f32, f32),
+) -> Crossing {
+    let (start, end) = barriers
+        .iter()
+        .filter(|b| pos_for_barrier_id.get(&b.barrier_id) == Some(&pos))
+        .map(|b| {
+            let (x1, y1, x2, y2) = b.position;
+            (pos.along((x1, y1)), pos.along((x2, y2)) + 1)
+        })
+        .fold((i32::MAX, i32::MIN), |(s, e), (bs, be)| {
+            (s.min(bs), e.max(be))
+        });
+    Crossing::new(pos.along((x, y)) as f64, (start as f64, end as f64))
+}
+
 fn distance_to_line(line: ((f32, f32), (f32, f32)), p: (f32, f32)) -> f32 {
     let ((x1, y1), (x2, y2)) = line;
     let (x0, y0) = p;
@@ -599,6 +620,10 @@ impl LanMouseInputCapture for LibeiInputCapture {
         Ok(())
     }
 
+    async fn capture(&mut self, _pos: Position) -> Result<(), CaptureError> {
+        Err(CaptureError::DirectCaptureUnsupported)
+    }
+
     async fn terminate(&mut self) -> Result<(), CaptureError> {
         self.cancellation_token.cancel();
         let task = &mut self.capture_task;
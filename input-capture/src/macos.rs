@@ -1,4 +1,6 @@
-use super::{Capture, CaptureError, CaptureEvent, Position, error::MacosCaptureCreationError};
+use super::{
+    Capture, CaptureError, CaptureEvent, Crossing, Position, error::MacosCaptureCreationError,
+};
 use async_trait::async_trait;
 use bitflags::bitflags;
 use core_foundation::{
@@ -28,7 +30,10 @@ use std::{
     collections::HashSet,
     ffi::{CString, c_char},
     pin::Pin,
-    sync::{Arc, OnceLock},
+    sync::{
+        Arc, OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
     task::{Context, Poll, ready},
     thread::{self},
 };
@@ -58,6 +63,16 @@ struct InputCaptureState {
     bounds: Bounds,
     /// current state of modifier keys
     modifier_state: XMods,
+    /// shared with [`MacOSInputCapture`]
+    drags: Arc<Drags>,
+}
+
+#[derive(Debug, Default)]
+struct Drags {
+    /// do not capture while a button is held
+    block: AtomicBool,
+    /// whether a button was held when the last capture began
+    buttons_held: AtomicBool,
 }
 
 #[derive(Debug)]
@@ -78,11 +93,26 @@ impl InputCaptureState {
             enter_position: None,
             bounds: Bounds::default(),
             modifier_state: Default::default(),
+            drags: Default::default(),
         };
         res.update_bounds()?;
         Ok(res)
     }
 
+    /// whether a button is held while crossing a barrier
+    /// and crossings are blocked in this case
+    fn drag_blocked(&self) -> bool {
+        let buttons_held = buttons_held();
+        self.drags
+            .buttons_held
+            .store(buttons_held, Ordering::Relaxed);
+        let blocked = buttons_held && self.drags.block.load(Ordering::Relaxed);
+        if blocked {
+            log::debug!("button held, not capturing");
+        }
+        blocked
+    }
+
     fn crossed(&mut self, event: &CGEvent) -> Option<Position> {
         let location = event.location();
         let relative_x = event.get_double_value_field(EventField::MOUSE_EVENT_DELTA_X);
@@ -101,6 +131,16 @@ impl InputCaptureState {
         None
     }
 
+    /// location along the edge at which the barrier was crossed
+    fn crossing(&self, event: &CGEvent, position: Position) -> Crossing {
+        let location = event.location();
+        let edge = match position {
+            Position::Left | Position::Right => (self.bounds.ymin, self.bounds.ymax),
+            Position::Top | Position::Bottom => (self.bounds.xmin, self.bounds.xmax),
+        };
+        Crossing::new(position.along((location.x, location.y)), edge)
+    }
+
     // Get the max bounds of all displays
     fn update_bounds(&mut self) -> Result<(), MacosCaptureCreationError> {
         let active_ids =
@@ -517,12 +557,14 @@ fn create_event_tap<'a>(
             }
         } else if matches!(event_type, CGEventType::MouseMoved) {
             // Did we cross a barrier?
-            if let Some(new_pos) = state.crossed(cg_ev) {
+            let crossed = state.crossed(cg_ev).filter(|_| !state.drag_blocked());
+            if let Some(new_pos) = crossed {
                 capture_position = Some(new_pos);
+                let crossing = state.crossing(cg_ev, new_pos);
                 state
                     .start_capture(cg_ev, new_pos)
                     .unwrap_or_else(|e| log::warn!("{e}"));
-                res_events.push(CaptureEvent::Begin);
+                res_events.push(CaptureEvent::Begin(Some(crossing)));
                 notify_tx
                     .blocking_send(ProducerEvent::Grab(new_pos))
                     .expect("Failed to send notification");
@@ -650,6 +692,7 @@ extern "C" fn display_reconfiguration_callback(_display: u32, flags: u32, user_i
 }
 
 pub struct MacOSInputCapture {
+    drags: Arc<Drags>,
     event_rx: Receiver<(Position, CaptureEvent)>,
     notify_tx: Sender<ProducerEvent>,
     run_loop: CFRunLoop,
@@ -659,7 +702,9 @@ impl MacOSInputCapture {
     pub async fn new() -> Result<Self, MacosCaptureCreationError> {
         request_macos_capture_permissions()?;
 
-        let state = Arc::new(Mutex::new(InputCaptureState::new()?));
+        let state = InputCaptureState::new()?;
+        let drags = state.drags.clone();
+        let state = Arc::new(Mutex::new(state));
         let (event_tx, event_rx) = mpsc::channel(32);
         let (notify_tx, mut notify_rx) = mpsc::channel(32);
         let (ready_tx, ready_rx) = std::sync::mpsc::channel();
@@ -705,6 +750,7 @@ impl MacOSInputCapture {
         });
 
         Ok(Self {
+            drags,
             event_rx,
             notify_tx,
             run_loop,
@@ -781,6 +827,18 @@ impl Capture for MacOSInputCapture {
         Ok(())
     }
 
+    async fn capture(&mut self, _pos: Position) -> Result<(), CaptureError> {
+        Err(CaptureError::DirectCaptureUnsupported)
+    }
+
+    fn set_block_drags(&mut self, block: bool) {
+        self.drags.block.store(block, Ordering::Relaxed);
+    }
+
+    fn buttons_held(&self) -> bool {
+        self.drags.buttons_held.load(Ordering::Relaxed)
+    }
+
     async fn terminate(&mut self) -> Result<(), CaptureError> {
         Ok(())
     }
@@ -816,6 +874,7 @@ extern "C" {
         seconds: CFTimeInterval,
     );
     fn CGPreflightListenEventAccess() -> bool;
+    fn CGEventSourceButtonState(state_id: CGEventSourceStateID, button: u32) -> bool;
     /// Re-enable an event tap that was disabled by a
     /// `kCGEventTapDisabledByTimeout` event. The Apple-documented
     /// recovery path: see Quartz Event Services Reference. The `tap`
@@ -871,6 +930,14 @@ unsafe fn configure_cf_settings() -> Result<(), MacosCaptureCreationError> {
     Ok(())
 }
 
+/// whether a mouse button is currently held
+fn buttons_held() -> bool {
+    // left, right, center and the side buttons
+    (0..5).any(|button| unsafe {
+        CGEventSourceButtonState(CGEventSourceStateID::CombinedSessionState, button)
+    })
+}
+
 // From X11/X.h
 bitflags! {
     #[repr(C)]
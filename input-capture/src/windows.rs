@@ -34,6 +34,18 @@ impl Capture for WindowsInputCapture {
         Ok(())
     }
 
+    async fn capture(&mut self, _pos: Position) -> Result<(), CaptureError> {
+        Err(CaptureError::DirectCaptureUnsupported)
+    }
+
+    fn set_block_drags(&mut self, block: bool) {
+        self.event_thread.set_block_drags(block);
+    }
+
+    fn buttons_held(&self) -> bool {
+        self.event_thread.buttons_held()
+    }
+
     async fn terminate(&mut self) -> Result<(), CaptureError> {
         Ok(())
     }
@@ -1,6 +1,6 @@
 use windows::Win32::Foundation::RECT;
 
-use crate::Position;
+use crate::{Crossing, Position};
 
 fn is_within_dp_region(point: (i32, i32), display: &RECT) -> bool {
     [
@@ -97,3 +97,18 @@ pub(crate) fn clamp_to_display_bounds(
     let (min_y, max_y) = (display.top, display.bottom - 1);
     (x.clamp(min_x, max_x), y.clamp(min_y, max_y))
 }
+
+/// location along the edge at `pos` (spanning all displays) at which `point` crossed it
+pub(crate) fn crossing(displays: &[RECT], pos: Position, point: (i32, i32)) -> Crossing {
+    let (start, end) = match pos {
+        Position::Left | Position::Right => (
+            displays.iter().map(|d| d.top).min().unwrap_or_default(),
+            displays.iter().map(|d| d.bottom).max().unwrap_or_default(),
+        ),
+        Position::Top | Position::Bottom => (
+            displays.iter().map(|d| d.left).min().unwrap_or_default(),
+            displays.iter().map(|d| d.right).max().unwrap_or_default(),
+        ),
+    };
+    Crossing::new(pos.along(point) as f64, (start as f64, end as f64))
+}
@@ -15,6 +15,9 @@ use windows::Win32::Graphics::Gdi::{
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
 use windows::Win32::System::Threading::GetCurrentThreadId;
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetAsyncKeyState, VK_LBUTTON, VK_MBUTTON, VK_RBUTTON, VK_XBUTTON1, VK_XBUTTON2,
+};
 use windows::core::{PCWSTR, w};
 
 use windows::Win32::UI::WindowsAndMessaging::{
@@ -62,6 +65,14 @@ impl EventThread {
         self.client_update(ClientUpdate::Destroy(pos));
     }
 
+    pub(crate) fn set_block_drags(&self, block: bool) {
+        BLOCK_DRAGS.store(block, Ordering::Relaxed);
+    }
+
+    pub(crate) fn buttons_held(&self) -> bool {
+        BUTTONS_HELD.load(Ordering::Relaxed)
+    }
+
     fn exit(&self) {
         self.signal(RequestType::Exit);
     }
@@ -289,6 +300,14 @@ fn check_client_activation(wparam: WPARAM, lparam: LPARAM) -> bool {
         return ret;
     }
 
+    /* do not enter while dragging */
+    let buttons_held = buttons_held();
+    BUTTONS_HELD.store(buttons_held, Ordering::Relaxed);
+    if buttons_held && BLOCK_DRAGS.load(Ordering::Relaxed) {
+        log::debug!("button held, not entering {pos}");
+        return ret;
+    }
+
     /* update active client and entry point */
     ACTIVE_CLIENT.replace(Some(pos));
     let entry_point = DISPLAYS.with_borrow(|(displays, _)| {
@@ -299,7 +318,9 @@ fn check_client_activation(wparam: WPARAM, lparam: LPARAM) -> bool {
     /* notify main thread */
     log::debug!("ENTERED @ {prev_pos:?} -> {curr_pos:?}");
     let active = ACTIVE_CLIENT.get().expect("active client");
-    blocking_send_event(active, CaptureEvent::Begin);
+    let crossing =
+        DISPLAYS.with_borrow(|(displays, _)| display_util::crossing(displays, active, entry_point));
+    blocking_send_event(active, CaptureEvent::Begin(Some(crossing)));
 
     ret
 }
@@ -365,6 +386,19 @@ unsafe extern "system" fn window_proc(
 
 static DISPLAY_RESOLUTION_GENERATION: AtomicI32 = AtomicI32::new(1);
 
+/// do not enter a client while a mouse button is held
+static BLOCK_DRAGS: AtomicBool = AtomicBool::new(false);
+/// whether a mouse button was held when the last client was entered
+static BUTTONS_HELD: AtomicBool = AtomicBool::new(false);
+
+/// whether a mouse button is currently held
+fn buttons_held() -> bool {
+    [VK_LBUTTON, VK_RBUTTON, VK_MBUTTON, VK_XBUTTON1, VK_XBUTTON2]
+        .into_iter()
+        // the most significant bit is set while the button is down
+        .any(|vk| unsafe { GetAsyncKeyState(vk.0 as i32) } < 0)
+}
+
 fn update_display_regions(displays: &mut Vec<RECT>, generation: &mut i32) {
     let global_generation = DISPLAY_RESOLUTION_GENERATION.load(Ordering::Acquire);
     if *generation != global_generation {
@@ -1,33 +1,599 @@
-use std::task::Poll;
+use std::{
+    collections::{HashSet, VecDeque},
+    ffi::CStr,
+    mem::MaybeUninit,
+    os::{
+        fd::{AsRawFd, RawFd},
+        raw::{c_int, c_uchar, c_uint},
+    },
+    pin::Pin,
+    ptr,
+    task::{Context, Poll, ready},
+};
 
 use async_trait::async_trait;
 use futures_core::Stream;
+use tokio::io::unix::AsyncFd;
+use x11::{
+    xinput2::{self, XIAllMasterDevices, XIEventMask, XIGrabModeAsync, XIGrabSuccess, XIRawEvent},
+    xlib,
+};
 
-use super::{Capture, CaptureError, CaptureEvent, Position, error::X11InputCaptureCreationError};
+use input_event::{
+    BTN_BACK, BTN_FORWARD, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, Event, KeyboardEvent, PointerEvent,
+    scancode,
+};
 
-pub struct X11InputCapture {}
+use super::{
+    Capture, CaptureError, CaptureEvent, Crossing, Position, error::X11InputCaptureCreationError,
+    hotkey::HotkeyTracker,
+};
+
+/// X11 input capture based on XInput2.
+///
+/// Raw motion events are selected on the root window to track when
+/// the pointer hits one of the configured screen edges. At that point
+/// the master pointer and keyboard are grabbed and raw (unaccelerated)
+/// events are forwarded until the capture is released.
+pub struct X11InputCapture(AsyncFd<Inner>);
+
+struct Inner {
+    display: *mut xlib::Display,
+    root: xlib::Window,
+    /// major opcode of the XInputExtension
+    xi_opcode: c_int,
+    /// master pointer device
+    pointer: c_int,
+    /// master keyboard paired with `pointer`
+    keyboard: c_int,
+    /// invisible cursor shown while the pointer is grabbed
+    blank_cursor: xlib::Cursor,
+    /// size of the root window
+    size: (i32, i32),
+    active_positions: HashSet<Position>,
+    /// position of the active capture and the location the
+    /// pointer is held at while grabbed
+    grabbed: Option<(Position, (i32, i32))>,
+    /// the pointer needs to leave the edge before a capture
+    /// can begin again, otherwise releasing the capture at the
+    /// edge would immediately trigger a new one
+    armed: bool,
+    /// do not grab while a button is held
+    block_drags: bool,
+    /// whether a button was held when the pointer hit the edge
+    buttons_held: bool,
+    /// modifiers held when the pointer hit the edge
+    modifiers_held: u32,
+    /// hotkeys pressed while not grabbed
+    hotkeys: HotkeyTracker,
+    pending_events: VecDeque<(Position, CaptureEvent)>,
+    /// hotkeys that began a capture
+    pending_hotkeys: VecDeque<(Position, Vec<scancode::Linux>)>,
+}
+
+unsafe impl Send for Inner {}
+
+impl AsRawFd for Inner {
+    fn as_raw_fd(&self) -> RawFd {
+        unsafe { xlib::XConnectionNumber(self.display) }
+    }
+}
 
 impl X11InputCapture {
     pub fn new() -> std::result::Result<Self, X11InputCaptureCreationError> {
-        Err(X11InputCaptureCreationError::NotImplemented)
+        Self::with_display(None)
     }
+
+    /// capture on the given display instead of `$DISPLAY`
+    fn with_display(display: Option<&CStr>) -> Result<Self, X11InputCaptureCreationError> {
+        let inner = Inner::new(display)?;
+        Ok(Self(AsyncFd::new(inner)?))
+    }
+}
+
+impl Inner {
+    fn new(display: Option<&CStr>) -> Result<Self, X11InputCaptureCreationError> {
+        let display = unsafe { xlib::XOpenDisplay(display.map_or(ptr::null(), CStr::as_ptr)) };
+        if display.is_null() {
+            return Err(X11InputCaptureCreationError::OpenDisplay);
+        }
+
+        // the display is closed when `inner` is dropped on one of the error paths below
+        let mut inner = Self {
+            display,
+            root: unsafe { xlib::XDefaultRootWindow(display) },
+            xi_opcode: 0,
+            pointer: 0,
+            keyboard: 0,
+            blank_cursor: 0,
+            size: (0, 0),
+            active_positions: Default::default(),
+            grabbed: None,
+            armed: true,
+            block_drags: false,
+            buttons_held: false,
+            modifiers_held: 0,
+            hotkeys: Default::default(),
+            pending_events: Default::default(),
+            pending_hotkeys: Default::default(),
+        };
+
+        inner.xi_opcode = inner.query_xinput2()?;
+        (inner.pointer, inner.keyboard) = inner.query_master_devices()?;
+        inner.blank_cursor = inner.create_blank_cursor();
+        inner.size = inner.root_size();
+        inner.select_events();
+
+        log::info!(
+            "X11 capture: screen size {}x{}, pointer {}, keyboard {}",
+            inner.size.0,
+            inner.size.1,
+            inner.pointer,
+            inner.keyboard
+        );
+
+        Ok(inner)
+    }
+
+    /// check for XInput >= 2.2 and return its major opcode
+    fn query_xinput2(&self) -> Result<c_int, X11InputCaptureCreationError> {
+        let (mut opcode, mut event, mut error) = (0, 0, 0);
+        let name = c"XInputExtension";
+        let present = unsafe {
+            xlib::XQueryExtension(
+                self.display,
+                name.as_ptr(),
+                &mut opcode,
+                &mut event,
+                &mut error,
+            )
+        };
+        if present == 0 {
+            return Err(X11InputCaptureCreationError::XInputMissing);
+        }
+        let (mut major, mut minor) = (2, 2);
+        let status = unsafe { xinput2::XIQueryVersion(self.display, &mut major, &mut minor) };
+        if status != xlib::Success as c_int {
+            return Err(X11InputCaptureCreationError::XInputVersion(major, minor));
+        }
+        Ok(opcode)
+    }
+
+    /// determine the client pointer and its paired master keyboard
+    fn query_master_devices(&self) -> Result<(c_int, c_int), X11InputCaptureCreationError> {
+        let mut pointer = 0;
+        if unsafe { xinput2::XIGetClientPointer(self.display, 0, &mut pointer) } == 0 {
+            return Err(X11InputCaptureCreationError::NoMasterDevice);
+        }
+        let mut n = 0;
+        let info = unsafe { xinput2::XIQueryDevice(self.display, pointer, &mut n) };
+        if info.is_null() || n < 1 {
+            return Err(X11InputCaptureCreationError::NoMasterDevice);
+        }
+        let keyboard = unsafe { (*info).attachment };
+        unsafe { xinput2::XIFreeDeviceInfo(info) };
+        Ok((pointer, keyboard))
+    }
+
+    fn create_blank_cursor(&self) -> xlib::Cursor {
+        let data = [0 as std::os::raw::c_char; 1];
+        unsafe {
+            let pixmap = xlib::XCreateBitmapFromData(self.display, self.root, data.as_ptr(), 1, 1);
+            let mut color = MaybeUninit::<xlib::XColor>::zeroed().assume_init();
+            let cursor = xlib::XCreatePixmapCursor(
+                self.display,
+                pixmap,
+                pixmap,
+                &mut color,
+                &mut color,
+                0,
+                0,
+            );
+            xlib::XFreePixmap(self.display, pixmap);
+            cursor
+        }
+    }
+
+    fn root_size(&self) -> (i32, i32) {
+        let mut attrs = MaybeUninit::<xlib::XWindowAttributes>::uninit();
+        unsafe {
+            xlib::XGetWindowAttributes(self.display, self.root, attrs.as_mut_ptr());
+            let attrs = attrs.assume_init();
+            (attrs.width, attrs.height)
+        }
+    }
+
+    /// Select raw input events on the root window. Raw events are
+    /// delivered regardless of which window has focus and, since
+    /// XI 2.1, also while a device is grabbed.
+    fn select_events(&self) {
+        let mut mask = [0 as c_uchar; 4];
+        for event in [
+            xinput2::XI_RawMotion,
+            xinput2::XI_RawButtonPress,
+            xinput2::XI_RawButtonRelease,
+            xinput2::XI_RawKeyPress,
+            xinput2::XI_RawKeyRelease,
+        ] {
+            xinput2::XISetMask(&mut mask, event);
+        }
+        let mut event_mask = XIEventMask {
+            deviceid: XIAllMasterDevices,
+            mask_len: mask.len() as c_int,
+            mask: mask.as_mut_ptr(),
+        };
+        unsafe {
+            xinput2::XISelectEvents(self.display, self.root, &mut event_mask, 1);
+            // screen size changes (xrandr)
+            xlib::XSelectInput(self.display, self.root, xlib::StructureNotifyMask);
+            xlib::XFlush(self.display);
+        }
+    }
+
+    fn pointer_position(&self) -> (i32, i32) {
+        self.query_pointer().0
+    }
+
+    /// position of the pointer and the state of the modifiers and buttons
+    fn query_pointer(&self) -> ((i32, i32), c_uint) {
+        let (mut root, mut child) = (0, 0);
+        let (mut root_x, mut root_y, mut win_x, mut win_y) = (0, 0, 0, 0);
+        let mut mask: c_uint = 0;
+        unsafe {
+            xlib::XQueryPointer(
+                self.display,
+                self.root,
+                &mut root,
+                &mut child,
+                &mut root_x,
+                &mut root_y,
+                &mut win_x,
+                &mut win_y,
+                &mut mask,
+            );
+        }
+        ((root_x, root_y), mask)
+    }
+
+    fn grab(&mut self, pos: Position, at: (i32, i32)) -> bool {
+        let mut mask = [0 as c_uchar; 4];
+        let mut event_mask = XIEventMask {
+            deviceid: self.pointer,
+            mask_len: mask.len() as c_int,
+            mask: mask.as_mut_ptr(),
+        };
+        let status = unsafe {
+            xinput2::XIGrabDevice(
+                self.display,
+                self.pointer,
+                self.root,
+                xlib::CurrentTime,
+                self.blank_cursor,
+                XIGrabModeAsync,
+                XIGrabModeAsync,
+                xlib::False,
+                &mut event_mask,
+            )
+        };
+        if status != XIGrabSuccess {
+            log::warn!("failed to grab pointer: {status}");
+            return false;
+        }
+        event_mask.deviceid = self.keyboard;
+        let status = unsafe {
+            xinput2::XIGrabDevice(
+                self.display,
+                self.keyboard,
+                self.root,
+                xlib::CurrentTime,
+                0,
+                XIGrabModeAsync,
+                XIGrabModeAsync,
+                xlib::False,
+                &mut event_mask,
+            )
+        };
+        if status != XIGrabSuccess {
+            log::warn!("failed to grab keyboard: {status}");
+            unsafe { xinput2::XIUngrabDevice(self.display, self.pointer, xlib::CurrentTime) };
+            return false;
+        }
+        unsafe { xlib::XFlush(self.display) };
+        self.grabbed = Some((pos, at));
+        self.armed = false;
+        self.hotkeys.reset();
+        true
+    }
+
+    fn ungrab(&mut self) {
+        if self.grabbed.take().is_none() {
+            return;
+        }
+        unsafe {
+            xinput2::XIUngrabDevice(self.display, self.keyboard, xlib::CurrentTime);
+            xinput2::XIUngrabDevice(self.display, self.pointer, xlib::CurrentTime);
+            xlib::XFlush(self.display);
+        }
+    }
+
+    /// keep the (invisible) pointer at the location where it hit the
+    /// edge, so it reappears there when the capture is released
+    fn warp_back(&self, (x, y): (i32, i32)) {
+        unsafe {
+            xlib::XWarpPointer(self.display, 0, self.root, 0, 0, 0, 0, x, y);
+        }
+    }
+
+    fn dispatch_events(&mut self) {
+        while unsafe { xlib::XPending(self.display) } > 0 {
+            let mut event = MaybeUninit::<xlib::XEvent>::uninit();
+            let mut event = unsafe {
+                xlib::XNextEvent(self.display, event.as_mut_ptr());
+                event.assume_init()
+            };
+            match event.get_type() {
+                xlib::ConfigureNotify => {
+                    let configure = unsafe { event.configure };
+                    if configure.window == self.root {
+                        self.size = (configure.width, configure.height);
+                        log::info!("X11 capture: screen size changed to {:?}", self.size);
+                    }
+                }
+                xlib::GenericEvent => {
+                    let cookie = unsafe { &mut event.generic_event_cookie };
+                    if cookie.extension != self.xi_opcode
+                        || unsafe { xlib::XGetEventData(self.display, cookie) } == 0
+                    {
+                        continue;
+                    }
+                    let raw = unsafe { &*(cookie.data as *const XIRawEvent) };
+                    self.handle_raw_event(cookie.evtype, raw);
+                    unsafe { xlib::XFreeEventData(self.display, cookie) };
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn handle_raw_event(&mut self, evtype: c_int, raw: &XIRawEvent) {
+        let Some((pos, at)) = self.grabbed else {
+            match evtype {
+                xinput2::XI_RawMotion => self.check_edge(),
+                xinput2::XI_RawKeyPress | xinput2::XI_RawKeyRelease => {
+                    let pressed = evtype == xinput2::XI_RawKeyPress;
+                    self.check_hotkey(raw.detail, pressed);
+                }
+                _ => {}
+            }
+            return;
+        };
+        let time = raw.time as u32;
+        let event = match evtype {
+            xinput2::XI_RawMotion => {
+                let (dx, dy) = raw_motion(raw);
+                if dx == 0. && dy == 0. {
+                    return;
+                }
+                self.warp_back(at);
+                Event::Pointer(PointerEvent::Motion { time, dx, dy })
+            }
+            xinput2::XI_RawButtonPress | xinput2::XI_RawButtonRelease => {
+                let pressed = evtype == xinput2::XI_RawButtonPress;
+                match to_pointer_event(time, raw.detail, pressed) {
+                    Some(e) => Event::Pointer(e),
+                    None => return,
+                }
+            }
+            xinput2::XI_RawKeyPress | xinput2::XI_RawKeyRelease => {
+                // xorg keycodes are shifted by 8
+                let Some(key) = (raw.detail as u32).checked_sub(8) else {
+                    return;
+                };
+                let state = (evtype == xinput2::XI_RawKeyPress) as u8;
+                Event::Keyboard(KeyboardEvent::Key { time, key, state })
+            }
+            _ => return,
+        };
+        self.pending_events
+            .push_back((pos, CaptureEvent::Input(event)));
+    }
+
+    /// The key is still delivered to the focused window,
+    /// as the keyboard is only grabbed once a hotkey was pressed.
+    fn check_hotkey(&mut self, keycode: c_int, pressed: bool) {
+        // xorg keycodes are shifted by 8
+        let Some(key) = (keycode as u32).checked_sub(8) else {
+            return;
+        };
+        if let Some((pos, keys)) = self.hotkeys.key(key, pressed as u8) {
+            log::debug!("hotkey pressed, grabbing pointer for {pos} edge");
+            if self.grab(pos, self.pointer_position()) {
+                self.pending_hotkeys.push_back((pos, keys));
+            }
+        }
+    }
+
+    fn check_edge(&mut self) {
+        let (location, mask) = self.query_pointer();
+        match edge_at(self.size, &self.active_positions, location) {
+            None => self.armed = true,
+            Some(pos) if self.armed => {
+                log::debug!("pointer hit {pos} edge at {location:?}");
+                self.buttons_held = mask & BUTTON_MASK != 0;
+                if self.block_drags && self.buttons_held {
+                    // grabbing the pointer would break the grab of the dragged window,
+                    // the pointer needs to leave the edge before crossing it
+                    log::debug!("button held, not grabbing the pointer");
+                    self.armed = false;
+                    return;
+                }
+                if self.grab(pos, location) {
+                    // the core modifier bits match the xkb modifier mask
+                    self.modifiers_held = mask & MODIFIER_MASK;
+                    let (width, height) = (self.size.0 as f64, self.size.1 as f64);
+                    let location = (location.0 as f64, location.1 as f64);
+                    let crossing =
+                        Crossing::new(pos.along(location), (0., pos.along((width, height))));
+                    self.pending_events
+                        .push_back((pos, CaptureEvent::Begin(Some(crossing))));
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.ungrab();
+        unsafe {
+            if self.blank_cursor != 0 {
+                xlib::XFreeCursor(self.display, self.blank_cursor);
+            }
+            xlib::XCloseDisplay(self.display);
+        }
+    }
+}
+
+/// the active edge of a screen of the given size the pointer is located at, if any
+fn edge_at(
+    (width, height): (i32, i32),
+    active_positions: &HashSet<Position>,
+    (x, y): (i32, i32),
+) -> Option<Position> {
+    [
+        (Position::Left, x <= 0),
+        (Position::Right, x >= width - 1),
+        (Position::Top, y <= 0),
+        (Position::Bottom, y >= height - 1),
+    ]
+    .into_iter()
+    .find(|&(pos, hit)| hit && active_positions.contains(&pos))
+    .map(|(pos, _)| pos)
+}
+
+/// accumulate the relative x / y motion of a raw motion event
+fn raw_motion(raw: &XIRawEvent) -> (f64, f64) {
+    let mask =
+        unsafe { std::slice::from_raw_parts(raw.valuators.mask, raw.valuators.mask_len as usize) };
+    let mut values = raw.raw_values;
+    let (mut dx, mut dy) = (0., 0.);
+    // raw_values only contains entries for valuators set in the mask
+    for i in 0..(mask.len() * 8) as c_int {
+        if !xinput2::XIMaskIsSet(mask, i) {
+            continue;
+        }
+        let value = unsafe { *values };
+        values = unsafe { values.add(1) };
+        match i {
+            0 => dx = value,
+            1 => dy = value,
+            _ => {}
+        }
+    }
+    (dx, dy)
+}
+
+/// left, middle and right button in the state of the pointer
+/// (4 and 5 are scroll "buttons")
+const BUTTON_MASK: c_uint = xlib::Button1Mask | xlib::Button2Mask | xlib::Button3Mask;
+
+/// shift, control, alt (mod1) and super (mod4) in the state of the pointer
+const MODIFIER_MASK: c_uint = xlib::ShiftMask | xlib::ControlMask | xlib::Mod1Mask | xlib::Mod4Mask;
+
+const SCROLL_UP: c_int = 4;
+const SCROLL_DOWN: c_int = 5;
+const SCROLL_LEFT: c_int = 6;
+const SCROLL_RIGHT: c_int = 7;
+
+fn to_pointer_event(time: u32, button: c_int, pressed: bool) -> Option<PointerEvent> {
+    let (axis, value) = match button {
+        SCROLL_UP => (0, -120),
+        SCROLL_DOWN => (0, 120),
+        SCROLL_LEFT => (1, -120),
+        SCROLL_RIGHT => (1, 120),
+        _ => {
+            let button = match button {
+                1 => BTN_LEFT,
+                2 => BTN_MIDDLE,
+                3 => BTN_RIGHT,
+                8 => BTN_BACK,
+                9 => BTN_FORWARD,
+                _ => return None,
+            };
+            let state = pressed as u32;
+            return Some(PointerEvent::Button {
+                time,
+                button,
+                state,
+            });
+        }
+    };
+    // scroll "buttons" send a press and release per tick
+    pressed.then_some(PointerEvent::AxisDiscrete120 { axis, value })
 }
 
 #[async_trait]
 impl Capture for X11InputCapture {
-    async fn create(&mut self, _pos: Position) -> Result<(), CaptureError> {
+    async fn create(&mut self, pos: Position) -> Result<(), CaptureError> {
+        self.0.get_mut().active_positions.insert(pos);
         Ok(())
     }
 
-    async fn destroy(&mut self, _pos: Position) -> Result<(), CaptureError> {
+    async fn destroy(&mut self, pos: Position) -> Result<(), CaptureError> {
+        let inner = self.0.get_mut();
+        inner.active_positions.remove(&pos);
+        if matches!(inner.grabbed, Some((p, _)) if p == pos) {
+            inner.ungrab();
+        }
         Ok(())
     }
 
     async fn release(&mut self) -> Result<(), CaptureError> {
+        log::debug!("releasing pointer");
+        self.0.get_mut().ungrab();
+        Ok(())
+    }
+
+    async fn capture(&mut self, pos: Position) -> Result<(), CaptureError> {
+        let inner = self.0.get_mut();
+        match inner.grabbed {
+            Some((_, at)) => inner.grabbed = Some((pos, at)),
+            None => {
+                if !inner.grab(pos, inner.pointer_position()) {
+                    return Err(CaptureError::Grab);
+                }
+            }
+        }
         Ok(())
     }
 
+    fn set_hotkeys(&mut self, hotkeys: Vec<(Position, Vec<scancode::Linux>)>) {
+        self.0.get_mut().hotkeys.set_hotkeys(hotkeys);
+    }
+
+    fn set_block_drags(&mut self, block: bool) {
+        self.0.get_mut().block_drags = block;
+    }
+
+    fn buttons_held(&self) -> bool {
+        self.0.get_ref().buttons_held
+    }
+
+    fn modifiers_held(&self) -> u32 {
+        self.0.get_ref().modifiers_held
+    }
+
+    fn poll_hotkey(&mut self, _cx: &mut Context<'_>) -> Poll<(Position, Vec<scancode::Linux>)> {
+        // hotkeys are only detected while dispatching events in `poll_next`
+        match self.0.get_mut().pending_hotkeys.pop_front() {
+            Some(hotkey) => Poll::Ready(hotkey),
+            None => Poll::Pending,
+        }
+    }
+
     async fn terminate(&mut self) -> Result<(), CaptureError> {
+        self.0.get_mut().ungrab();
         Ok(())
     }
 }
@@ -35,10 +601,233 @@ impl Capture for X11InputCapture {
 impl Stream for X11InputCapture {
     type Item = Result<(Position, CaptureEvent), CaptureError>;
 
-    fn poll_next(
-        self: std::pin::Pin<&mut Self>,
-        _cx: &mut std::task::Context<'_>,
-    ) -> std::task::Poll<Option<Self::Item>> {
-        Poll::Pending
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            // Xlib may have already read events into its queue as a side
+            // effect of a round trip, so drain it before waiting on the fd
+            let inner = self.0.get_mut();
+            inner.dispatch_events();
+            if let Some(event) = inner.pending_events.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            let mut guard = ready!(self.0.poll_read_ready_mut(cx))?;
+            guard.clear_ready();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        ffi::CString,
+        path::Path,
+        process::{Child, Command, Stdio},
+        time::Duration,
+    };
+
+    use futures::StreamExt;
+
+    use super::*;
+
+    #[test]
+    fn buttons() {
+        let button = |button, pressed| to_pointer_event(7, button, pressed);
+        for (x11, evdev) in [
+            (1, BTN_LEFT),
+            (2, BTN_MIDDLE),
+            (3, BTN_RIGHT),
+            (8, BTN_BACK),
+            (9, BTN_FORWARD),
+        ] {
+            for pressed in [true, false] {
+                let state = pressed as u32;
+                assert_eq!(
+                    button(x11, pressed),
+                    Some(PointerEvent::Button {
+                        time: 7,
+                        button: evdev,
+                        state
+                    })
+                );
+            }
+        }
+        assert_eq!(button(10, true), None);
+        assert_eq!(button(0, false), None);
+    }
+
+    #[test]
+    fn scroll_buttons() {
+        for (button, axis, value) in [(4, 0, -120), (5, 0, 120), (6, 1, -120), (7, 1, 120)] {
+            assert_eq!(
+                to_pointer_event(0, button, true),
+                Some(PointerEvent::AxisDiscrete120 { axis, value })
+            );
+            // only the press of a scroll "button" is a tick
+            assert_eq!(to_pointer_event(0, button, false), None);
+        }
+    }
+
+    #[test]
+    fn motion_valuators() {
+        let motion = |mut mask: Vec<u8>, mut values: Vec<f64>| {
+            let mut raw: XIRawEvent = unsafe { std::mem::zeroed() };
+            raw.valuators.mask_len = mask.len() as c_int;
+            raw.valuators.mask = mask.as_mut_ptr();
+            raw.raw_values = values.as_mut_ptr();
+            raw_motion(&raw)
+        };
+        assert_eq!(motion(vec![0b11], vec![-1.5, 2.5]), (-1.5, 2.5));
+        // only valuators set in the mask have a value
+        assert_eq!(motion(vec![0b10], vec![4.]), (0., 4.));
+        assert_eq!(motion(vec![0b101], vec![3., 9.]), (3., 0.));
+        assert_eq!(motion(vec![0, 0b1], vec![5.]), (0., 0.));
+        assert_eq!(
+            motion(vec![0b1010_0011, 0b1], vec![1., 2., 3., 4., 5.]),
+            (1., 2.)
+        );
+        assert_eq!(motion(vec![], vec![]), (0., 0.));
+    }
+
+    #[test]
+    fn edges() {
+        let active = HashSet::from([Position::Left, Position::Bottom]);
+        let edge = |location| edge_at((200, 100), &active, location);
+        assert_eq!(edge((0, 50)), Some(Position::Left));
+        assert_eq!(edge((-3, 50)), Some(Position::Left));
+        assert_eq!(edge((100, 99)), Some(Position::Bottom));
+        assert_eq!(edge((100, 50)), None);
+        // inactive edges
+        assert_eq!(edge((199, 50)), None);
+        assert_eq!(edge((100, 0)), None);
+        // corners belong to both edges
+        assert_eq!(edge((0, 99)), Some(Position::Left));
+        assert_eq!(edge((199, 99)), Some(Position::Bottom));
+    }
+
+    /// a headless X server, killed when dropped
+    struct Xvfb {
+        display: CString,
+        server: Child,
+    }
+
+    impl Xvfb {
+        fn start() -> Option<Self> {
+            if Command::new("xdotool").arg("version").output().is_err() {
+                eprintln!("skipping X11 capture test, xdotool not found");
+                return None;
+            }
+            let n = 100 + std::process::id() % 900;
+            let server = Command::new("Xvfb")
+                .args([
+                    &format!(":{n}"),
+                    "-screen",
+                    "0",
+                    "200x100x24",
+                    "-nolisten",
+                    "tcp",
+                ])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            let server = match server {
+                Ok(server) => server,
+                Err(e) => {
+                    eprintln!("skipping X11 capture test, Xvfb not found: {e}");
+                    return None;
+                }
+            };
+            let socket = format!("/tmp/.X11-unix/X{n}");
+            for _ in 0..250 {
+                if Path::new(&socket).exists() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            let display = CString::new(format!(":{n}")).expect("display name");
+            Some(Self { display, server })
+        }
+
+        /// fake input through the XTEST extension
+        fn xdotool(&self, args: &[&str]) {
+            let status = Command::new("xdotool")
+                .env("DISPLAY", self.display.to_str().expect("display name"))
+                .args(args)
+                .status()
+                .expect("xdotool");
+            assert!(status.success(), "xdotool {args:?}: {status}");
+        }
+    }
+
+    impl Drop for Xvfb {
+        fn drop(&mut self) {
+            let _ = self.server.kill();
+            let _ = self.server.wait();
+        }
+    }
+
+    async fn next(capture: &mut X11InputCapture) -> (Position, CaptureEvent) {
+        let event = tokio::time::timeout(Duration::from_secs(5), capture.next());
+        event
+            .await
+            .expect("timeout")
+            .expect("stream")
+            .expect("event")
+    }
+
+    /// moves the pointer of an Xvfb server across an edge,
+    /// skipped if Xvfb or xdotool are not installed
+    #[tokio::test]
+    async fn capture_xvfb_pointer() {
+        let Some(xvfb) = Xvfb::start() else {
+            return;
+        };
+        let mut capture = X11InputCapture::with_display(Some(&xvfb.display)).expect("X11 capture");
+        capture.create(Position::Left).await.unwrap();
+        // make sure the raw events are selected before faking any input
+        unsafe { xlib::XSync(capture.0.get_ref().display, xlib::False) };
+
+        // the pointer is not grabbed while dragging
+        capture.set_block_drags(true);
+        // the pointer starts at the center of the screen
+        xvfb.xdotool(&["mousedown", "1", "mousemove", "0", "50"]);
+        let event = tokio::time::timeout(Duration::from_millis(300), capture.next());
+        assert!(event.await.is_err(), "captured while dragging");
+        // leave the edge and hit it again
+        xvfb.xdotool(&[
+            "mouseup",
+            "1",
+            "mousemove",
+            "10",
+            "50",
+            "mousemove",
+            "0",
+            "50",
+        ]);
+        let (pos, event) = next(&mut capture).await;
+        assert_eq!(pos, Position::Left);
+        let crossing = Crossing::new(50., (0., 100.));
+        assert_eq!(event, CaptureEvent::Begin(Some(crossing)));
+
+        xvfb.xdotool(&["click", "1"]);
+        for state in [1, 0] {
+            match next(&mut capture).await {
+                (
+                    Position::Left,
+                    CaptureEvent::Input(Event::Pointer(PointerEvent::Button {
+                        button,
+                        state: s,
+                        ..
+                    })),
+                ) => {
+                    assert_eq!(button, BTN_LEFT);
+                    assert_eq!(s, state);
+                }
+                e => panic!("unexpected event {e:?}"),
+            }
+        }
+
+        capture.release().await.unwrap();
+        capture.terminate().await.unwrap();
     }
 }
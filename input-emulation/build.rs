@@ -5,16 +5,19 @@ fn main() {
     let macos = cfg!(target_os = "macos");
     let wlroots = cfg!(feature = "wlroots");
     let rdp = cfg!(feature = "remote_desktop_portal");
+    let uinput = cfg!(feature = "uinput");
 
     let libei = unix && !macos && libei;
     let wlroots = unix && !macos && wlroots;
     let x11 = unix && !macos && x11;
     let rdp = unix && !macos && rdp;
+    let uinput = unix && !macos && uinput;
 
     println!("cargo::rustc-check-cfg=cfg(wlroots)");
     println!("cargo::rustc-check-cfg=cfg(libei)");
     println!("cargo::rustc-check-cfg=cfg(x11)");
     println!("cargo::rustc-check-cfg=cfg(rdp)");
+    println!("cargo::rustc-check-cfg=cfg(uinput)");
 
     if libei {
         println!("cargo::rustc-cfg=libei");
@@ -28,4 +31,7 @@ fn main() {
     if rdp {
         println!("cargo::rustc-cfg=rdp");
     }
+    if uinput {
+        println!("cargo::rustc-cfg=uinput");
+    }
 }
@@ -1,16 +1,124 @@
+mod trace;
+
+use std::fmt::{self, Debug};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
 use async_trait::async_trait;
 use input_event::Event;
+use tokio::sync::Notify;
+
+use crate::error::{EmulationCreationError, EmulationError};
+
+use super::{Emulation, EmulationHandle, Position};
+
+/// what the dummy emulation does with the events it receives
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum DummySink {
+    /// log the events
+    #[default]
+    Log,
+    /// record the events in a [`Recorder`]
+    Recorder(Recorder),
+    /// write the events and the resulting key and button state
+    /// to a trace file, one JSON object per line
+    Trace(PathBuf),
+}
+
+/// a call to the dummy emulation
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Emulated {
+    Create(EmulationHandle),
+    Destroy(EmulationHandle),
+    Event(EmulationHandle, Event),
+    Warp(EmulationHandle, Position, f64),
+}
+
+/// Records what a dummy emulation was asked to do, e.g. for tests.
+/// Clones share the same recording.
+#[derive(Clone, Default)]
+pub struct Recorder {
+    inner: Arc<(Mutex<Vec<Emulated>>, Notify)>,
+}
 
-use crate::error::EmulationError;
+impl Recorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// everything recorded so far
+    pub fn recorded(&self) -> Vec<Emulated> {
+        self.inner.0.lock().expect("lock").clone()
+    }
+
+    /// wait until the recording satisfies `f`
+    pub async fn wait_for(&self, f: impl Fn(&[Emulated]) -> bool) {
+        loop {
+            let changed = self.inner.1.notified();
+            if f(&self.inner.0.lock().expect("lock")) {
+                return;
+            }
+            changed.await;
+        }
+    }
 
-use super::{Emulation, EmulationHandle};
+    fn record(&self, emulated: Emulated) {
+        self.inner.0.lock().expect("lock").push(emulated);
+        self.inner.1.notify_waiters();
+    }
+}
 
-#[derive(Default)]
-pub(crate) struct DummyEmulation;
+impl Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder").finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Recorder {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.inner, &other.inner)
+    }
+}
+
+impl Eq for Recorder {}
+
+pub(crate) struct DummyEmulation {
+    sink: Sink,
+}
+
+enum Sink {
+    Log,
+    Recorder(Recorder),
+    Trace(trace::Trace),
+}
 
 impl DummyEmulation {
-    pub(crate) fn new() -> Self {
-        Self {}
+    pub(crate) fn new(sink: DummySink) -> Result<Self, EmulationCreationError> {
+        let sink = match sink {
+            DummySink::Log => Sink::Log,
+            DummySink::Recorder(recorder) => Sink::Recorder(recorder),
+            DummySink::Trace(path) => {
+                let trace = trace::Trace::create(&path).map_err(EmulationCreationError::Trace)?;
+                log::info!("dummy emulation: writing trace to {}", path.display());
+                Sink::Trace(trace)
+            }
+        };
+        Ok(Self { sink })
+    }
+
+    fn emulate(&mut self, emulated: Emulated) -> Result<(), EmulationError> {
+        match &mut self.sink {
+            Sink::Log => match emulated {
+                Emulated::Event(handle, event) => log::info!("received event: ({handle}) {event}"),
+                Emulated::Warp(handle, pos, offset) => {
+                    log::info!("warp: ({handle}) {pos:?} {offset:.3}")
+                }
+                Emulated::Create(_) | Emulated::Destroy(_) => {}
+            },
+            Sink::Recorder(recorder) => recorder.record(emulated),
+            Sink::Trace(trace) => trace.write(emulated)?,
+        }
+        Ok(())
     }
 }
 
@@ -21,12 +129,35 @@ impl Emulation for DummyEmulation {
         event: Event,
         client_handle: EmulationHandle,
     ) -> Result<(), EmulationError> {
-        log::info!("received event: ({client_handle}) {event}");
-        Ok(())
+        self.emulate(Emulated::Event(client_handle, event))
+    }
+    fn absolute_motion(&self) -> bool {
+        true
+    }
+
+    async fn warp_to_edge(
+        &mut self,
+        pos: Position,
+        offset: f64,
+        client_handle: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        self.emulate(Emulated::Warp(client_handle, pos, offset))
+    }
+    async fn create(&mut self, handle: EmulationHandle) {
+        if let Err(e) = self.emulate(Emulated::Create(handle)) {
+            log::warn!("{e}");
+        }
+    }
+    async fn destroy(&mut self, handle: EmulationHandle) {
+        if let Err(e) = self.emulate(Emulated::Destroy(handle)) {
+            log::warn!("{e}");
+        }
     }
-    async fn create(&mut self, _: EmulationHandle) {}
-    async fn destroy(&mut self, _: EmulationHandle) {}
     async fn terminate(&mut self) {
-        /* nothing to do */
+        if let Sink::Trace(trace) = &mut self.sink {
+            if let Err(e) = trace.flush() {
+                log::warn!("dummy emulation: failed to write trace: {e}");
+            }
+        }
     }
 }
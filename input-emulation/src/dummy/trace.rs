@@ -0,0 +1,230 @@
+//! Trace of the dummy emulation: one JSON object per line for every call,
+//! including the keys and buttons held on the emulated device afterwards, e.g.
+//!
+//! ```text
+//! {"time":0.0,"handle":0,"call":"create","pressed_keys":[],"pressed_buttons":[]}
+//! {"time":0.52,"handle":0,"call":"event","event":{"type":"key","time":0,"key":30,"state":1},"pressed_keys":["KeyA"],"pressed_buttons":[]}
+//! {"time":0.61,"handle":0,"call":"warp","position":"left","offset":0.5,"pressed_keys":["KeyA"],"pressed_buttons":[]}
+//! ```
+//!
+//! `time` is the number of seconds since the trace was created.
+//! The trace is written out at most every [`FLUSH_INTERVAL`],
+//! when a device is destroyed and when the emulation terminates.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use input_event::{Event, KeyboardEvent, PointerEvent, scancode};
+use serde::Serialize;
+
+use super::Emulated;
+use crate::{EmulationHandle, Position};
+
+/// time after which written entries are flushed to the file
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+pub(super) struct Trace {
+    writer: BufWriter<File>,
+    start: Instant,
+    last_flush: Instant,
+    held: HashMap<EmulationHandle, Held>,
+}
+
+/// keys and buttons held on an emulated device
+#[derive(Default)]
+struct Held {
+    keys: BTreeSet<u32>,
+    buttons: BTreeSet<u32>,
+}
+
+#[derive(Serialize)]
+struct Entry<'a> {
+    time: f64,
+    handle: EmulationHandle,
+    #[serde(flatten)]
+    call: Call,
+    pressed_keys: Vec<Key>,
+    pressed_buttons: &'a BTreeSet<u32>,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "call", rename_all = "snake_case")]
+enum Call {
+    Create,
+    Destroy,
+    Event { event: Event },
+    Warp { position: Position, offset: f64 },
+}
+
+/// name of the key if it has one, its scancode otherwise
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Key {
+    Named(scancode::Linux),
+    Scancode(u32),
+}
+
+impl Trace {
+    pub(super) fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+            last_flush: Instant::now(),
+            held: Default::default(),
+        })
+    }
+
+    pub(super) fn write(&mut self, emulated: Emulated) -> io::Result<()> {
+        let (handle, call) = match emulated {
+            Emulated::Create(handle) => (handle, Call::Create),
+            Emulated::Destroy(handle) => (handle, Call::Destroy),
+            Emulated::Event(handle, event) => (handle, Call::Event { event }),
+            Emulated::Warp(handle, position, offset) => (handle, Call::Warp { position, offset }),
+        };
+        let held = self.held.entry(handle).or_default();
+        match emulated {
+            Emulated::Event(_, Event::Keyboard(KeyboardEvent::Key { key, state, .. })) => {
+                match state {
+                    0 => held.keys.remove(&key),
+                    _ => held.keys.insert(key),
+                };
+            }
+            Emulated::Event(_, Event::Pointer(PointerEvent::Button { button, state, .. })) => {
+                match state {
+                    0 => held.buttons.remove(&button),
+                    _ => held.buttons.insert(button),
+                };
+            }
+            _ => {}
+        }
+        let entry = Entry {
+            time: self.start.elapsed().as_secs_f64(),
+            handle,
+            call,
+            pressed_keys: held
+                .keys
+                .iter()
+                .map(|&k| match scancode::Linux::try_from(k) {
+                    Ok(key) => Key::Named(key),
+                    Err(_) => Key::Scancode(k),
+                })
+                .collect(),
+            pressed_buttons: &held.buttons,
+        };
+        serde_json::to_writer(&mut self.writer, &entry)?;
+        writeln!(self.writer)?;
+        if let Emulated::Destroy(handle) = emulated {
+            self.held.remove(&handle);
+            self.flush()?;
+        } else if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            // keep the trace readable while the emulation is running
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(super) fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use input_event::{BTN_LEFT, BTN_RIGHT};
+    use serde_json::{Value, json};
+
+    use super::*;
+
+    fn key(key: scancode::Linux, state: u8) -> Event {
+        Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: key as u32,
+            state,
+        })
+    }
+
+    fn button(button: u32, state: u32) -> Event {
+        Event::Pointer(PointerEvent::Button {
+            time: 0,
+            button,
+            state,
+        })
+    }
+
+    fn read(path: &Path) -> Vec<Value> {
+        std::fs::read_to_string(path)
+            .expect("read trace")
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("json"))
+            .collect()
+    }
+
+    #[test]
+    fn held_keys_and_buttons() {
+        let path = std::env::temp_dir().join(format!("lan-mouse-trace-{}", std::process::id()));
+        let mut trace = Trace::create(&path).expect("create");
+        let calls = [
+            Emulated::Create(0),
+            Emulated::Event(0, key(scancode::Linux::KeyLeftShift, 1)),
+            Emulated::Event(0, key(scancode::Linux::KeyA, 1)),
+            Emulated::Event(0, button(BTN_LEFT, 1)),
+            Emulated::Event(1, button(BTN_RIGHT, 1)),
+            Emulated::Event(0, key(scancode::Linux::KeyLeftShift, 0)),
+            Emulated::Event(0, key(scancode::Linux::KeyA, 0)),
+            // keys without a name are traced by their scancode
+            Emulated::Event(
+                0,
+                Event::Keyboard(KeyboardEvent::Key {
+                    time: 0,
+                    key: 0xffff,
+                    state: 1,
+                }),
+            ),
+            Emulated::Warp(0, Position::Left, 0.5),
+            Emulated::Event(0, button(BTN_LEFT, 0)),
+        ];
+        for call in calls {
+            trace.write(call).expect("write");
+        }
+        // entries are not flushed for every event
+        assert!(read(&path).is_empty());
+        // but once a device is destroyed, which also forgets what it held
+        trace.write(Emulated::Destroy(0)).expect("write");
+        trace.write(Emulated::Create(0)).expect("write");
+        trace.flush().expect("flush");
+
+        let held: Vec<_> = read(&path)
+            .into_iter()
+            .map(|e| {
+                (
+                    e["handle"].clone(),
+                    e["pressed_keys"].clone(),
+                    e["pressed_buttons"].clone(),
+                )
+            })
+            .collect();
+        let expected = [
+            (0, json!([]), json!([])),
+            (0, json!(["KeyLeftShift"]), json!([])),
+            (0, json!(["KeyA", "KeyLeftShift"]), json!([])),
+            (0, json!(["KeyA", "KeyLeftShift"]), json!([BTN_LEFT])),
+            (1, json!([]), json!([BTN_RIGHT])),
+            (0, json!(["KeyA"]), json!([BTN_LEFT])),
+            (0, json!([]), json!([BTN_LEFT])),
+            (0, json!([0xffff]), json!([BTN_LEFT])),
+            (0, json!([0xffff]), json!([BTN_LEFT])),
+            (0, json!([0xffff]), json!([])),
+            (0, json!([0xffff]), json!([])),
+            (0, json!([]), json!([])),
+        ]
+        .map(|(handle, keys, buttons)| (json!(handle), keys, buttons));
+        assert_eq!(held, expected);
+        let _ = std::fs::remove_file(&path);
+    }
+}
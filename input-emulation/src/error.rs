@@ -49,12 +49,17 @@ pub enum EmulationCreationError {
     #[cfg(x11)]
     #[error("x11: `{0}`")]
     X11(#[from] X11EmulationCreationError),
+    #[cfg(uinput)]
+    #[error("uinput: `{0}`")]
+    Uinput(#[from] UinputEmulationCreationError),
     #[cfg(target_os = "macos")]
     #[error("macos: `{0}`")]
     MacOs(#[from] MacOSEmulationCreationError),
     #[cfg(windows)]
     #[error("windows: `{0}`")]
     Windows(#[from] WindowsEmulationCreationError),
+    #[error("dummy backend: can not create trace file: `{0}`")]
+    Trace(io::Error),
     #[error("capture error")]
     NoAvailableBackend,
 }
@@ -141,6 +146,13 @@ pub enum X11EmulationCreationError {
     OpenDisplay,
 }
 
+#[cfg(uinput)]
+#[derive(Debug, Error)]
+pub enum UinputEmulationCreationError {
+    #[error("could not open /dev/uinput: `{0}`")]
+    Io(#[from] std::io::Error),
+}
+
 #[cfg(target_os = "macos")]
 #[derive(Debug, Error)]
 pub enum MacOSEmulationCreationError {
@@ -5,7 +5,9 @@ use std::{
 };
 
 use input_event::{Event, KeyboardEvent};
+use serde::Serialize;
 
+pub use self::dummy::{DummySink, Emulated, Recorder};
 pub use self::error::{EmulationCreationError, EmulationError, InputEmulationError};
 
 #[cfg(windows)]
@@ -23,16 +25,29 @@ mod xdg_desktop_portal;
 #[cfg(libei)]
 mod libei;
 
+#[cfg(uinput)]
+mod uinput;
+
 #[cfg(target_os = "macos")]
 mod macos;
 
-/// fallback input emulation (logs events)
+/// fallback input emulation (logs or records events)
 mod dummy;
 mod error;
 
 pub type EmulationHandle = u64;
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// edge of the screen
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Position {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Backend {
     #[cfg(wlroots)]
     Wlroots,
@@ -42,11 +57,13 @@ pub enum Backend {
     Xdp,
     #[cfg(x11)]
     X11,
+    #[cfg(uinput)]
+    Uinput,
     #[cfg(windows)]
     Windows,
     #[cfg(target_os = "macos")]
     MacOs,
-    Dummy,
+    Dummy(DummySink),
 }
 
 impl Display for Backend {
@@ -60,11 +77,13 @@ impl Display for Backend {
             Backend::Xdp => write!(f, "xdg-desktop-portal"),
             #[cfg(x11)]
             Backend::X11 => write!(f, "X11"),
+            #[cfg(uinput)]
+            Backend::Uinput => write!(f, "uinput"),
             #[cfg(windows)]
             Backend::Windows => write!(f, "windows"),
             #[cfg(target_os = "macos")]
             Backend::MacOs => write!(f, "macos"),
-            Backend::Dummy => write!(f, "dummy"),
+            Backend::Dummy(_) => write!(f, "dummy"),
         }
     }
 }
@@ -84,13 +103,15 @@ impl InputEmulation {
             Backend::Libei => Box::new(libei::LibeiEmulation::new().await?),
             #[cfg(x11)]
             Backend::X11 => Box::new(x11::X11Emulation::new()?),
+            #[cfg(uinput)]
+            Backend::Uinput => Box::new(uinput::UinputEmulation::new()?),
             #[cfg(rdp)]
             Backend::Xdp => Box::new(xdg_desktop_portal::DesktopPortalEmulation::new().await?),
             #[cfg(windows)]
             Backend::Windows => Box::new(windows::WindowsEmulation::new()?),
             #[cfg(target_os = "macos")]
             Backend::MacOs => Box::new(macos::MacOSEmulation::new()?),
-            Backend::Dummy => Box::new(dummy::DummyEmulation::new()),
+            Backend::Dummy(sink) => Box::new(dummy::DummyEmulation::new(sink)?),
         };
         Ok(Self {
             emulation,
@@ -101,7 +122,7 @@ impl InputEmulation {
 
     pub async fn new(backend: Option<Backend>) -> Result<InputEmulation, EmulationCreationError> {
         if let Some(backend) = backend {
-            let b = Self::with_backend(backend).await;
+            let b = Self::with_backend(backend.clone()).await;
             if b.is_ok() {
                 log::info!("using emulation backend: {backend}");
             }
@@ -117,13 +138,15 @@ impl InputEmulation {
             Backend::Xdp,
             #[cfg(x11)]
             Backend::X11,
+            #[cfg(uinput)]
+            Backend::Uinput,
             #[cfg(windows)]
             Backend::Windows,
             #[cfg(target_os = "macos")]
             Backend::MacOs,
-            Backend::Dummy,
+            Backend::Dummy(DummySink::Log),
         ] {
-            match Self::with_backend(backend).await {
+            match Self::with_backend(backend.clone()).await {
                 Ok(b) => {
                     log::info!("using emulation backend: {backend}");
                     return Ok(b);
@@ -153,6 +176,28 @@ impl InputEmulation {
         }
     }
 
+    /// whether the backend can move the pointer to a point along an edge,
+    /// see [`InputEmulation::warp_to_edge`]
+    pub fn absolute_motion(&self) -> bool {
+        self.emulation.absolute_motion()
+    }
+
+    /// move the pointer to `offset` (`0.0..=1.0`) along the edge at `pos`,
+    /// e.g. where the cursor entered this device
+    pub async fn warp_to_edge(
+        &mut self,
+        pos: Position,
+        offset: f64,
+        handle: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        if !self.handles.contains(&handle) {
+            return Ok(());
+        }
+        self.emulation
+            .warp_to_edge(pos, offset.clamp(0., 1.), handle)
+            .await
+    }
+
     pub async fn create(&mut self, handle: EmulationHandle) -> bool {
         if self.handles.insert(handle) {
             self.pressed_keys.insert(handle, HashSet::new());
@@ -234,6 +279,21 @@ trait Emulation: Send {
         event: Event,
         handle: EmulationHandle,
     ) -> Result<(), EmulationError>;
+    /// whether the backend implements [`Emulation::warp_to_edge`]
+    fn absolute_motion(&self) -> bool {
+        false
+    }
+    /// absolute motion: move the pointer just inside the edge at `pos`,
+    /// `offset` (`0.0..=1.0`) along it.
+    /// Backends without support for absolute motion leave the pointer where it is.
+    async fn warp_to_edge(
+        &mut self,
+        _pos: Position,
+        _offset: f64,
+        _handle: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        Ok(())
+    }
     async fn create(&mut self, handle: EmulationHandle);
     async fn destroy(&mut self, handle: EmulationHandle);
     async fn terminate(&mut self);
@@ -0,0 +1,273 @@
+use async_trait::async_trait;
+use evdev::{
+    AttributeSet, EventType, InputEvent, KeyCode, RelativeAxisCode, uinput::VirtualDevice,
+};
+use std::collections::HashMap;
+use std::io;
+use std::ops::RangeInclusive;
+
+use input_event::{Event, KeyboardEvent, PointerEvent};
+
+use crate::error::EmulationError;
+
+use super::{Emulation, EmulationHandle, error::UinputEmulationCreationError};
+
+/// highest keycode supported by the kernel (KEY_MAX)
+const KEY_MAX: u16 = 0x2ff;
+
+/// mouse buttons
+const BUTTONS: RangeInclusive<u16> = KeyCode::BTN_LEFT.0..=KeyCode::BTN_TASK.0;
+
+/// input emulation through virtual devices created via `/dev/uinput`.
+/// Works independently of any display server (tty, kiosk setups),
+/// but requires write access to `/dev/uinput`.
+pub(crate) struct UinputEmulation {
+    devices: HashMap<EmulationHandle, VirtualInput>,
+}
+
+impl UinputEmulation {
+    pub(crate) fn new() -> Result<Self, UinputEmulationCreationError> {
+        // make sure /dev/uinput can be opened
+        VirtualDevice::builder()?;
+        Ok(Self {
+            devices: HashMap::new(),
+        })
+    }
+}
+
+#[async_trait]
+impl Emulation for UinputEmulation {
+    async fn consume(
+        &mut self,
+        event: Event,
+        handle: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        if let Some(device) = self.devices.get_mut(&handle) {
+            device.consume_event(event)?;
+        }
+        Ok(())
+    }
+
+    async fn create(&mut self, handle: EmulationHandle) {
+        match VirtualInput::new(handle) {
+            Ok(device) => {
+                self.devices.insert(handle, device);
+            }
+            Err(e) => log::error!("could not create uinput device: {e}"),
+        }
+    }
+
+    async fn destroy(&mut self, handle: EmulationHandle) {
+        self.devices.remove(&handle);
+    }
+
+    async fn terminate(&mut self) {
+        /* devices are removed when dropped */
+    }
+}
+
+/// virtual keyboard and pointer of a single client
+struct VirtualInput {
+    keyboard: VirtualDevice,
+    pointer: VirtualDevice,
+    /// sub-pixel motion not yet emitted
+    motion_remainder: (f64, f64),
+    /// high resolution scroll (1/120 of a notch) not yet emitted as a full notch
+    scroll_remainder: [i32; 2],
+}
+
+impl VirtualInput {
+    fn new(handle: EmulationHandle) -> io::Result<Self> {
+        // buttons are kept on the pointer device,
+        // otherwise the keyboard may be classified as a pointer
+        let keys = (1..=KEY_MAX)
+            .filter(|k| !BUTTONS.contains(k))
+            .map(KeyCode::new)
+            .collect::<AttributeSet<_>>();
+        let keyboard = VirtualDevice::builder()?
+            .name(&format!("lan-mouse keyboard ({handle})"))
+            .with_keys(&keys)?
+            .build()?;
+
+        let buttons = BUTTONS.map(KeyCode::new).collect::<AttributeSet<_>>();
+        let axes = AttributeSet::from_iter([
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+        ]);
+        let pointer = VirtualDevice::builder()?
+            .name(&format!("lan-mouse pointer ({handle})"))
+            .with_keys(&buttons)?
+            .with_relative_axes(&axes)?
+            .build()?;
+
+        Ok(Self {
+            keyboard,
+            pointer,
+            motion_remainder: (0., 0.),
+            scroll_remainder: [0; 2],
+        })
+    }
+
+    fn consume_event(&mut self, event: Event) -> io::Result<()> {
+        match event {
+            Event::Pointer(e) => match e {
+                PointerEvent::Motion { time: _, dx, dy } => {
+                    let (rx, ry) = &mut self.motion_remainder;
+                    self.pointer.emit(&[
+                        relative(RelativeAxisCode::REL_X, take_pixels(rx, dx)),
+                        relative(RelativeAxisCode::REL_Y, take_pixels(ry, dy)),
+                    ])?;
+                }
+                PointerEvent::Button {
+                    time: _,
+                    button,
+                    state,
+                } => {
+                    let event = InputEvent::new(EventType::KEY.0, button as u16, state as i32);
+                    self.pointer.emit(&[event])?;
+                }
+                PointerEvent::Axis {
+                    time: _,
+                    axis,
+                    value,
+                } => {
+                    // 15 units of smooth scrolling correspond to one notch
+                    let value = (value * 8.) as i32;
+                    let events = scroll_events(&mut self.scroll_remainder, axis, value);
+                    self.pointer.emit(&events)?;
+                }
+                PointerEvent::AxisDiscrete120 { axis, value } => {
+                    let events = scroll_events(&mut self.scroll_remainder, axis, value);
+                    self.pointer.emit(&events)?;
+                }
+            },
+            Event::Keyboard(KeyboardEvent::Key {
+                time: _,
+                key,
+                state,
+            }) => {
+                let event = InputEvent::new(EventType::KEY.0, key as u16, state as i32);
+                self.keyboard.emit(&[event])?;
+            }
+            // modifier state follows from the emulated keys
+            Event::Keyboard(KeyboardEvent::Modifiers { .. }) => {}
+        }
+        Ok(())
+    }
+}
+
+/// whole pixels of the motion `delta` and the `remainder` carried over
+/// from previous motions, keeping the fraction in `remainder`
+fn take_pixels(remainder: &mut f64, delta: f64) -> i32 {
+    let total = *remainder + delta;
+    *remainder = total.fract();
+    total.trunc() as i32
+}
+
+/// full notches of `value` (1/120 of a notch) and the `remainder` carried over
+/// from previous scrolls, keeping the rest in `remainder`.
+/// Notches are truncated toward zero, so scrolling back and forth
+/// within a notch does not emit one.
+fn take_notches(remainder: &mut i32, value: i32) -> i32 {
+    *remainder += value;
+    let notches = *remainder / 120;
+    *remainder %= 120;
+    notches
+}
+
+/// high resolution and notch events scrolling by `value` 1/120 of a notch,
+/// `remainders` holds the rest of both axes not yet emitted as a notch
+fn scroll_events(remainders: &mut [i32; 2], axis: u8, value: i32) -> [InputEvent; 2] {
+    let (idx, axis, hi_res, value) = match axis {
+        // wayland scrolls down for positive values, evdev scrolls up
+        0 => (
+            0,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            -value,
+        ),
+        _ => (
+            1,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+            value,
+        ),
+    };
+    let notches = take_notches(&mut remainders[idx], value);
+    [relative(hi_res, value), relative(axis, notches)]
+}
+
+fn relative(axis: RelativeAxisCode, value: i32) -> InputEvent {
+    InputEvent::new(EventType::RELATIVE.0, axis.0, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixels() {
+        let take = |deltas: &[f64]| {
+            let mut remainder = 0.;
+            let pixels = deltas
+                .iter()
+                .map(|&d| take_pixels(&mut remainder, d))
+                .collect::<Vec<_>>();
+            (pixels, (remainder * 10.).round() / 10.)
+        };
+        assert_eq!(take(&[0.4, 0.4, 0.4]), (vec![0, 0, 1], 0.2));
+        assert_eq!(take(&[2.5, 0.5]), (vec![2, 1], 0.));
+        assert_eq!(take(&[-0.6, -0.6]), (vec![0, -1], -0.2));
+        // fractions of opposite directions cancel out
+        assert_eq!(take(&[0.7, -1.]), (vec![0, 0], -0.3));
+        assert_eq!(take(&[-0.5, 1.7]), (vec![0, 1], 0.2));
+    }
+
+    #[test]
+    fn notches() {
+        let take = |values: &[i32]| {
+            let mut remainder = 0;
+            let notches = values
+                .iter()
+                .map(|&v| take_notches(&mut remainder, v))
+                .collect::<Vec<_>>();
+            (notches, remainder)
+        };
+        assert_eq!(take(&[60, 60]), (vec![0, 1], 0));
+        assert_eq!(take(&[-130]), (vec![-1], -10));
+        assert_eq!(take(&[119, -1]), (vec![0, 0], 118));
+        assert_eq!(take(&[-119, 1]), (vec![0, 0], -118));
+        assert_eq!(take(&[60, -130, -60]), (vec![0, 0, -1], -10));
+        assert_eq!(take(&[240, 30]), (vec![2, 0], 30));
+    }
+
+    #[test]
+    fn scrolling() {
+        let emitted = |events: [InputEvent; 2]| events.map(|e| (e.code(), e.value()));
+        let mut remainders = [0; 2];
+        let wheel = (
+            RelativeAxisCode::REL_WHEEL.0,
+            RelativeAxisCode::REL_WHEEL_HI_RES.0,
+        );
+        let hwheel = (
+            RelativeAxisCode::REL_HWHEEL.0,
+            RelativeAxisCode::REL_HWHEEL_HI_RES.0,
+        );
+
+        // the vertical axis is inverted
+        let events = scroll_events(&mut remainders, 0, 60);
+        assert_eq!(emitted(events), [(wheel.1, -60), (wheel.0, 0)]);
+        // the horizontal axis keeps its own remainder
+        let events = scroll_events(&mut remainders, 1, 60);
+        assert_eq!(emitted(events), [(hwheel.1, 60), (hwheel.0, 0)]);
+        let events = scroll_events(&mut remainders, 0, 60);
+        assert_eq!(emitted(events), [(wheel.1, -60), (wheel.0, -1)]);
+        let events = scroll_events(&mut remainders, 1, -120);
+        assert_eq!(emitted(events), [(hwheel.1, -120), (hwheel.0, 0)]);
+        assert_eq!(remainders, [0, -60]);
+    }
+}
@@ -1,6 +1,6 @@
 use crate::error::EmulationError;
 
-use super::{Emulation, error::WlrootsEmulationCreationError};
+use super::{Emulation, Position, error::WlrootsEmulationCreationError};
 use async_trait::async_trait;
 use bitflags::bitflags;
 use std::collections::HashMap;
@@ -159,6 +159,23 @@ impl Emulation for WlrootsEmulation {
         Ok(())
     }
 
+    fn absolute_motion(&self) -> bool {
+        true
+    }
+
+    async fn warp_to_edge(
+        &mut self,
+        pos: Position,
+        offset: f64,
+        handle: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        if let Some(virtual_input) = self.state.input_for_client.get(&handle) {
+            virtual_input.warp_to_edge(pos, offset);
+            self.queue.flush()?;
+        }
+        Ok(())
+    }
+
     async fn create(&mut self, handle: EmulationHandle) {
         self.state.add_client(handle);
         if let Err(e) = self.queue.flush() {
@@ -183,6 +200,28 @@ struct VirtualInput {
 }
 
 impl VirtualInput {
+    /// The output layout is not known to us, so absolute coordinates
+    /// are given relative to an arbitrary extent spanning the whole layout.
+    fn warp_to_edge(&self, pos: Position, offset: f64) {
+        const EXTENT: u32 = 1 << 16;
+        // stay clear of the edge itself, where the pointer
+        // would immediately hit the capture barrier again
+        const INSET: u32 = EXTENT / 512;
+        let now: u32 = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u32;
+        let along = ((offset * EXTENT as f64) as u32).min(EXTENT - 1);
+        let (x, y) = match pos {
+            Position::Left => (INSET, along),
+            Position::Right => (EXTENT - INSET, along),
+            Position::Top => (along, INSET),
+            Position::Bottom => (along, EXTENT - INSET),
+        };
+        self.pointer.motion_absolute(now, x, y, EXTENT, EXTENT);
+        self.pointer.frame();
+    }
+
     fn consume_event(&self, event: Event) -> Result<(), ()> {
         let now: u32 = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -11,7 +11,7 @@ use input_event::{
 
 use crate::error::EmulationError;
 
-use super::{Emulation, EmulationHandle, error::X11EmulationCreationError};
+use super::{Emulation, EmulationHandle, Position, error::X11EmulationCreationError};
 
 pub(crate) struct X11Emulation {
     display: *mut xlib::Display,
@@ -38,6 +38,26 @@ impl X11Emulation {
         }
     }
 
+    /// move the pointer just inside the edge at `pos`, `offset` along it
+    fn move_to_edge(&self, pos: Position, offset: f64) {
+        // stay clear of the edge itself, where the pointer
+        // would immediately hit the capture barrier again
+        const INSET: i32 = 2;
+        unsafe {
+            let screen = xlib::XDefaultScreen(self.display);
+            let width = xlib::XDisplayWidth(self.display, screen);
+            let height = xlib::XDisplayHeight(self.display, screen);
+            let along = |length: i32| ((offset * length as f64) as i32).min(length - 1);
+            let (x, y) = match pos {
+                Position::Left => (INSET, along(height)),
+                Position::Right => (width - 1 - INSET, along(height)),
+                Position::Top => (along(width), INSET),
+                Position::Bottom => (along(width), height - 1 - INSET),
+            };
+            xtest::XTestFakeMotionEvent(self.display, screen, x, y, 0);
+        }
+    }
+
     fn emulate_mouse_button(&self, button: u32, state: u32) {
         unsafe {
             let x11_button = match button {
@@ -140,6 +160,23 @@ impl Emulation for X11Emulation {
         Ok(())
     }
 
+    fn absolute_motion(&self) -> bool {
+        true
+    }
+
+    async fn warp_to_edge(
+        &mut self,
+        pos: Position,
+        offset: f64,
+        _: EmulationHandle,
+    ) -> Result<(), EmulationError> {
+        self.move_to_edge(pos, offset);
+        unsafe {
+            xlib::XFlush(self.display);
+        }
+        Ok(())
+    }
+
     async fn create(&mut self, _: EmulationHandle) {
         // for our purposes it does not matter what client sent the event
     }
@@ -1 +1,17 @@
+use thiserror::Error;
 
+#[derive(Debug, Error)]
+#[error("invalid {kind} `{name}`")]
+pub struct InvalidName {
+    kind: &'static str,
+    name: String,
+}
+
+impl InvalidName {
+    pub(crate) fn new(kind: &'static str, name: &str) -> Self {
+        Self {
+            kind,
+            name: name.to_owned(),
+        }
+    }
+}
@@ -1,8 +1,13 @@
 use std::fmt::{self, Display};
 
+use serde::Serialize;
+
 pub mod error;
+mod names;
 pub mod scancode;
 
+pub use names::{Button, Modifier};
+
 #[cfg(all(unix, feature = "libei", not(target_os = "macos")))]
 mod libei;
 
@@ -13,7 +18,8 @@ pub const BTN_MIDDLE: u32 = 0x112;
 pub const BTN_BACK: u32 = 0x113;
 pub const BTN_FORWARD: u32 = 0x114;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum PointerEvent {
     /// relative motion event
     Motion { time: u32, dx: f64, dy: f64 },
@@ -25,7 +31,8 @@ pub enum PointerEvent {
     AxisDiscrete120 { axis: u8, value: i32 },
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum KeyboardEvent {
     /// a key press / release event
     Key { time: u32, key: u32, state: u8 },
@@ -38,7 +45,8 @@ pub enum KeyboardEvent {
     },
 }
 
-#[derive(PartialEq, Debug, Clone, Copy)]
+#[derive(PartialEq, Debug, Clone, Copy, Serialize)]
+#[serde(untagged)]
 pub enum Event {
     /// pointer event (motion / button / axis)
     Pointer(PointerEvent),
@@ -55,15 +63,8 @@ impl Display for PointerEvent {
                 button,
                 state,
             } => {
-                let str = match *button {
-                    BTN_LEFT => Some("left"),
-                    BTN_RIGHT => Some("right"),
-                    BTN_MIDDLE => Some("middle"),
-                    BTN_FORWARD => Some("forward"),
-                    BTN_BACK => Some("back"),
-                    _ => None,
-                };
-                if let Some(button) = str {
+                if let Ok(button) = Button::try_from(*button) {
+                    let button = button.name();
                     write!(f, "button({button}, {state})")
                 } else {
                     write!(f, "button({button}, {state}")
@@ -0,0 +1,214 @@
+//! Mouse buttons and modifier keys known by name,
+//! e.g. in the configuration or in capture scripts.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    BTN_BACK, BTN_FORWARD, BTN_LEFT, BTN_MIDDLE, BTN_RIGHT, error::InvalidName, scancode::Linux,
+};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+    Back,
+    Forward,
+}
+
+/// name and (evdev) code of every button
+const BUTTONS: [(Button, &str, u32); 5] = [
+    (Button::Left, "left", BTN_LEFT),
+    (Button::Right, "right", BTN_RIGHT),
+    (Button::Middle, "middle", BTN_MIDDLE),
+    (Button::Back, "back", BTN_BACK),
+    (Button::Forward, "forward", BTN_FORWARD),
+];
+
+impl Button {
+    pub fn all() -> impl Iterator<Item = Button> {
+        BUTTONS.into_iter().map(|(button, _, _)| button)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    pub fn code(self) -> u32 {
+        self.entry().2
+    }
+
+    fn entry(self) -> (Button, &'static str, u32) {
+        BUTTONS
+            .into_iter()
+            .find(|&(button, _, _)| button == self)
+            .expect("every button is listed")
+    }
+}
+
+impl From<Button> for u32 {
+    fn from(button: Button) -> Self {
+        button.code()
+    }
+}
+
+impl From<Button> for &'static str {
+    fn from(button: Button) -> Self {
+        button.name()
+    }
+}
+
+impl TryFrom<u32> for Button {
+    type Error = ();
+
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        Button::all().find(|b| b.code() == code).ok_or(())
+    }
+}
+
+impl FromStr for Button {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Button::all()
+            .find(|b| b.name() == s)
+            .ok_or_else(|| InvalidName::new("button", s))
+    }
+}
+
+impl TryFrom<String> for Button {
+    type Error = InvalidName;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(try_from = "String", into = "&'static str")]
+pub enum Modifier {
+    Shift,
+    Ctrl,
+    Alt,
+    Super,
+}
+
+/// name, left and right key and bit in the (xkb) modifier mask of every modifier
+const MODIFIERS: [(Modifier, &str, [Linux; 2], u32); 4] = [
+    (
+        Modifier::Shift,
+        "shift",
+        [Linux::KeyLeftShift, Linux::KeyRightShift],
+        1 << 0,
+    ),
+    (
+        Modifier::Ctrl,
+        "ctrl",
+        [Linux::KeyLeftCtrl, Linux::KeyRightCtrl],
+        1 << 2,
+    ),
+    (
+        Modifier::Alt,
+        "alt",
+        [Linux::KeyLeftAlt, Linux::KeyRightalt],
+        1 << 3,
+    ),
+    (
+        Modifier::Super,
+        "super",
+        [Linux::KeyLeftMeta, Linux::KeyRightmeta],
+        1 << 6,
+    ),
+];
+
+impl Modifier {
+    pub fn all() -> impl Iterator<Item = Modifier> {
+        MODIFIERS.into_iter().map(|(modifier, _, _, _)| modifier)
+    }
+
+    pub fn name(self) -> &'static str {
+        self.entry().1
+    }
+
+    /// left and right key of the modifier
+    pub fn keys(self) -> [Linux; 2] {
+        self.entry().2
+    }
+
+    /// bit of the modifier in the (xkb) modifier mask
+    pub fn mask(self) -> u32 {
+        self.entry().3
+    }
+
+    /// the modifier of a key, if it is a modifier key
+    pub fn of_key(key: u32) -> Option<Modifier> {
+        let key = Linux::try_from(key).ok()?;
+        Modifier::all().find(|m| m.keys().contains(&key))
+    }
+
+    fn entry(self) -> (Modifier, &'static str, [Linux; 2], u32) {
+        MODIFIERS
+            .into_iter()
+            .find(|&(modifier, _, _, _)| modifier == self)
+            .expect("every modifier is listed")
+    }
+}
+
+impl From<Modifier> for &'static str {
+    fn from(modifier: Modifier) -> Self {
+        modifier.name()
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = InvalidName;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Modifier::all()
+            .find(|m| m.name() == s)
+            .ok_or_else(|| InvalidName::new("modifier", s))
+    }
+}
+
+impl TryFrom<String> for Modifier {
+    type Error = InvalidName;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buttons() {
+        for button in Button::all() {
+            assert_eq!(button.name().parse::<Button>().ok(), Some(button));
+            assert_eq!(Button::try_from(button.code()), Ok(button));
+        }
+        assert_eq!(Button::try_from(BTN_BACK), Ok(Button::Back));
+        assert!("wheel".parse::<Button>().is_err());
+        assert_eq!(Button::try_from(0x115), Err(()));
+    }
+
+    #[test]
+    fn modifiers() {
+        for modifier in Modifier::all() {
+            assert_eq!(modifier.name().parse::<Modifier>().ok(), Some(modifier));
+            for key in modifier.keys() {
+                assert_eq!(Modifier::of_key(key as u32), Some(modifier));
+            }
+        }
+        assert_eq!(
+            Modifier::of_key(Linux::KeyRightalt as u32).map(Modifier::mask),
+            Some(1 << 3)
+        );
+        assert_eq!(Modifier::of_key(Linux::KeyA as u32), None);
+        assert!("hyper".parse::<Modifier>().is_err());
+    }
+}
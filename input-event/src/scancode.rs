@@ -1,5 +1,10 @@
+use std::str::FromStr;
+
 use num_enum::TryFromPrimitive;
-use serde::{Deserialize, Serialize};
+use serde::{
+    Deserialize, Serialize,
+    de::{IntoDeserializer, value},
+};
 
 /*
  * https://learn.microsoft.com/en-us/windows/win32/inputdev/about-keyboard-input
@@ -432,6 +437,15 @@ pub enum Linux {
     KeyCount = 249,
 }
 
+/// parse the name of a key, e.g. `KeyLeftCtrl`
+impl FromStr for Linux {
+    type Err = value::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::deserialize(s.into_deserializer())
+    }
+}
+
 impl TryFrom<Linux> for Windows {
     type Error = ();
 
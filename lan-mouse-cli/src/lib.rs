@@ -1,12 +1,18 @@
 use clap::{Args, Parser, Subcommand};
 use futures::StreamExt;
 
-use std::{net::IpAddr, time::Duration};
+use std::{
+    io::{self, Write},
+    net::IpAddr,
+    time::Duration,
+};
 use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, BufReader};
 
 use lan_mouse_ipc::{
-    ClientHandle, ConnectionError, FrontendEvent, FrontendRequest, IpcError, Position,
-    connect_async,
+    AsyncFrontendEventReader, AsyncFrontendRequestWriter, ClientHandle, ConnectionError,
+    EdgeSegment, FrontendEvent, FrontendMessage, FrontendRequest, HookEvent, IpcError, Position,
+    RequestError, RequestId, Response, Status, connect_async,
 };
 
 #[derive(Debug, Error)]
@@ -16,9 +22,17 @@ pub enum CliError {
     ServiceNotRunning(#[from] ConnectionError),
     #[error("error communicating with service: {0}")]
     Ipc(#[from] IpcError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("request failed: {0}")]
+    Request(#[from] RequestError),
+    #[error("connection to service closed")]
+    ConnectionClosed,
+    #[error("unexpected response: {0:?}")]
+    UnexpectedResponse(Response),
 }
 
-#[derive(Parser, Clone, Debug, PartialEq, Eq)]
+#[derive(Parser, Clone, Debug, PartialEq)]
 #[command(name = "lan-mouse-cli", about = "LanMouse CLI interface")]
 pub struct CliArgs {
     #[command(subcommand)]
@@ -37,7 +51,7 @@ struct Client {
     enter_hook: Option<String>,
 }
 
-#[derive(Clone, Subcommand, Debug, PartialEq, Eq)]
+#[derive(Clone, Subcommand, Debug, PartialEq)]
 enum CliSubcommand {
     /// add a new client
     AddClient(Client),
@@ -49,6 +63,10 @@ enum CliSubcommand {
     Deactivate { id: ClientHandle },
     /// list configured clients
     List,
+    /// list peers discovered on the local network
+    ListPeers,
+    /// add a discovered peer as a new client
+    AddPeer { name: String, pos: Position },
     /// change hostname
     SetHost {
         id: ClientHandle,
@@ -58,8 +76,44 @@ enum CliSubcommand {
     SetPort { id: ClientHandle, port: u16 },
     /// set position
     SetPosition { id: ClientHandle, pos: Position },
+    /// claim a segment of the edge (offset and length in pixels)
+    /// or the whole edge if omitted
+    SetSegment {
+        id: ClientHandle,
+        #[arg(requires = "length")]
+        offset: Option<u32>,
+        length: Option<u32>,
+    },
+    /// capture input for a client directly, as if its hotkey was pressed
+    Capture { id: ClientHandle },
     /// set ips
     SetIps { id: ClientHandle, ips: Vec<IpAddr> },
+    /// set the factor applied to pointer motion
+    SetMotionMultiplier { id: ClientHandle, multiplier: f64 },
+    /// set the pointer acceleration (0 disables acceleration)
+    SetAcceleration { id: ClientHandle, acceleration: f64 },
+    /// set the factor applied to scroll events
+    SetScrollMultiplier { id: ClientHandle, multiplier: f64 },
+    /// invert the scroll direction
+    SetNaturalScroll {
+        id: ClientHandle,
+        #[arg(action = clap::ArgAction::Set)]
+        enabled: bool,
+    },
+    /// set the command executed when the cursor enters (enter) or leaves (leave)
+    /// the client or the connection to it is established (connect) or closed (disconnect)
+    /// (omit the command to remove the hook)
+    SetHook {
+        id: ClientHandle,
+        event: HookEvent,
+        command: Option<String>,
+    },
+    /// set the expected certificate fingerprint
+    /// (omit to pin the fingerprint on the next connection)
+    SetFingerprint {
+        id: ClientHandle,
+        sha256_fingerprint: Option<String>,
+    },
     /// re-enable capture
     EnableCapture,
     /// re-enable emulation
@@ -71,6 +125,10 @@ enum CliSubcommand {
     },
     /// deauthorize a public key
     RemoveAuthorizedKey { sha256_fingerprint: String },
+    /// pair with another device by comparing a short code
+    Pair,
+    /// confirm the pairing with a device after comparing the code
+    ConfirmPairing { sha256_fingerprint: String },
     /// save configuration to file
     SaveConfig,
 }
@@ -80,8 +138,81 @@ pub async fn run(args: CliArgs) -> Result<(), CliError> {
     Ok(())
 }
 
+/// connection to the service
+struct Service {
+    rx: AsyncFrontendEventReader,
+    tx: AsyncFrontendRequestWriter,
+}
+
+impl Service {
+    async fn connect() -> Result<Self, CliError> {
+        let (rx, tx) = connect_async(Some(Duration::from_millis(500))).await?;
+        Ok(Self { rx, tx })
+    }
+
+    /// send the request and wait for its response
+    async fn request(&mut self, request: FrontendRequest) -> Result<(), CliError> {
+        let id = self.tx.request(request).await?;
+        self.response(id).await?;
+        Ok(())
+    }
+
+    /// send a request creating a client and return the handle of the client
+    async fn create(&mut self, request: FrontendRequest) -> Result<ClientHandle, CliError> {
+        let id = self.tx.request(request).await?;
+        match self.response(id).await? {
+            Response::Created(handle) => Ok(handle),
+            response => Err(CliError::UnexpectedResponse(response)),
+        }
+    }
+
+    /// wait for the response to the request with the given id
+    async fn response(&mut self, id: RequestId) -> Result<Response, CliError> {
+        while let Some(message) = self.rx.next().await {
+            if let FrontendMessage::Response(response) = message? {
+                if response.id == id {
+                    return Ok(response.response?);
+                }
+            }
+        }
+        Err(CliError::ConnectionClosed)
+    }
+
+    /// send the request and return the first event selected by `f`,
+    /// which is broadcast before the response to the request
+    async fn request_event<T>(
+        &mut self,
+        request: FrontendRequest,
+        mut f: impl FnMut(FrontendEvent) -> Option<T>,
+    ) -> Result<T, CliError> {
+        let id = self.tx.request(request).await?;
+        let mut selected = None;
+        while let Some(message) = self.rx.next().await {
+            match message? {
+                FrontendMessage::Event(event) if selected.is_none() => selected = f(event),
+                FrontendMessage::Response(response) if response.id == id => {
+                    response.response?;
+                    return selected.ok_or(CliError::ConnectionClosed);
+                }
+                _ => {}
+            }
+        }
+        Err(CliError::ConnectionClosed)
+    }
+
+    /// wait for the next event
+    async fn event(&mut self) -> Result<FrontendEvent, CliError> {
+        while let Some(message) = self.rx.next().await {
+            if let FrontendMessage::Event(event) = message? {
+                return Ok(event);
+            }
+        }
+        Err(CliError::ConnectionClosed)
+    }
+}
+
 async fn execute(cmd: CliSubcommand) -> Result<(), CliError> {
-    let (mut rx, mut tx) = connect_async(Some(Duration::from_millis(500))).await?;
+    let mut service = Service::connect().await?;
     match cmd {
         CliSubcommand::AddClient(Client {
             hostname,
@@ -89,82 +220,210 @@ async fn execute(cmd: CliSubcommand) -> Result<(), CliError> {
             ips,
             enter_hook,
         }) => {
-            tx.request(FrontendRequest::Create).await?;
-            while let Some(e) = rx.next().await {
-                if let FrontendEvent::Created(handle, _, _) = e? {
-                    if let Some(hostname) = hostname {
-                        tx.request(FrontendRequest::UpdateHostname(handle, Some(hostname)))
-                            .await?;
-                    }
-                    if let Some(port) = port {
-                        tx.request(FrontendRequest::UpdatePort(handle, port))
-                            .await?;
-                    }
-                    if let Some(ips) = ips {
-                        tx.request(FrontendRequest::UpdateFixIps(handle, ips))
-                            .await?;
-                    }
-                    if let Some(enter_hook) = enter_hook {
-                        tx.request(FrontendRequest::UpdateEnterHook(handle, Some(enter_hook)))
-                            .await?;
-                    }
-                    break;
-                }
+            let handle = service.create(FrontendRequest::Create).await?;
+            if let Some(hostname) = hostname {
+                service
+                    .request(FrontendRequest::UpdateHostname(handle, Some(hostname)))
+                    .await?;
+            }
+            if let Some(port) = port {
+                service
+                    .request(FrontendRequest::UpdatePort(handle, port))
+                    .await?;
+            }
+            if let Some(ips) = ips {
+                service
+                    .request(FrontendRequest::UpdateFixIps(handle, ips))
+                    .await?;
             }
+            if let Some(enter_hook) = enter_hook {
+                let request =
+                    FrontendRequest::UpdateHook(handle, HookEvent::Enter, Some(enter_hook));
+                service.request(request).await?;
+            }
+            println!("added client {handle}");
+        }
+        CliSubcommand::RemoveClient { id } => service.request(FrontendRequest::Delete(id)).await?,
+        CliSubcommand::Activate { id } => {
+            service.request(FrontendRequest::Activate(id, true)).await?
         }
-        CliSubcommand::RemoveClient { id } => tx.request(FrontendRequest::Delete(id)).await?,
-        CliSubcommand::Activate { id } => tx.request(FrontendRequest::Activate(id, true)).await?,
         CliSubcommand::Deactivate { id } => {
-            tx.request(FrontendRequest::Activate(id, false)).await?
+            service
+                .request(FrontendRequest::Activate(id, false))
+                .await?
         }
         CliSubcommand::List => {
-            tx.request(FrontendRequest::Enumerate()).await?;
-            while let Some(e) = rx.next().await {
-                if let FrontendEvent::Enumerate(clients) = e? {
-                    for (handle, config, state) in clients {
-                        let host = config.hostname.unwrap_or("unknown".to_owned());
-                        let port = config.port;
-                        let pos = config.pos;
-                        let active = state.active;
-                        let ips = state.ips;
-                        println!(
-                            "id {handle}: {host}:{port} ({pos}) active: {active}, ips: {ips:?}"
-                        );
-                    }
-                    break;
+            let clients = service
+                .request_event(FrontendRequest::Enumerate(), |e| match e {
+                    FrontendEvent::Enumerate(clients) => Some(clients),
+                    _ => None,
+                })
+                .await?;
+            for (handle, config, state) in clients {
+                let host = config.hostname.unwrap_or("unknown".to_owned());
+                let port = config.port;
+                let pos = match config.segment {
+                    Some(segment) => format!("{} {segment}", config.pos),
+                    None => config.pos.to_string(),
+                };
+                let active = state.active;
+                let ips = state.ips;
+                print!("id {handle}: {host}:{port} ({pos}) active: {active}, ips: {ips:?}");
+                if let Some(version) = state.protocol_version {
+                    let capabilities = state
+                        .capabilities
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    print!(", protocol version: {version}, capabilities: [{capabilities}]");
                 }
+                println!();
             }
         }
+        CliSubcommand::ListPeers => {
+            let peers = service
+                .request_event(FrontendRequest::EnumeratePeers, |e| match e {
+                    FrontendEvent::EnumeratePeers(peers) => Some(peers),
+                    _ => None,
+                })
+                .await?;
+            for peer in peers {
+                let name = peer.name;
+                let host = peer.hostname;
+                let port = peer.port;
+                let ips = peer.ips;
+                let fingerprint = peer.fingerprint.unwrap_or("unknown".to_owned());
+                println!("{name}: {host}:{port}, ips: {ips:?}, fingerprint: {fingerprint}");
+            }
+        }
+        CliSubcommand::AddPeer { name, pos } => {
+            let handle = service
+                .create(FrontendRequest::AddDiscoveredPeer(name, pos))
+                .await?;
+            println!("added client {handle}");
+        }
         CliSubcommand::SetHost { id, host } => {
-            tx.request(FrontendRequest::UpdateHostname(id, host))
+            service
+                .request(FrontendRequest::UpdateHostname(id, host))
                 .await?
         }
         CliSubcommand::SetPort { id, port } => {
-            tx.request(FrontendRequest::UpdatePort(id, port)).await?
+            service
+                .request(FrontendRequest::UpdatePort(id, port))
+                .await?
         }
         CliSubcommand::SetPosition { id, pos } => {
-            tx.request(FrontendRequest::UpdatePosition(id, pos)).await?
+            service
+                .request(FrontendRequest::UpdatePosition(id, pos))
+                .await?
+        }
+        CliSubcommand::SetSegment { id, offset, length } => {
+            let segment = offset
+                .zip(length)
+                .map(|(offset, length)| EdgeSegment { offset, length });
+            service
+                .request(FrontendRequest::UpdateSegment(id, segment))
+                .await?
         }
         CliSubcommand::SetIps { id, ips } => {
-            tx.request(FrontendRequest::UpdateFixIps(id, ips)).await?
+            service
+                .request(FrontendRequest::UpdateFixIps(id, ips))
+                .await?
+        }
+        CliSubcommand::SetMotionMultiplier { id, multiplier } => {
+            service
+                .request(FrontendRequest::UpdateMotionMultiplier(
+                    id,
+                    multiplier.into(),
+                ))
+                .await?
+        }
+        CliSubcommand::SetAcceleration { id, acceleration } => {
+            service
+                .request(FrontendRequest::UpdateAcceleration(id, acceleration.into()))
+                .await?
         }
-        CliSubcommand::EnableCapture => tx.request(FrontendRequest::EnableCapture).await?,
-        CliSubcommand::EnableEmulation => tx.request(FrontendRequest::EnableEmulation).await?,
+        CliSubcommand::SetScrollMultiplier { id, multiplier } => {
+            service
+                .request(FrontendRequest::UpdateScrollMultiplier(
+                    id,
+                    multiplier.into(),
+                ))
+                .await?
+        }
+        CliSubcommand::SetNaturalScroll { id, enabled } => {
+            service
+                .request(FrontendRequest::UpdateNaturalScroll(id, enabled))
+                .await?
+        }
+        CliSubcommand::SetHook { id, event, command } => {
+            service
+                .request(FrontendRequest::UpdateHook(id, event, command))
+                .await?
+        }
+        CliSubcommand::SetFingerprint {
+            id,
+            sha256_fingerprint,
+        } => {
+            service
+                .request(FrontendRequest::UpdateFingerprint(id, sha256_fingerprint))
+                .await?
+        }
+        CliSubcommand::Capture { id } => service.request(FrontendRequest::Capture(id)).await?,
+        CliSubcommand::EnableCapture => service.request(FrontendRequest::EnableCapture).await?,
+        CliSubcommand::EnableEmulation => service.request(FrontendRequest::EnableEmulation).await?,
         CliSubcommand::AuthorizeKey {
             description,
             sha256_fingerprint,
         } => {
-            tx.request(FrontendRequest::AuthorizeKey(
-                description,
-                sha256_fingerprint,
-            ))
-            .await?
+            service
+                .request(FrontendRequest::AuthorizeKey(
+                    description,
+                    sha256_fingerprint,
+                ))
+                .await?
         }
         CliSubcommand::RemoveAuthorizedKey { sha256_fingerprint } => {
-            tx.request(FrontendRequest::RemoveAuthorizedKey(sha256_fingerprint))
+            service
+                .request(FrontendRequest::RemoveAuthorizedKey(sha256_fingerprint))
+                .await?
+        }
+        CliSubcommand::Pair => {
+            service.request(FrontendRequest::StartPairing).await?;
+            println!("pairing mode enabled, start pairing on the other device as well");
+            println!("and move the cursor from the sending device to the receiving one");
+            let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+            loop {
+                match service.event().await? {
+                    FrontendEvent::PairingCode { fingerprint, code } => {
+                        println!("device {fingerprint} wants to pair, code: {code}");
+                        print!("does the code match the one shown on the other device? [y/N] ");
+                        io::stdout().flush()?;
+                        let answer = stdin.next_line().await?.unwrap_or_default();
+                        if answer.trim().eq_ignore_ascii_case("y") {
+                            service
+                                .request(FrontendRequest::ConfirmPairing(fingerprint))
+                                .await?;
+                            println!("paired successfully");
+                            break;
+                        }
+                    }
+                    FrontendEvent::PairingStatus(Status::Disabled) => {
+                        eprintln!("pairing timed out");
+                        break;
+                    }
+                    FrontendEvent::Error(e) => eprintln!("{e}"),
+                    _ => {}
+                }
+            }
+        }
+        CliSubcommand::ConfirmPairing { sha256_fingerprint } => {
+            service
+                .request(FrontendRequest::ConfirmPairing(sha256_fingerprint))
                 .await?
         }
-        CliSubcommand::SaveConfig => tx.request(FrontendRequest::SaveConfiguration).await?,
+        CliSubcommand::SaveConfig => service.request(FrontendRequest::SaveConfiguration).await?,
     }
     Ok(())
 }
@@ -0,0 +1,13 @@
+fn main() {
+    let unix = cfg!(unix);
+    let macos = cfg!(target_os = "macos");
+    let data_control = cfg!(feature = "data_control");
+
+    let data_control = unix && !macos && data_control;
+
+    println!("cargo::rustc-check-cfg=cfg(data_control)");
+
+    if data_control {
+        println!("cargo::rustc-cfg=data_control");
+    }
+}
@@ -0,0 +1,63 @@
+use std::io::Read;
+
+use async_trait::async_trait;
+use tokio::task::spawn_blocking;
+use wl_clipboard_rs::{
+    copy::{self, Options, Source},
+    paste::{self, ClipboardType, Error as PasteError, Seat, get_contents},
+};
+
+use crate::{ClipboardError, error::DataControlClipboardCreationError};
+
+use super::Clipboard;
+
+/// clipboard access through the wlr-data-control / ext-data-control
+/// protocols, which (unlike wl_data_device) do not require keyboard focus
+pub(crate) struct DataControlClipboard;
+
+impl DataControlClipboard {
+    pub(crate) async fn new() -> Result<Self, DataControlClipboardCreationError> {
+        // probe for the required protocols
+        spawn_blocking(read_clipboard).await.expect("join error")?;
+        Ok(Self)
+    }
+}
+
+/// blocking read of the clipboard content
+fn read_clipboard() -> Result<Option<String>, ClipboardError> {
+    let (mut pipe, _mime_type) = match get_contents(
+        ClipboardType::Regular,
+        Seat::Unspecified,
+        paste::MimeType::Text,
+    ) {
+        Ok(c) => c,
+        Err(PasteError::NoSeats | PasteError::ClipboardEmpty | PasteError::NoMimeType) => {
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut content = vec![];
+    pipe.read_to_end(&mut content)?;
+    Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+}
+
+#[async_trait]
+impl Clipboard for DataControlClipboard {
+    async fn get_text(&self) -> Result<Option<String>, ClipboardError> {
+        spawn_blocking(read_clipboard).await.expect("join error")
+    }
+
+    async fn set_text(&self, text: String) -> Result<(), ClipboardError> {
+        // the copy is served from a background thread until
+        // the clipboard is replaced by another application
+        spawn_blocking(move || {
+            Options::new().copy(
+                Source::Bytes(text.into_bytes().into_boxed_slice()),
+                copy::MimeType::Text,
+            )
+        })
+        .await
+        .expect("join error")?;
+        Ok(())
+    }
+}
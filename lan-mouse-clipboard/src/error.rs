@@ -0,0 +1,29 @@
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ClipboardError {
+    #[error("io error: `{0}`")]
+    Io(#[from] std::io::Error),
+    #[cfg(data_control)]
+    #[error("wlr-data-control: `{0}`")]
+    Paste(#[from] wl_clipboard_rs::paste::Error),
+    #[cfg(data_control)]
+    #[error("wlr-data-control: `{0}`")]
+    Copy(#[from] wl_clipboard_rs::copy::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ClipboardCreationError {
+    #[cfg(data_control)]
+    #[error("wlr-data-control backend: `{0}`")]
+    DataControl(#[from] DataControlClipboardCreationError),
+    #[error("no backend available")]
+    NoAvailableBackend,
+}
+
+#[cfg(data_control)]
+#[derive(Debug, Error)]
+pub enum DataControlClipboardCreationError {
+    #[error(transparent)]
+    Clipboard(#[from] ClipboardError),
+}
@@ -0,0 +1,79 @@
+use async_trait::async_trait;
+use std::fmt::Display;
+
+pub use error::{ClipboardCreationError, ClipboardError};
+
+#[cfg(data_control)]
+mod data_control;
+
+/// in-memory clipboard (not shared with other applications)
+mod memory;
+
+mod error;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backend {
+    #[cfg(data_control)]
+    DataControl,
+    Memory,
+}
+
+impl Display for Backend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(data_control)]
+            Backend::DataControl => write!(f, "wlr-data-control"),
+            Backend::Memory => write!(f, "memory"),
+        }
+    }
+}
+
+/// Access to the text content of the clipboard
+#[async_trait]
+pub trait Clipboard {
+    /// current text content of the clipboard, `None` if the
+    /// clipboard is empty or does not contain any text
+    async fn get_text(&self) -> Result<Option<String>, ClipboardError>;
+
+    /// replace the content of the clipboard with the given text
+    async fn set_text(&self, text: String) -> Result<(), ClipboardError>;
+}
+
+async fn with_backend(backend: Backend) -> Result<Box<dyn Clipboard>, ClipboardCreationError> {
+    let clipboard: Box<dyn Clipboard> = match backend {
+        #[cfg(data_control)]
+        Backend::DataControl => Box::new(data_control::DataControlClipboard::new().await?),
+        Backend::Memory => Box::new(memory::MemoryClipboard::default()),
+    };
+    Ok(clipboard)
+}
+
+/// create a clipboard using the given backend or
+/// the first available one, if `backend` is `None`
+pub async fn create(
+    backend: Option<Backend>,
+) -> Result<Box<dyn Clipboard>, ClipboardCreationError> {
+    if let Some(backend) = backend {
+        let b = with_backend(backend).await;
+        if b.is_ok() {
+            log::info!("using clipboard backend: {backend}");
+        }
+        return b;
+    }
+
+    for backend in [
+        #[cfg(data_control)]
+        Backend::DataControl,
+        Backend::Memory,
+    ] {
+        match with_backend(backend).await {
+            Ok(b) => {
+                log::info!("using clipboard backend: {backend}");
+                return Ok(b);
+            }
+            Err(e) => log::warn!("{e}"),
+        }
+    }
+
+    Err(ClipboardCreationError::NoAvailableBackend)
+}
@@ -0,0 +1,23 @@
+use std::sync::Mutex;
+
+use async_trait::async_trait;
+
+use crate::{Clipboard, ClipboardError};
+
+#[derive(Default)]
+pub(crate) struct MemoryClipboard {
+    text: Mutex<Option<String>>,
+}
+
+#[async_trait]
+impl Clipboard for MemoryClipboard {
+    async fn get_text(&self) -> Result<Option<String>, ClipboardError> {
+        Ok(self.text.lock().expect("lock").clone())
+    }
+
+    async fn set_text(&self, text: String) -> Result<(), ClipboardError> {
+        log::debug!("clipboard: {} bytes", text.len());
+        self.text.lock().expect("lock").replace(text);
+        Ok(())
+    }
+}
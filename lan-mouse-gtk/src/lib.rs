@@ -302,6 +302,32 @@ fn build_ui(app: &Application) {
                     FrontendEvent::IncomingDisconnected(addr) => {
                         window.show_toast(format!("{addr} disconnected").as_str());
                     }
+                    FrontendEvent::FingerprintMismatch {
+                        handle,
+                        expected: _,
+                        actual,
+                    } => window.fingerprint_mismatch(handle, actual),
+                    FrontendEvent::PeerDiscovered(peer) => window.peer_discovered(peer),
+                    FrontendEvent::PeerRemoved(_) | FrontendEvent::EnumeratePeers(_) => {}
+                    FrontendEvent::PairingStatus(s) => {
+                        if !bool::from(s) {
+                            window.show_toast("pairing finished");
+                        }
+                    }
+                    FrontendEvent::PairingCode { fingerprint, code } => {
+                        window.pairing_code(fingerprint, code)
+                    }
+                    FrontendEvent::HookFinished {
+                        event,
+                        handle: _,
+                        command,
+                        status,
+                    } => {
+                        if !status.success() {
+                            window
+                                .show_toast(format!("{event} hook `{command}` {status}").as_str());
+                        }
+                    }
                 }
             }
         }
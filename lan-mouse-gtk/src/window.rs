@@ -11,8 +11,8 @@ use gtk::{
 };
 
 use lan_mouse_ipc::{
-    ClientConfig, ClientHandle, ClientState, DEFAULT_PORT, FrontendRequest, FrontendRequestWriter,
-    Position,
+    ClientConfig, ClientHandle, ClientState, DEFAULT_PORT, DiscoveredPeer, FrontendRequest,
+    FrontendRequestWriter, Position,
 };
 
 use crate::{
@@ -541,6 +541,61 @@ impl Window {
         self.imp().fingerprint_row.set_subtitle(fingerprint);
     }
 
+    pub(super) fn fingerprint_mismatch(&self, handle: ClientHandle, actual: String) {
+        let toast = adw::Toast::builder()
+            .title(format!(
+                "client {handle} presented an unknown certificate: {actual}"
+            ))
+            .button_label("Trust")
+            .timeout(0)
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.request(FrontendRequest::UpdateFingerprint(
+                    handle,
+                    Some(actual.clone()),
+                ));
+            }
+        ));
+        self.add_toast(toast);
+    }
+
+    pub(super) fn pairing_code(&self, fingerprint: String, code: String) {
+        let toast = adw::Toast::builder()
+            .title(format!("pairing request, code: {code}"))
+            .button_label("Confirm")
+            .timeout(0)
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.request(FrontendRequest::ConfirmPairing(fingerprint.clone()));
+            }
+        ));
+        self.add_toast(toast);
+    }
+
+    pub(super) fn peer_discovered(&self, peer: DiscoveredPeer) {
+        let toast = adw::Toast::builder()
+            .title(format!("discovered {} ({})", peer.name, peer.hostname))
+            .button_label("Add")
+            .build();
+        toast.connect_button_clicked(clone!(
+            #[weak(rename_to = window)]
+            self,
+            move |_| {
+                window.request(FrontendRequest::AddDiscoveredPeer(
+                    peer.name.clone(),
+                    Position::default(),
+                ));
+            }
+        ));
+        self.add_toast(toast);
+    }
+
     pub(super) fn request_authorization(&self, fingerprint: &str) {
         if let Some(w) = self.imp().authorization_window.borrow_mut().take() {
             w.close();
@@ -1,4 +1,7 @@
-use crate::{ConnectionError, FrontendEvent, FrontendRequest, IpcError};
+use crate::{
+    ConnectionError, FrontendMessage, FrontendRequest, IPC_VERSION, IpcError, RequestEnvelope,
+    RequestId,
+};
 use std::{
     cmp::min,
     task::{Poll, ready},
@@ -27,10 +30,12 @@ pub struct AsyncFrontendRequestWriter {
     tx: WriteHalf<UnixStream>,
     #[cfg(windows)]
     tx: WriteHalf<TcpStream>,
+    /// id of the next request
+    next_id: RequestId,
 }
 
 impl Stream for AsyncFrontendEventReader {
-    type Item = Result<FrontendEvent, IpcError>;
+    type Item = Result<FrontendMessage, IpcError>;
 
     fn poll_next(
         mut self: std::pin::Pin<&mut Self>,
@@ -46,12 +51,20 @@ impl Stream for AsyncFrontendEventReader {
 }
 
 impl AsyncFrontendRequestWriter {
-    pub async fn request(&mut self, request: FrontendRequest) -> Result<(), IpcError> {
-        let mut json = serde_json::to_string(&request).unwrap();
+    /// send the request, its response carries the returned id
+    pub async fn request(&mut self, request: FrontendRequest) -> Result<RequestId, IpcError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let envelope = RequestEnvelope {
+            version: IPC_VERSION,
+            id,
+            request,
+        };
+        let mut json = serde_json::to_string(&envelope).unwrap();
         log::debug!("requesting: {json}");
         json.push('\n');
         self.tx.write_all(json.as_bytes()).await?;
-        Ok(())
+        Ok(id)
     }
 }
 
@@ -74,7 +87,7 @@ pub async fn connect_async(
     let lines = buf_reader.lines();
     let lines_stream = LinesStream::new(lines);
     let reader = AsyncFrontendEventReader { lines_stream };
-    let writer = AsyncFrontendRequestWriter { tx };
+    let writer = AsyncFrontendRequestWriter { tx, next_id: 0 };
     Ok((reader, writer))
 }
 
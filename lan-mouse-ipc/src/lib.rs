@@ -22,7 +22,7 @@ mod listen;
 
 pub use connect::{FrontendEventReader, FrontendRequestWriter, connect};
 pub use connect_async::{AsyncFrontendEventReader, AsyncFrontendRequestWriter, connect_async};
-pub use listen::AsyncFrontendListener;
+pub use listen::{AsyncFrontendListener, IncomingRequest, ResponseToken};
 
 #[derive(Debug, Error)]
 pub enum ConnectionError {
@@ -58,6 +58,73 @@ pub enum IpcError {
 
 pub const DEFAULT_PORT: u16 = 4242;
 
+/// version of the [`RequestEnvelope`] and [`ResponseEnvelope`]
+pub const IPC_VERSION: u32 = 1;
+
+/// identifies a request and its response, chosen by the frontend
+pub type RequestId = u64;
+
+/// A request expecting a [`ResponseEnvelope`].
+/// Requests sent without an envelope do not get a response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestEnvelope {
+    pub version: u32,
+    pub id: RequestId,
+    pub request: FrontendRequest,
+}
+
+/// Response to a [`RequestEnvelope`], sent only to the requesting frontend
+/// after the events caused by the request have been broadcast.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseEnvelope {
+    pub version: u32,
+    pub id: RequestId,
+    pub response: Result<Response, RequestError>,
+}
+
+/// result of a successful request
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Response {
+    /// the request was carried out
+    #[default]
+    Done,
+    /// a client was created by [`FrontendRequest::Create`]
+    /// or [`FrontendRequest::AddDiscoveredPeer`]
+    Created(ClientHandle),
+}
+
+/// message sent by the service to a frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
+pub enum FrontendMessage {
+    /// response to a request of this frontend
+    Response(ResponseEnvelope),
+    /// state update broadcast to all frontends
+    Event(FrontendEvent),
+}
+
+/// reason a request failed
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum RequestError {
+    #[error("no such client: {0}")]
+    NoSuchClient(ClientHandle),
+    #[error("client {0} is not active")]
+    ClientNotActive(ClientHandle),
+    #[error("no such peer: {0}")]
+    NoSuchPeer(String),
+    #[error("no pairing request from {0}")]
+    NoPairingRequest(String),
+    #[error("invalid argument: {0}")]
+    InvalidArgument(String),
+    #[error("failed to change port: {0}")]
+    PortChange(String),
+    #[error("invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("unsupported ipc version {0} (expected {IPC_VERSION})")]
+    UnsupportedVersion(u32),
+}
+
 #[derive(Debug, Default, Eq, Hash, PartialEq, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum Position {
@@ -128,6 +195,88 @@ impl TryFrom<&str> for Position {
     }
 }
 
+/// Part of an edge claimed by a client, in pixels from the
+/// top end (left / right) or the left end (top / bottom) of the edge
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct EdgeSegment {
+    pub offset: u32,
+    pub length: u32,
+}
+
+impl EdgeSegment {
+    /// whether the two segments share a part of the edge
+    pub fn overlaps(&self, other: &EdgeSegment) -> bool {
+        let (start, end) = (self.offset as u64, self.offset as u64 + self.length as u64);
+        let (other_start, other_end) = (
+            other.offset as u64,
+            other.offset as u64 + other.length as u64,
+        );
+        start < other_end && other_start < end
+    }
+}
+
+impl Display for EdgeSegment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}+{}", self.offset, self.length)
+    }
+}
+
+/// pointer speed and scroll settings applied to events sent to a client
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct PointerSettings {
+    /// factor applied to pointer motion
+    pub motion_multiplier: f64,
+    /// pointer acceleration, the gain grows with the distance
+    /// moved per event (`0.0` disables acceleration)
+    pub acceleration: f64,
+    /// factor applied to scroll events
+    pub scroll_multiplier: f64,
+    /// invert the scroll direction
+    pub natural_scroll: bool,
+}
+
+impl Default for PointerSettings {
+    fn default() -> Self {
+        Self {
+            motion_multiplier: 1.0,
+            acceleration: 0.0,
+            scroll_multiplier: 1.0,
+            natural_scroll: false,
+        }
+    }
+}
+
+/// Value of a pointer setting in a [`FrontendRequest`].
+/// It is compared by its bit pattern, so requests remain `Eq`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Factor(pub f64);
+
+impl PartialEq for Factor {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.to_bits() == other.0.to_bits()
+    }
+}
+
+impl Eq for Factor {}
+
+impl From<f64> for Factor {
+    fn from(value: f64) -> Self {
+        Self(value)
+    }
+}
+
+impl PointerSettings {
+    /// multipliers must be positive, acceleration must not be negative
+    pub fn is_valid(&self) -> bool {
+        let positive = |f: f64| f.is_finite() && f > 0.0;
+        positive(self.motion_multiplier)
+            && positive(self.scroll_multiplier)
+            && self.acceleration.is_finite()
+            && self.acceleration >= 0.0
+    }
+}
+
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ClientConfig {
     /// hostname of this client
@@ -138,8 +287,26 @@ pub struct ClientConfig {
     pub port: u16,
     /// position of a client on screen
     pub pos: Position,
+    /// segment of the edge claimed by the client (`None` = the whole edge)
+    pub segment: Option<EdgeSegment>,
     /// enter hook
     pub cmd: Option<String>,
+    /// command executed when the cursor leaves the client
+    pub leave_hook: Option<String>,
+    /// command executed when a connection to the client is established
+    pub connect_hook: Option<String>,
+    /// command executed when the connection to the client is closed
+    pub disconnect_hook: Option<String>,
+    /// expected certificate fingerprint of the client,
+    /// pinned on the first successful connection
+    pub fingerprint: Option<String>,
+    /// keys (linux scancodes) replaced before sending them to the client
+    pub key_map: HashMap<u32, u32>,
+    /// mouse buttons (linux event codes) replaced before sending them to the client
+    pub button_map: HashMap<u32, u32>,
+    /// keys (linux scancodes) capturing input for the client directly,
+    /// empty if there is no hotkey
+    pub hotkey: Vec<u32>,
 }
 
 impl Default for ClientConfig {
@@ -149,13 +316,136 @@ impl Default for ClientConfig {
             hostname: Default::default(),
             fix_ips: Default::default(),
             pos: Default::default(),
+            segment: None,
             cmd: None,
+            leave_hook: None,
+            connect_hook: None,
+            disconnect_hook: None,
+            fingerprint: None,
+            key_map: Default::default(),
+            button_map: Default::default(),
+            hotkey: Default::default(),
         }
     }
 }
 
 pub type ClientHandle = u64;
 
+/// event triggering a hook command
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookEvent {
+    /// the cursor entered a client
+    Enter,
+    /// the cursor left a client
+    Leave,
+    /// a connection to a client was established
+    Connect,
+    /// the connection to a client was closed
+    Disconnect,
+    /// a device connected to this one
+    IncomingConnect,
+    /// a device connected to this one disconnected
+    IncomingDisconnect,
+}
+
+impl HookEvent {
+    /// whether the hook is configured per client
+    pub fn is_client_hook(&self) -> bool {
+        !matches!(
+            self,
+            HookEvent::IncomingConnect | HookEvent::IncomingDisconnect
+        )
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("not a valid hook event: {event}")]
+pub struct HookEventParseError {
+    event: String,
+}
+
+impl FromStr for HookEvent {
+    type Err = HookEventParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "enter" => Ok(Self::Enter),
+            "leave" => Ok(Self::Leave),
+            "connect" => Ok(Self::Connect),
+            "disconnect" => Ok(Self::Disconnect),
+            "incoming-connect" => Ok(Self::IncomingConnect),
+            "incoming-disconnect" => Ok(Self::IncomingDisconnect),
+            _ => Err(HookEventParseError { event: s.into() }),
+        }
+    }
+}
+
+impl Display for HookEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookEvent::Enter => write!(f, "enter"),
+            HookEvent::Leave => write!(f, "leave"),
+            HookEvent::Connect => write!(f, "connect"),
+            HookEvent::Disconnect => write!(f, "disconnect"),
+            HookEvent::IncomingConnect => write!(f, "incoming-connect"),
+            HookEvent::IncomingDisconnect => write!(f, "incoming-disconnect"),
+        }
+    }
+}
+
+/// how a hook command finished
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub enum HookStatus {
+    /// the command exited with the given exit code
+    Exited(i32),
+    /// the command was terminated by a signal
+    Terminated,
+    /// the command did not finish in time and was killed
+    TimedOut,
+    /// the command could not be executed
+    Failed(String),
+}
+
+impl HookStatus {
+    pub fn success(&self) -> bool {
+        matches!(self, HookStatus::Exited(0))
+    }
+}
+
+impl Display for HookStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HookStatus::Exited(code) => write!(f, "exited with code {code}"),
+            HookStatus::Terminated => write!(f, "terminated by a signal"),
+            HookStatus::TimedOut => write!(f, "timed out"),
+            HookStatus::Failed(e) => write!(f, "failed to execute: {e}"),
+        }
+    }
+}
+
+/// optional protocol feature negotiated with a peer
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Capability {
+    /// clipboard sharing
+    Clipboard,
+    /// absolute pointer motion
+    AbsoluteMotion,
+    /// high-resolution scroll events
+    HighResScroll,
+}
+
+impl Display for Capability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Capability::Clipboard => write!(f, "clipboard"),
+            Capability::AbsoluteMotion => write!(f, "absolute-motion"),
+            Capability::HighResScroll => write!(f, "high-res-scroll"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct ClientState {
     /// events should be sent to and received from the client
@@ -182,6 +472,29 @@ pub struct ClientState {
     /// that predates the Hello event. The frontend uses this to
     /// soft-warn on version mismatch.
     pub peer_commit: Option<[u8; 8]>,
+    /// protocol version announced by the peer in its [`Hello`].
+    /// `Some(0)` for peers predating version negotiation.
+    pub protocol_version: Option<u16>,
+    /// optional protocol features supported by both ends
+    pub capabilities: Vec<Capability>,
+    /// pointer speed and scroll settings
+    /// (not part of the [`ClientConfig`], which is `Eq`)
+    pub pointer: PointerSettings,
+}
+
+/// another lan-mouse instance discovered on the local network
+#[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DiscoveredPeer {
+    /// DNS-SD instance name, unique on the local network
+    pub name: String,
+    /// hostname of the peer
+    pub hostname: String,
+    /// advertised ip addresses
+    pub ips: Vec<IpAddr>,
+    /// listen port
+    pub port: u16,
+    /// advertised certificate fingerprint
+    pub fingerprint: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -223,6 +536,33 @@ pub enum FrontendEvent {
     IncomingDisconnected(SocketAddr),
     /// failed connection attempt (approval for fingerprint required)
     ConnectionAttempt { fingerprint: String },
+    /// the client presented a certificate that does not match
+    /// the pinned fingerprint, the connection was refused
+    FingerprintMismatch {
+        handle: ClientHandle,
+        expected: String,
+        actual: String,
+    },
+    /// another instance was discovered on the local network
+    PeerDiscovered(DiscoveredPeer),
+    /// a discovered peer is no longer available
+    PeerRemoved(String),
+    /// list of all discovered peers
+    EnumeratePeers(Vec<DiscoveredPeer>),
+    /// pairing mode (enabled / disabled)
+    PairingStatus(Status),
+    /// a device with an unknown certificate connected while pairing.
+    /// The code is shown on both devices and must match before
+    /// the pairing is confirmed.
+    PairingCode { fingerprint: String, code: String },
+    /// a hook command finished
+    HookFinished {
+        event: HookEvent,
+        /// the client the hook belongs to, `None` for incoming hooks
+        handle: Option<ClientHandle>,
+        command: String,
+        status: HookStatus,
+    },
 }
 
 #[derive(Debug, Eq, PartialEq, Clone, Serialize, Deserialize)]
@@ -245,8 +585,20 @@ pub enum FrontendRequest {
     UpdatePort(ClientHandle, u16),
     /// update position
     UpdatePosition(ClientHandle, Position),
+    /// update the claimed segment of the edge
+    UpdateSegment(ClientHandle, Option<EdgeSegment>),
     /// update fix-ips
     UpdateFixIps(ClientHandle, Vec<IpAddr>),
+    /// update the factor applied to pointer motion
+    UpdateMotionMultiplier(ClientHandle, Factor),
+    /// update the pointer acceleration
+    UpdateAcceleration(ClientHandle, Factor),
+    /// update the factor applied to scroll events
+    UpdateScrollMultiplier(ClientHandle, Factor),
+    /// invert the scroll direction
+    UpdateNaturalScroll(ClientHandle, bool),
+    /// capture input for the client directly, as if its hotkey was pressed
+    Capture(ClientHandle),
     /// request reenabling input capture
     EnableCapture,
     /// request reenabling input emulation
@@ -257,10 +609,62 @@ pub enum FrontendRequest {
     AuthorizeKey(String, String),
     /// remove fingerprint (fingerprint)
     RemoveAuthorizedKey(String),
-    /// change the hook command
-    UpdateEnterHook(u64, Option<String>),
+    /// change the command executed on the given event
+    UpdateHook(ClientHandle, HookEvent, Option<String>),
+    /// change the enter hook, same as [`FrontendRequest::UpdateHook`]
+    /// with [`HookEvent::Enter`] (kept for existing frontends)
+    UpdateEnterHook(ClientHandle, Option<String>),
+    /// change the expected certificate fingerprint of a client
+    /// (`None` pins the fingerprint on the next connection)
+    UpdateFingerprint(ClientHandle, Option<String>),
+    /// request an enumeration of all discovered peers
+    EnumeratePeers,
+    /// create a client from a discovered peer (instance name, position)
+    AddDiscoveredPeer(String, Position),
     /// save config file
     SaveConfiguration,
+    /// temporarily accept unknown certificates to pair with another device
+    StartPairing,
+    /// authorize the device with the given fingerprint,
+    /// after its pairing code has been compared (fingerprint)
+    ConfirmPairing(String),
+}
+
+impl FrontendRequest {
+    /// the client the request refers to
+    pub fn client(&self) -> Option<ClientHandle> {
+        match self {
+            FrontendRequest::Activate(handle, _)
+            | FrontendRequest::Delete(handle)
+            | FrontendRequest::ResolveDns(handle)
+            | FrontendRequest::UpdateHostname(handle, _)
+            | FrontendRequest::UpdatePort(handle, _)
+            | FrontendRequest::UpdatePosition(handle, _)
+            | FrontendRequest::UpdateSegment(handle, _)
+            | FrontendRequest::UpdateFixIps(handle, _)
+            | FrontendRequest::UpdateMotionMultiplier(handle, _)
+            | FrontendRequest::UpdateAcceleration(handle, _)
+            | FrontendRequest::UpdateScrollMultiplier(handle, _)
+            | FrontendRequest::UpdateNaturalScroll(handle, _)
+            | FrontendRequest::Capture(handle)
+            | FrontendRequest::UpdateHook(handle, _, _)
+            | FrontendRequest::UpdateEnterHook(handle, _)
+            | FrontendRequest::UpdateFingerprint(handle, _) => Some(*handle),
+            FrontendRequest::Create
+            | FrontendRequest::ChangePort(_)
+            | FrontendRequest::Enumerate()
+            | FrontendRequest::EnableCapture
+            | FrontendRequest::EnableEmulation
+            | FrontendRequest::Sync
+            | FrontendRequest::AuthorizeKey(_, _)
+            | FrontendRequest::RemoveAuthorizedKey(_)
+            | FrontendRequest::EnumeratePeers
+            | FrontendRequest::AddDiscoveredPeer(_, _)
+            | FrontendRequest::SaveConfiguration
+            | FrontendRequest::StartPairing
+            | FrontendRequest::ConfirmPairing(_) => None,
+        }
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
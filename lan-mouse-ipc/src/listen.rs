@@ -1,4 +1,5 @@
-use futures::{Stream, StreamExt, stream::SelectAll};
+use futures::{Stream, StreamExt};
+use serde::{Deserialize, de::IgnoredAny};
 #[cfg(unix)]
 use std::path::PathBuf;
 use std::{
@@ -8,7 +9,7 @@ use std::{
 };
 
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
-use tokio_stream::wrappers::LinesStream;
+use tokio_stream::{StreamMap, wrappers::LinesStream};
 
 #[cfg(unix)]
 use tokio::net::UnixListener;
@@ -20,7 +21,36 @@ use tokio::net::TcpListener;
 #[cfg(windows)]
 use tokio::net::TcpStream;
 
-use crate::{FrontendEvent, FrontendRequest, IpcError, IpcListenerCreationError};
+use crate::{
+    FrontendEvent, FrontendRequest, IPC_VERSION, IpcError, IpcListenerCreationError,
+    RequestEnvelope, RequestError, RequestId, Response, ResponseEnvelope,
+};
+
+/// a request received from a frontend
+#[derive(Debug)]
+pub struct IncomingRequest {
+    /// where to send the response, `None` if the frontend does not expect one
+    pub token: Option<ResponseToken>,
+    /// the request or the reason it could not be decoded
+    pub request: Result<FrontendRequest, RequestError>,
+}
+
+/// identifies the connection and request a response belongs to
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ResponseToken {
+    connection: usize,
+    id: RequestId,
+}
+
+/// [`RequestEnvelope`] with the request ignored,
+/// so that undecodable requests can be responded to
+#[derive(Deserialize)]
+struct RawEnvelope {
+    version: u32,
+    id: RequestId,
+    #[allow(dead_code)]
+    request: IgnoredAny,
+}
 
 pub struct AsyncFrontendListener {
     #[cfg(windows)]
@@ -30,47 +60,63 @@ pub struct AsyncFrontendListener {
     #[cfg(unix)]
     socket_path: PathBuf,
     #[cfg(unix)]
-    line_streams: SelectAll<LinesStream<BufReader<ReadHalf<UnixStream>>>>,
+    line_streams: StreamMap<usize, LinesStream<BufReader<ReadHalf<UnixStream>>>>,
     #[cfg(windows)]
-    line_streams: SelectAll<LinesStream<BufReader<ReadHalf<TcpStream>>>>,
+    line_streams: StreamMap<usize, LinesStream<BufReader<ReadHalf<TcpStream>>>>,
     #[cfg(unix)]
-    tx_streams: Vec<WriteHalf<UnixStream>>,
+    tx_streams: Vec<(usize, WriteHalf<UnixStream>)>,
     #[cfg(windows)]
-    tx_streams: Vec<WriteHalf<TcpStream>>,
+    tx_streams: Vec<(usize, WriteHalf<TcpStream>)>,
+    /// id of the next connection
+    next_connection: usize,
 }
 
 impl AsyncFrontendListener {
+    #[cfg(unix)]
     pub async fn new() -> Result<Self, IpcListenerCreationError> {
-        #[cfg(unix)]
-        let (socket_path, listener) = {
-            let socket_path = crate::default_socket_path()?;
-
-            log::debug!("remove socket: {socket_path:?}");
-            if socket_path.exists() {
-                // try to connect to see if some other instance
-                // of lan-mouse is already running
-                match UnixStream::connect(&socket_path).await {
-                    // connected -> lan-mouse is already running
-                    Ok(_) => return Err(IpcListenerCreationError::AlreadyRunning),
-                    // lan-mouse is not running but a socket was left behind
-                    Err(e) => {
-                        log::debug!("{socket_path:?}: {e} - removing left behind socket");
-                        let _ = std::fs::remove_file(&socket_path);
-                    }
+        Self::bind(crate::default_socket_path()?).await
+    }
+
+    /// listen on the given socket instead of the default one,
+    /// e.g. to run multiple instances of the service
+    #[cfg(unix)]
+    pub async fn bind(socket_path: PathBuf) -> Result<Self, IpcListenerCreationError> {
+        log::debug!("remove socket: {socket_path:?}");
+        if socket_path.exists() {
+            // try to connect to see if some other instance
+            // of lan-mouse is already running
+            match UnixStream::connect(&socket_path).await {
+                // connected -> lan-mouse is already running
+                Ok(_) => return Err(IpcListenerCreationError::AlreadyRunning),
+                // lan-mouse is not running but a socket was left behind
+                Err(e) => {
+                    log::debug!("{socket_path:?}: {e} - removing left behind socket");
+                    let _ = std::fs::remove_file(&socket_path);
                 }
             }
-            let listener = match UnixListener::bind(&socket_path) {
-                Ok(ls) => ls,
-                // some other lan-mouse instance has bound the socket in the meantime
-                Err(e) if e.kind() == ErrorKind::AddrInUse => {
-                    return Err(IpcListenerCreationError::AlreadyRunning);
-                }
-                Err(e) => return Err(IpcListenerCreationError::Bind(e)),
-            };
-            (socket_path, listener)
+        }
+        let listener = match UnixListener::bind(&socket_path) {
+            Ok(ls) => ls,
+            // some other lan-mouse instance has bound the socket in the meantime
+            Err(e) if e.kind() == ErrorKind::AddrInUse => {
+                return Err(IpcListenerCreationError::AlreadyRunning);
+            }
+            Err(e) => return Err(IpcListenerCreationError::Bind(e)),
         };
 
-        #[cfg(windows)]
+        let adapter = Self {
+            listener,
+            socket_path,
+            line_streams: StreamMap::new(),
+            tx_streams: vec![],
+            next_connection: 0,
+        };
+
+        Ok(adapter)
+    }
+
+    #[cfg(windows)]
+    pub async fn new() -> Result<Self, IpcListenerCreationError> {
         let listener = match TcpListener::bind("127.0.0.1:5252").await {
             Ok(ls) => ls,
             // some other lan-mouse instance has bound the socket in the meantime
@@ -82,10 +128,9 @@ impl AsyncFrontendListener {
 
         let adapter = Self {
             listener,
-            #[cfg(unix)]
-            socket_path,
-            line_streams: SelectAll::new(),
+            line_streams: StreamMap::new(),
             tx_streams: vec![],
+            next_connection: 0,
         };
 
         Ok(adapter)
@@ -98,9 +143,9 @@ impl AsyncFrontendListener {
 
         let mut keep = vec![];
         // TODO do simultaneously
-        for tx in self.tx_streams.iter_mut() {
+        for (_, tx) in self.tx_streams.iter_mut() {
             // write len + payload
-            if tx.write(json.as_bytes()).await.is_err() {
+            if tx.write_all(json.as_bytes()).await.is_err() {
                 keep.push(false);
                 continue;
             }
@@ -111,6 +156,65 @@ impl AsyncFrontendListener {
         let mut keep = keep.into_iter();
         self.tx_streams.retain(|_| keep.next().unwrap());
     }
+
+    /// send the response to the frontend the request came from
+    pub async fn respond(
+        &mut self,
+        token: ResponseToken,
+        response: Result<Response, RequestError>,
+    ) {
+        let response = ResponseEnvelope {
+            version: IPC_VERSION,
+            id: token.id,
+            response,
+        };
+        let mut json = serde_json::to_string(&response).unwrap();
+        json.push('\n');
+
+        let Some(idx) = self
+            .tx_streams
+            .iter()
+            .position(|(c, _)| *c == token.connection)
+        else {
+            log::debug!(
+                "frontend disconnected before receiving response {}",
+                token.id
+            );
+            return;
+        };
+        if self.tx_streams[idx]
+            .1
+            .write_all(json.as_bytes())
+            .await
+            .is_err()
+        {
+            self.tx_streams.remove(idx);
+        }
+    }
+}
+
+/// decode a request, with or without an envelope
+fn decode(connection: usize, line: &str) -> IncomingRequest {
+    let invalid = |e: serde_json::Error| RequestError::InvalidRequest(e.to_string());
+    match serde_json::from_str::<RawEnvelope>(line) {
+        Ok(envelope) => IncomingRequest {
+            token: Some(ResponseToken {
+                connection,
+                id: envelope.id,
+            }),
+            request: if envelope.version == IPC_VERSION {
+                serde_json::from_str::<RequestEnvelope>(line)
+                    .map(|e| e.request)
+                    .map_err(invalid)
+            } else {
+                Err(RequestError::UnsupportedVersion(envelope.version))
+            },
+        },
+        Err(_) => IncomingRequest {
+            token: None,
+            request: serde_json::from_str(line).map_err(invalid),
+        },
+    }
 }
 
 #[cfg(unix)]
@@ -122,10 +226,10 @@ impl Drop for AsyncFrontendListener {
 }
 
 impl Stream for AsyncFrontendListener {
-    type Item = Result<FrontendRequest, IpcError>;
+    type Item = Result<IncomingRequest, IpcError>;
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if let Poll::Ready(Some(Ok(l))) = self.line_streams.poll_next_unpin(cx) {
-            let request = serde_json::from_str(l.as_str()).map_err(|e| e.into());
+        if let Poll::Ready(Some((connection, line))) = self.line_streams.poll_next_unpin(cx) {
+            let request = line.map(|l| decode(connection, &l)).map_err(|e| e.into());
             return Poll::Ready(Some(request));
         }
         let mut sync = false;
@@ -134,14 +238,67 @@ impl Stream for AsyncFrontendListener {
             let buf_reader = BufReader::new(rx);
             let lines = buf_reader.lines();
             let lines = LinesStream::new(lines);
-            self.line_streams.push(lines);
-            self.tx_streams.push(tx);
+            let connection = self.next_connection;
+            self.next_connection += 1;
+            self.line_streams.insert(connection, lines);
+            self.tx_streams.push((connection, tx));
             sync = true;
         }
         if sync {
-            Poll::Ready(Some(Ok(FrontendRequest::Sync)))
+            Poll::Ready(Some(Ok(IncomingRequest {
+                token: None,
+                request: Ok(FrontendRequest::Sync),
+            })))
         } else {
             Poll::Pending
         }
     }
 }
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::{FrontendMessage, Response};
+
+    #[tokio::test]
+    async fn respond_to_request() {
+        let path = std::env::temp_dir().join(format!("lan-mouse-ipc-{}.sock", std::process::id()));
+        let mut listener = AsyncFrontendListener::bind(path.clone())
+            .await
+            .expect("bind");
+        let stream = UnixStream::connect(&path).await.expect("connect");
+        let (rx, mut tx) = tokio::io::split(stream);
+        let mut lines = BufReader::new(rx).lines();
+
+        // new frontends are synchronized
+        let sync = listener.next().await.expect("sync").expect("sync");
+        assert_eq!(sync.request, Ok(FrontendRequest::Sync));
+        assert_eq!(sync.token, None);
+
+        let envelope = RequestEnvelope {
+            version: IPC_VERSION,
+            id: 7,
+            request: FrontendRequest::Create,
+        };
+        let mut json = serde_json::to_string(&envelope).unwrap();
+        json.push_str("\n\"EnableCapture\"\n");
+        tx.write_all(json.as_bytes()).await.expect("write");
+
+        let request = listener.next().await.expect("request").expect("request");
+        assert_eq!(request.request, Ok(FrontendRequest::Create));
+        let token = request.token.expect("token");
+        // requests without an envelope are not responded to
+        let request = listener.next().await.expect("request").expect("request");
+        assert_eq!(request.request, Ok(FrontendRequest::EnableCapture));
+        assert_eq!(request.token, None);
+
+        listener.respond(token, Ok(Response::Created(3))).await;
+        let line = lines.next_line().await.expect("read").expect("line");
+        let FrontendMessage::Response(response) = serde_json::from_str(&line).expect("decode")
+        else {
+            panic!("expected a response: {line}");
+        };
+        assert_eq!(response.id, 7);
+        assert_eq!(response.response, Ok(Response::Created(3)));
+    }
+}
@@ -7,10 +7,77 @@ use std::{
 };
 use thiserror::Error;
 
+/// number of clipboard content bytes carried by a single [`ProtoEvent::ClipboardData`] event
+pub const CLIPBOARD_CHUNK_SIZE: usize = 512;
+
 /// defines the maximum size an encoded event can take up
-/// this is currently the pointer motion event
-/// type: u8, time: u32, dx: f64, dy: f64
-pub const MAX_EVENT_SIZE: usize = size_of::<u8>() + size_of::<u32>() + 2 * size_of::<f64>();
+/// this is currently the clipboard data event
+/// type: u8, id: u16, offset: u32, data: [u8; CLIPBOARD_CHUNK_SIZE]
+pub const MAX_EVENT_SIZE: usize =
+    size_of::<u8>() + size_of::<u16>() + size_of::<u32>() + CLIPBOARD_CHUNK_SIZE;
+
+/// version of the lan-mouse protocol spoken by this build,
+/// exchanged in [`ProtoEvent::Hello`].
+/// Peers predating version negotiation report version `0`.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// optional protocol features, exchanged in [`ProtoEvent::Hello`]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Capabilities(u32);
+
+impl Capabilities {
+    /// clipboard sharing via [`ProtoEvent::ClipboardOffer`], [`ProtoEvent::ClipboardData`]
+    /// and [`ProtoEvent::ClipboardAck`]
+    pub const CLIPBOARD: Self = Self(1 << 0);
+    /// absolute pointer motion, i.e. the entry point along the edge
+    /// carried by [`ProtoEvent::Enter`]
+    pub const ABSOLUTE_MOTION: Self = Self(1 << 1);
+    /// high-resolution scroll events ([`PointerEvent::AxisDiscrete120`])
+    pub const HIGH_RES_SCROLL: Self = Self(1 << 2);
+
+    /// capabilities of peers predating version negotiation
+    pub const LEGACY: Self = Self::HIGH_RES_SCROLL;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    pub const fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// capabilities supported by both `self` and `other`
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+
+    pub fn insert(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+
+    pub fn remove(&mut self, other: Self) {
+        self.0 &= !other.0;
+    }
+
+    /// capabilities usable with a peer that announced
+    /// `version` and `capabilities` in its [`ProtoEvent::Hello`]
+    pub const fn negotiate(self, version: u16, capabilities: Self) -> Self {
+        let peer = if version == 0 {
+            Self::LEGACY
+        } else {
+            capabilities
+        };
+        self.intersection(peer)
+    }
+}
 
 /// error type for protocol violations
 #[derive(Debug, Error)]
@@ -24,7 +91,7 @@ pub enum ProtocolError {
 }
 
 /// Position of a client
-#[derive(Clone, Copy, Debug, TryFromPrimitive, IntoPrimitive)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, TryFromPrimitive, IntoPrimitive)]
 #[repr(u8)]
 pub enum Position {
     Left,
@@ -46,11 +113,15 @@ impl Display for Position {
 }
 
 /// main lan-mouse protocol event type
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum ProtoEvent {
     /// notify a client that the cursor entered its region at the given position
     /// [`ProtoEvent::Ack`] with the same serial is used for synchronization between devices
-    Enter(Position),
+    ///
+    /// The second field is the normalized coordinate along the edge where the
+    /// cursor entered (`0.0` = left / top, `1.0` = right / bottom), if known.
+    /// It requires [`Capabilities::ABSOLUTE_MOTION`].
+    Enter(Position, Option<f64>),
     /// notify a client that the cursor left its region
     /// [`ProtoEvent::Ack`] with the same serial is used for synchronization between devices
     Leave(u32),
@@ -71,13 +142,44 @@ pub enum ProtoEvent {
     /// `shadow_rs`'s `SHORT_COMMIT`. Old peers that don't
     /// recognize the event type silently skip it per the
     /// forward-compat handling in the receive loop.
-    Hello { commit: [u8; 8] },
+    ///
+    /// `version` and `capabilities` are used to negotiate optional
+    /// protocol features. Since missing trailing bytes are decoded as zero,
+    /// peers predating the negotiation are decoded with version `0`.
+    Hello {
+        commit: [u8; 8],
+        version: u16,
+        capabilities: Capabilities,
+    },
+    /// Announces the text content of the clipboard (utf-8, `len` bytes).
+    /// The content follows in [`ProtoEvent::ClipboardData`] chunks with the same `id`.
+    ClipboardOffer { id: u16, len: u32 },
+    /// Chunk of the clipboard content announced by the
+    /// [`ProtoEvent::ClipboardOffer`] with the same `id`, starting at byte `offset`.
+    /// The last chunk is padded with zeroes.
+    ClipboardData {
+        id: u16,
+        offset: u32,
+        data: Box<[u8; CLIPBOARD_CHUNK_SIZE]>,
+    },
+    /// The clipboard content with the given `id` was received completely.
+    /// Offers that are not acknowledged are sent again.
+    ClipboardAck { id: u16 },
+    /// Commitment to the random nonce contributing to the pairing code
+    /// (SHA-256 of the nonce), sent while pairing with a device.
+    /// The nonce is only revealed in [`ProtoEvent::PairingNonce`] after the
+    /// commitment of the other device was received, so neither side can
+    /// choose its nonce depending on the other one.
+    PairingCommit { commitment: [u8; 32] },
+    /// The nonce committed to in [`ProtoEvent::PairingCommit`]
+    PairingNonce { nonce: [u8; 32] },
 }
 
 impl Display for ProtoEvent {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            ProtoEvent::Enter(s) => write!(f, "Enter({s})"),
+            ProtoEvent::Enter(s, None) => write!(f, "Enter({s})"),
+            ProtoEvent::Enter(s, Some(offset)) => write!(f, "Enter({s}, {offset:.3})"),
             ProtoEvent::Leave(s) => write!(f, "Leave({s})"),
             ProtoEvent::Ack(s) => write!(f, "Ack({s})"),
             ProtoEvent::Input(e) => write!(f, "{e}"),
@@ -89,10 +191,24 @@ impl Display for ProtoEvent {
                     if *alive { "alive" } else { "not available" }
                 )
             }
-            ProtoEvent::Hello { commit } => {
+            ProtoEvent::Hello {
+                commit,
+                version,
+                capabilities,
+            } => {
                 let s = std::str::from_utf8(commit).unwrap_or("????????");
-                write!(f, "Hello({s})")
+                let caps = capabilities.bits();
+                write!(f, "Hello({s}, version: {version}, capabilities: {caps:#x})")
+            }
+            ProtoEvent::ClipboardOffer { id, len } => {
+                write!(f, "ClipboardOffer(id: {id}, len: {len})")
             }
+            ProtoEvent::ClipboardData { id, offset, .. } => {
+                write!(f, "ClipboardData(id: {id}, offset: {offset})")
+            }
+            ProtoEvent::ClipboardAck { id } => write!(f, "ClipboardAck(id: {id})"),
+            ProtoEvent::PairingCommit { .. } => write!(f, "PairingCommit"),
+            ProtoEvent::PairingNonce { .. } => write!(f, "PairingNonce"),
         }
     }
 }
@@ -112,9 +228,42 @@ pub enum EventType {
     Leave,
     Ack,
     Hello,
+    ClipboardOffer,
+    ClipboardData,
+    ClipboardAck,
+    PairingCommit,
+    PairingNonce,
 }
 
 impl ProtoEvent {
+    /// adapt the event to the capabilities of the receiving peer.
+    /// Returns `None` if the peer can not handle the event.
+    pub fn restrict_to(self, capabilities: Capabilities) -> Option<Self> {
+        match self {
+            ProtoEvent::ClipboardOffer { .. }
+            | ProtoEvent::ClipboardData { .. }
+            | ProtoEvent::ClipboardAck { .. }
+                if !capabilities.contains(Capabilities::CLIPBOARD) =>
+            {
+                None
+            }
+            ProtoEvent::Enter(pos, _) if !capabilities.contains(Capabilities::ABSOLUTE_MOTION) => {
+                Some(ProtoEvent::Enter(pos, None))
+            }
+            ProtoEvent::Input(InputEvent::Pointer(PointerEvent::AxisDiscrete120 {
+                axis,
+                value,
+            })) if !capabilities.contains(Capabilities::HIGH_RES_SCROLL) => {
+                Some(ProtoEvent::Input(InputEvent::Pointer(PointerEvent::Axis {
+                    time: 0,
+                    axis,
+                    value: value as f64 / 8.,
+                })))
+            }
+            event => Some(event),
+        }
+    }
+
     fn event_type(&self) -> EventType {
         match self {
             ProtoEvent::Input(e) => match e {
@@ -131,14 +280,32 @@ impl ProtoEvent {
             },
             ProtoEvent::Ping => EventType::Ping,
             ProtoEvent::Pong(_) => EventType::Pong,
-            ProtoEvent::Enter(_) => EventType::Enter,
+            ProtoEvent::Enter(..) => EventType::Enter,
             ProtoEvent::Leave(_) => EventType::Leave,
             ProtoEvent::Ack(_) => EventType::Ack,
             ProtoEvent::Hello { .. } => EventType::Hello,
+            ProtoEvent::ClipboardOffer { .. } => EventType::ClipboardOffer,
+            ProtoEvent::ClipboardData { .. } => EventType::ClipboardData,
+            ProtoEvent::ClipboardAck { .. } => EventType::ClipboardAck,
+            ProtoEvent::PairingCommit { .. } => EventType::PairingCommit,
+            ProtoEvent::PairingNonce { .. } => EventType::PairingNonce,
         }
     }
 }
 
+/// decodes a received datagram. Trailing bytes not sent by the peer
+/// (e.g. fields added in later protocol versions) are decoded as zero.
+impl TryFrom<&[u8]> for ProtoEvent {
+    type Error = ProtocolError;
+
+    fn try_from(data: &[u8]) -> Result<Self, Self::Error> {
+        let mut buf = [0u8; MAX_EVENT_SIZE];
+        let len = data.len().min(MAX_EVENT_SIZE);
+        buf[..len].copy_from_slice(&data[..len]);
+        buf.try_into()
+    }
+}
+
 impl TryFrom<[u8; MAX_EVENT_SIZE]> for ProtoEvent {
     type Error = ProtocolError;
 
@@ -186,16 +353,44 @@ impl TryFrom<[u8; MAX_EVENT_SIZE]> for ProtoEvent {
             ))),
             EventType::Ping => Ok(Self::Ping),
             EventType::Pong => Ok(Self::Pong(decode_u8(&mut buf)? != 0)),
-            EventType::Enter => Ok(Self::Enter(decode_u8(&mut buf)?.try_into()?)),
+            EventType::Enter => {
+                let pos = decode_u8(&mut buf)?.try_into()?;
+                // missing for peers that do not send the coordinate
+                let offset = match decode_u8(&mut buf)? {
+                    0 => None,
+                    _ => Some(decode_f64(&mut buf)?),
+                };
+                Ok(Self::Enter(pos, offset))
+            }
             EventType::Leave => Ok(Self::Leave(decode_u32(&mut buf)?)),
             EventType::Ack => Ok(Self::Ack(decode_u32(&mut buf)?)),
-            EventType::Hello => {
-                let mut commit = [0u8; 8];
-                for b in commit.iter_mut() {
+            EventType::Hello => Ok(Self::Hello {
+                commit: decode_bytes(&mut buf)?,
+                version: decode_u16(&mut buf)?,
+                capabilities: Capabilities::from_bits(decode_u32(&mut buf)?),
+            }),
+            EventType::ClipboardOffer => Ok(Self::ClipboardOffer {
+                id: decode_u16(&mut buf)?,
+                len: decode_u32(&mut buf)?,
+            }),
+            EventType::ClipboardData => {
+                let id = decode_u16(&mut buf)?;
+                let offset = decode_u32(&mut buf)?;
+                let mut data = Box::new([0u8; CLIPBOARD_CHUNK_SIZE]);
+                for b in data.iter_mut() {
                     *b = decode_u8(&mut buf)?;
                 }
-                Ok(Self::Hello { commit })
+                Ok(Self::ClipboardData { id, offset, data })
             }
+            EventType::ClipboardAck => Ok(Self::ClipboardAck {
+                id: decode_u16(&mut buf)?,
+            }),
+            EventType::PairingCommit => Ok(Self::PairingCommit {
+                commitment: decode_bytes(&mut buf)?,
+            }),
+            EventType::PairingNonce => Ok(Self::PairingNonce {
+                nonce: decode_bytes(&mut buf)?,
+            }),
         }
     }
 }
@@ -257,13 +452,41 @@ impl From<ProtoEvent> for ([u8; MAX_EVENT_SIZE], usize) {
                 },
                 ProtoEvent::Ping => {}
                 ProtoEvent::Pong(alive) => encode_u8(buf, len, alive as u8),
-                ProtoEvent::Enter(pos) => encode_u8(buf, len, pos as u8),
+                ProtoEvent::Enter(pos, offset) => {
+                    encode_u8(buf, len, pos as u8);
+                    encode_u8(buf, len, offset.is_some() as u8);
+                    encode_f64(buf, len, offset.unwrap_or_default());
+                }
                 ProtoEvent::Leave(serial) => encode_u32(buf, len, serial),
                 ProtoEvent::Ack(serial) => encode_u32(buf, len, serial),
-                ProtoEvent::Hello { commit } => {
+                ProtoEvent::Hello {
+                    commit,
+                    version,
+                    capabilities,
+                } => {
                     for b in commit.iter() {
                         encode_u8(buf, len, *b);
                     }
+                    encode_u16(buf, len, version);
+                    encode_u32(buf, len, capabilities.bits());
+                }
+                ProtoEvent::ClipboardOffer { id, len: size } => {
+                    encode_u16(buf, len, id);
+                    encode_u32(buf, len, size);
+                }
+                ProtoEvent::ClipboardData { id, offset, data } => {
+                    encode_u16(buf, len, id);
+                    encode_u32(buf, len, offset);
+                    for b in data.iter() {
+                        encode_u8(buf, len, *b);
+                    }
+                }
+                ProtoEvent::ClipboardAck { id } => encode_u16(buf, len, id),
+                ProtoEvent::PairingCommit { commitment: bytes }
+                | ProtoEvent::PairingNonce { nonce: bytes } => {
+                    for b in bytes.iter() {
+                        encode_u8(buf, len, *b);
+                    }
                 }
             }
         }
@@ -284,10 +507,19 @@ macro_rules! decode_impl {
 }
 
 decode_impl!(u8);
+decode_impl!(u16);
 decode_impl!(u32);
 decode_impl!(i32);
 decode_impl!(f64);
 
+fn decode_bytes<const N: usize>(data: &mut &[u8]) -> Result<[u8; N], ProtocolError> {
+    let mut bytes = [0u8; N];
+    for b in bytes.iter_mut() {
+        *b = decode_u8(data)?;
+    }
+    Ok(bytes)
+}
+
 macro_rules! encode_impl {
     ($t:ty) => {
         paste! {
@@ -304,6 +536,130 @@ macro_rules! encode_impl {
 }
 
 encode_impl!(u8);
+encode_impl!(u16);
 encode_impl!(u32);
 encode_impl!(i32);
 encode_impl!(f64);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode(event: ProtoEvent) -> Vec<u8> {
+        let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = event.into();
+        buf[..len].to_vec()
+    }
+
+    fn decode(data: &[u8]) -> ProtoEvent {
+        ProtoEvent::try_from(data).expect("valid event")
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut data = Box::new([0u8; CLIPBOARD_CHUNK_SIZE]);
+        data[..5].copy_from_slice(b"hello");
+        let events = [
+            ProtoEvent::Input(InputEvent::Pointer(PointerEvent::Motion {
+                time: 1,
+                dx: 2.5,
+                dy: -3.,
+            })),
+            ProtoEvent::Input(InputEvent::Pointer(PointerEvent::Button {
+                time: 1,
+                button: 0x110,
+                state: 1,
+            })),
+            ProtoEvent::Input(InputEvent::Pointer(PointerEvent::Axis {
+                time: 1,
+                axis: 1,
+                value: -15.,
+            })),
+            ProtoEvent::Input(InputEvent::Pointer(PointerEvent::AxisDiscrete120 {
+                axis: 0,
+                value: -120,
+            })),
+            ProtoEvent::Input(InputEvent::Keyboard(KeyboardEvent::Key {
+                time: 1,
+                key: 30,
+                state: 1,
+            })),
+            ProtoEvent::Input(InputEvent::Keyboard(KeyboardEvent::Modifiers {
+                depressed: 1,
+                latched: 2,
+                locked: 3,
+                group: 4,
+            })),
+            ProtoEvent::Ping,
+            ProtoEvent::Pong(true),
+            ProtoEvent::Enter(Position::Top, None),
+            ProtoEvent::Enter(Position::Right, Some(0.25)),
+            ProtoEvent::Leave(7),
+            ProtoEvent::Ack(7),
+            ProtoEvent::Hello {
+                commit: *b"0123abcd",
+                version: PROTOCOL_VERSION,
+                capabilities: Capabilities::CLIPBOARD,
+            },
+            ProtoEvent::ClipboardOffer { id: 3, len: 5 },
+            ProtoEvent::ClipboardData {
+                id: 3,
+                offset: 512,
+                data,
+            },
+            ProtoEvent::ClipboardAck { id: 3 },
+            ProtoEvent::PairingCommit {
+                commitment: [1; 32],
+            },
+            ProtoEvent::PairingNonce { nonce: [2; 32] },
+        ];
+        for event in events {
+            let encoded = encode(event.clone());
+            assert!(encoded.len() <= MAX_EVENT_SIZE);
+            assert_eq!(format!("{:?}", decode(&encoded)), format!("{event:?}"));
+        }
+    }
+
+    #[test]
+    fn short_frames_are_zero_extended() {
+        // peer without absolute motion: event type and position only
+        let enter = [EventType::Enter as u8, Position::Left as u8];
+        assert!(matches!(
+            decode(&enter),
+            ProtoEvent::Enter(Position::Left, None)
+        ));
+
+        // peer predating version negotiation: commit only
+        let mut hello = vec![EventType::Hello as u8];
+        hello.extend_from_slice(b"0123abcd");
+        match decode(&hello) {
+            ProtoEvent::Hello {
+                commit,
+                version,
+                capabilities,
+            } => {
+                assert_eq!(&commit, b"0123abcd");
+                assert_eq!(version, 0);
+                assert_eq!(capabilities, Capabilities::empty());
+            }
+            e => panic!("unexpected event {e}"),
+        }
+    }
+
+    #[test]
+    fn stale_bytes_of_a_reused_buffer_are_ignored() {
+        let mut buf = [0u8; MAX_EVENT_SIZE];
+        let long = encode(ProtoEvent::Enter(Position::Top, Some(0.5)));
+        buf[..long.len()].copy_from_slice(&long);
+        let short = [EventType::Enter as u8, Position::Bottom as u8];
+        buf[..short.len()].copy_from_slice(&short);
+        assert!(matches!(
+            decode(&buf[..short.len()]),
+            ProtoEvent::Enter(Position::Bottom, None)
+        ));
+    }
+
+    #[test]
+    fn unknown_event_type() {
+        assert!(ProtoEvent::try_from(&[0xff][..]).is_err());
+    }
+}
@@ -1,20 +1,28 @@
 use std::{
     cell::{Cell, RefCell},
+    collections::HashMap,
     rc::Rc,
     time::{Duration, Instant},
 };
 
 use futures::StreamExt;
 use input_capture::{
-    CaptureError, CaptureEvent, CaptureHandle, InputCapture, InputCaptureError, Position,
+    CaptureError, CaptureEvent, CaptureHandle, Crossing, EdgeGuard, GuardedCrossing, InputCapture,
+    InputCaptureError, Position, Segment, Verdict,
 };
-use input_event::{Event, KeyboardEvent, scancode};
+use input_event::{Event, KeyboardEvent, Modifier, PointerEvent, scancode};
+use lan_mouse_clipboard::Clipboard;
+use lan_mouse_ipc::PointerSettings;
 use lan_mouse_proto::ProtoEvent;
 use local_channel::mpsc::{Receiver, Sender, channel};
 use tokio::task::{JoinHandle, spawn_local};
 use tokio_util::sync::CancellationToken;
 
-use crate::connect::LanMouseConnection;
+use crate::{
+    client::ClientManager,
+    clipboard::{self, ClipboardReceiver, ClipboardSender},
+    connect::LanMouseConnection,
+};
 
 pub(crate) struct Capture {
     cancellation_token: CancellationToken,
@@ -37,6 +45,8 @@ pub(crate) enum ICaptureEvent {
     /// either the remote client leaving its device region,
     /// a new device entering the screen or the release bind.
     ClientEntered(u64),
+    /// The cursor left the client, because the capture was released.
+    ClientLeft(u64),
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -54,20 +64,27 @@ enum CaptureRequest {
     /// capture must release the mouse
     Release,
     /// add a capture client
-    Create(CaptureHandle, Position, CaptureType),
+    Create(CaptureHandle, Position, Option<Segment>, CaptureType),
     /// destory a capture client
     Destroy(CaptureHandle),
+    /// capture input for a client without crossing its edge
+    Capture(CaptureHandle),
     /// reenable input capture
     Reenable,
     /// set release bind
     SetReleaseBind(Vec<scancode::Linux>),
+    /// set the guards against crossing an edge by accident
+    SetEdgeGuard(EdgeGuard),
 }
 
 impl Capture {
     pub(crate) fn new(
         backend: Option<input_capture::Backend>,
         conn: LanMouseConnection,
+        client_manager: ClientManager,
         release_bind: Vec<scancode::Linux>,
+        edge_guard: EdgeGuard,
+        clipboard: Option<Rc<dyn Clipboard>>,
     ) -> Self {
         let (request_tx, request_rx) = channel();
         let (event_tx, event_rx) = channel();
@@ -77,10 +94,20 @@ impl Capture {
             backend,
             cancellation_token: cancellation_token.clone(),
             captures: Default::default(),
+            client_manager,
+            clipboard,
+            clipboard_receivers: Default::default(),
+            clipboard_sender: Default::default(),
             conn,
+            edge_guard,
+            entry_point: None,
             event_tx,
+            guarded_crossing: None,
+            held_back: Default::default(),
+            pointer: Default::default(),
             request_rx,
             release_bind: Rc::new(RefCell::new(release_bind)),
+            remap: Default::default(),
             state: Default::default(),
         };
         let task = spawn_local(capture_task.run());
@@ -110,11 +137,13 @@ impl Capture {
         &self,
         handle: CaptureHandle,
         pos: lan_mouse_ipc::Position,
+        segment: Option<lan_mouse_ipc::EdgeSegment>,
         capture_type: CaptureType,
     ) {
         let pos = to_capture_pos(pos);
+        let segment = segment.map(to_capture_segment);
         self.request_tx
-            .send(CaptureRequest::Create(handle, pos, capture_type))
+            .send(CaptureRequest::Create(handle, pos, segment, capture_type))
             .expect("channel closed");
     }
 
@@ -130,6 +159,12 @@ impl Capture {
             .expect("channel closed");
     }
 
+    pub(crate) fn capture(&self, handle: CaptureHandle) {
+        self.request_tx
+            .send(CaptureRequest::Capture(handle))
+            .expect("channel closed");
+    }
+
     pub(crate) async fn event(&mut self) -> ICaptureEvent {
         self.event_rx.recv().await.expect("channel closed")
     }
@@ -137,6 +172,10 @@ impl Capture {
     pub(crate) fn set_release_bind(&mut self, bind: Vec<scancode::Linux>) {
         let _ = self.request_tx.send(CaptureRequest::SetReleaseBind(bind));
     }
+
+    pub(crate) fn set_edge_guard(&mut self, guard: EdgeGuard) {
+        let _ = self.request_tx.send(CaptureRequest::SetEdgeGuard(guard));
+    }
 }
 
 /// debounce a statement `$st`, i.e. the statement is executed only if the
@@ -160,27 +199,50 @@ struct CaptureTask {
     active_client: Option<CaptureHandle>,
     backend: Option<input_capture::Backend>,
     cancellation_token: CancellationToken,
-    captures: Vec<(CaptureHandle, Position, CaptureType)>,
+    captures: Vec<(CaptureHandle, Position, Option<Segment>, CaptureType)>,
+    client_manager: ClientManager,
+    clipboard: Option<Rc<dyn Clipboard>>,
+    clipboard_receivers: HashMap<CaptureHandle, ClipboardReceiver>,
+    clipboard_sender: ClipboardSender<CaptureHandle>,
     conn: LanMouseConnection,
+    edge_guard: EdgeGuard,
+    /// normalized coordinate along the edge where the active client was entered
+    entry_point: Option<f64>,
     event_tx: Sender<ICaptureEvent>,
+    /// crossing held back until the edge guard is satisfied
+    guarded_crossing: Option<(CaptureHandle, GuardedCrossing)>,
+    /// input held back by the edge guard, sent once the crossing is acknowledged
+    held_back: Vec<Event>,
+    /// pointer speed and scroll settings of the active client
+    pointer: PointerSettings,
     release_bind: Rc<RefCell<Vec<scancode::Linux>>>,
+    /// key and button remapping of the active client
+    remap: Remap,
     request_rx: Receiver<CaptureRequest>,
     state: State,
 }
 
 impl CaptureTask {
-    fn add_capture(&mut self, handle: CaptureHandle, pos: Position, capture_type: CaptureType) {
-        self.captures.push((handle, pos, capture_type));
+    fn add_capture(
+        &mut self,
+        handle: CaptureHandle,
+        pos: Position,
+        segment: Option<Segment>,
+        capture_type: CaptureType,
+    ) {
+        self.captures.push((handle, pos, segment, capture_type));
     }
 
     fn remove_capture(&mut self, handle: CaptureHandle) {
         self.captures.retain(|&(h, ..)| handle != h);
     }
 
-    fn is_default_capture_at(&self, pos: Position) -> bool {
-        self.captures
-            .iter()
-            .any(|&(_, p, t)| p == pos && t == CaptureType::Default)
+    /// whether there is a default capture at the given position,
+    /// whose segment of the edge contains the crossing
+    fn is_default_capture_at(&self, pos: Position, crossing: Option<Crossing>) -> bool {
+        self.captures.iter().any(|&(_, p, s, t)| {
+            p == pos && t == CaptureType::Default && segment_contains(s, crossing)
+        })
     }
 
     fn get_pos(&self, handle: CaptureHandle) -> Position {
@@ -191,7 +253,7 @@ impl CaptureTask {
             .1
     }
 
-    fn get_type(&self, handle: CaptureHandle) -> CaptureType {
+    fn get_segment(&self, handle: CaptureHandle) -> Option<Segment> {
         self.captures
             .iter()
             .find(|(h, ..)| *h == handle)
@@ -199,6 +261,24 @@ impl CaptureTask {
             .2
     }
 
+    /// keys capturing input for the client directly
+    fn get_hotkey(&self, handle: CaptureHandle) -> Option<Vec<scancode::Linux>> {
+        let keys = self.client_manager.get_hotkey(handle)?;
+        let keys = keys
+            .into_iter()
+            .filter_map(|k| scancode::Linux::try_from(k).ok())
+            .collect::<Vec<_>>();
+        (!keys.is_empty()).then_some(keys)
+    }
+
+    fn get_type(&self, handle: CaptureHandle) -> CaptureType {
+        self.captures
+            .iter()
+            .find(|(h, ..)| *h == handle)
+            .expect("no such capture")
+            .3
+    }
+
     async fn run(mut self) {
         loop {
             if let Err(e) = self.do_capture().await {
@@ -208,12 +288,16 @@ impl CaptureTask {
                 tokio::select! {
                     r = self.request_rx.recv() => match r.expect("channel closed") {
                         CaptureRequest::Reenable => break,
-                        CaptureRequest::Create(h, p, t) => self.add_capture(h, p, t),
+                        CaptureRequest::Create(h, p, s, t) => self.add_capture(h, p, s, t),
                         CaptureRequest::Destroy(h) => self.remove_capture(h),
                         CaptureRequest::Release => { /* nothing to do */ }
+                        CaptureRequest::Capture(h) => {
+                            log::warn!("can not capture input for client {h}: capture disabled");
+                        }
                         CaptureRequest::SetReleaseBind(bind) => {
                             self.release_bind.borrow_mut().clone_from(&bind);
                         }
+                        CaptureRequest::SetEdgeGuard(guard) => self.edge_guard = guard,
                     },
                     _ = self.cancellation_token.cancelled() => return,
                 }
@@ -224,9 +308,10 @@ impl CaptureTask {
     async fn do_capture(&mut self) -> Result<(), InputCaptureError> {
         /* allow cancelling capture request */
         let mut capture = tokio::select! {
-            r = InputCapture::new(self.backend) => r?,
+            r = InputCapture::new(self.backend.clone()) => r?,
             _ = self.cancellation_token.cancelled() => return Ok(()),
         };
+        capture.set_block_drags(self.edge_guard.block_drags);
 
         let _capture_guard = DropGuard::new(
             self.event_tx.clone(),
@@ -251,15 +336,30 @@ impl CaptureTask {
 
     async fn create_captures(&mut self, capture: &mut InputCapture) -> Result<(), CaptureError> {
         let captures = self.captures.clone();
-        for (handle, pos, _type) in captures {
+        for (handle, pos, segment, capture_type) in captures {
             tokio::select! {
-                r = capture.create(handle, pos) => r?,
+                r = self.create_capture(capture, handle, pos, segment, capture_type) => r?,
                 _ = self.cancellation_token.cancelled() => return Ok(()),
             }
         }
         Ok(())
     }
 
+    async fn create_capture(
+        &self,
+        capture: &mut InputCapture,
+        handle: CaptureHandle,
+        pos: Position,
+        segment: Option<Segment>,
+        capture_type: CaptureType,
+    ) -> Result<(), CaptureError> {
+        capture.create(handle, pos, segment).await?;
+        if capture_type == CaptureType::Default {
+            capture.set_hotkey(handle, self.get_hotkey(handle));
+        }
+        Ok(())
+    }
+
     async fn do_capture_session(
         &mut self,
         capture: &mut InputCapture,
@@ -271,6 +371,11 @@ impl CaptureTask {
                     None => return Ok(()),
                 },
                 (handle, event) = self.conn.recv() => {
+                    // clipboard of the client we left, may arrive after entering another one
+                    if let ProtoEvent::ClipboardOffer { .. } | ProtoEvent::ClipboardData { .. } | ProtoEvent::ClipboardAck { .. } = event {
+                        self.handle_clipboard_event(handle, event).await;
+                        continue;
+                    }
                     if let Some(active) = self.active_client {
                         if handle != active {
                             // we only care about events coming from the client we are currently connected to
@@ -283,7 +388,13 @@ impl CaptureTask {
                         // connection acknowlegded => set state to Sending
                         ProtoEvent::Ack(_) => {
                             log::info!("client {handle} acknowledged the connection!");
+                            if self.state == State::WaitingForAck {
+                                self.send_clipboard(handle);
+                            }
                             self.state = State::Sending;
+                            for event in std::mem::take(&mut self.held_back) {
+                                self.send_event(capture, handle, CaptureEvent::Input(event)).await?;
+                            }
                         }
                         // client disconnected
                         ProtoEvent::Leave(_) => {
@@ -296,18 +407,34 @@ impl CaptureTask {
                 e = self.request_rx.recv() => match e.expect("channel closed") {
                     CaptureRequest::Reenable => { /* already active */ },
                     CaptureRequest::Release => self.release_capture(capture).await?,
-                    CaptureRequest::Create(h, p, t) => {
-                        self.add_capture(h, p, t);
-                        capture.create(h, p).await?;
+                    CaptureRequest::Create(h, p, s, t) => {
+                        self.add_capture(h, p, s, t);
+                        self.create_capture(capture, h, p, s, t).await?;
                     }
                     CaptureRequest::Destroy(h) => {
                         self.remove_capture(h);
+                        self.clipboard_receivers.remove(&h);
+                        self.clipboard_sender.forget(h);
                         capture.destroy(h).await?;
                     }
+                    CaptureRequest::Capture(h) => self.capture_client(capture, h).await?,
                     CaptureRequest::SetReleaseBind(bind) => {
                         self.release_bind.borrow_mut().clone_from(&bind);
                     }
+                    CaptureRequest::SetEdgeGuard(guard) => {
+                        capture.set_block_drags(guard.block_drags);
+                        self.edge_guard = guard;
+                    }
                 },
+                (handle, events) = self.clipboard_sender.next() => {
+                    for event in events {
+                        if let Err(e) = self.conn.send(event, handle).await {
+                            log::warn!("failed to send clipboard to client {handle}: {e}");
+                            break;
+                        }
+                    }
+                }
+                _ = dwell_time_over(&self.guarded_crossing) => self.dwell_time_over(capture).await?,
                 _ = self.cancellation_token.cancelled() => break,
             }
         }
@@ -327,7 +454,15 @@ impl CaptureTask {
             return self.release_capture(capture).await;
         }
 
-        if event == CaptureEvent::Begin {
+        if let Some(target) = capture
+            .pressed_hotkey()
+            .filter(|&h| Some(h) != self.active_client)
+        {
+            log::info!("hotkey pressed: switching to client {target}");
+            return self.capture_client(capture, target).await;
+        }
+
+        if let CaptureEvent::Begin(_) = event {
             self.event_tx
                 .send(ICaptureEvent::CaptureBegin(handle))
                 .expect("channel closed");
@@ -337,7 +472,11 @@ impl CaptureTask {
         if self.get_type(handle) == CaptureType::EnterOnly {
             // if there is no active outgoing connection at the current capture,
             // we release the capture
-            if !self.is_default_capture_at(self.get_pos(handle)) {
+            let crossing = match event {
+                CaptureEvent::Begin(crossing) => crossing,
+                _ => None,
+            };
+            if !self.is_default_capture_at(self.get_pos(handle), crossing) {
                 log::info!("releasing capture: no active client at this position");
                 capture.release().await?;
             }
@@ -345,23 +484,68 @@ impl CaptureTask {
             return Ok(());
         }
 
+        // crossed the edge outside of the segment claimed by this client
+        if let CaptureEvent::Begin(crossing) = event {
+            if !segment_contains(self.get_segment(handle), crossing) {
+                log::info!("releasing capture: no active client at this part of the edge");
+                return capture.release().await;
+            }
+        }
+
+        match self.guard_crossing(capture, handle, event).await? {
+            Some(event) => self.send_event(capture, handle, event).await,
+            None => Ok(()),
+        }
+    }
+
+    /// send a captured event to the client, entering it on [`CaptureEvent::Begin`]
+    async fn send_event(
+        &mut self,
+        capture: &mut InputCapture,
+        handle: CaptureHandle,
+        event: CaptureEvent,
+    ) -> Result<(), CaptureError> {
         // activated a new client
-        if event == CaptureEvent::Begin && Some(handle) != self.active_client {
+        if matches!(event, CaptureEvent::Begin(_)) && Some(handle) != self.active_client {
             self.state = State::WaitingForAck;
-            self.active_client.replace(handle);
+            if let Some(previous) = self.active_client.replace(handle) {
+                self.event_tx
+                    .send(ICaptureEvent::ClientLeft(previous))
+                    .expect("channel closed");
+            }
+            self.remap = Remap::new(
+                self.client_manager.get_key_map(handle).unwrap_or_default(),
+                self.client_manager
+                    .get_button_map(handle)
+                    .unwrap_or_default(),
+            );
+            self.pointer = self
+                .client_manager
+                .get_pointer_settings(handle)
+                .unwrap_or_default();
             self.event_tx
                 .send(ICaptureEvent::ClientEntered(handle))
                 .expect("channel closed");
         }
 
+        // the entered client spans the segment it claims, not the whole edge
+        if let CaptureEvent::Begin(crossing) = event {
+            self.entry_point = crossing.map(|c| match self.get_segment(handle) {
+                Some(segment) => segment.fraction(&c),
+                None => c.fraction(),
+            });
+        }
+
         let opposite_pos = to_proto_pos(self.get_pos(handle).opposite());
 
         let event = match event {
-            CaptureEvent::Begin => ProtoEvent::Enter(opposite_pos),
+            CaptureEvent::Begin(_) => ProtoEvent::Enter(opposite_pos, self.entry_point),
             CaptureEvent::Input(e) => match self.state {
                 // connection not acknowledged, repeat `Enter` event
-                State::WaitingForAck => ProtoEvent::Enter(opposite_pos),
-                State::Sending => ProtoEvent::Input(e),
+                State::WaitingForAck => ProtoEvent::Enter(opposite_pos, self.entry_point),
+                State::Sending => {
+                    ProtoEvent::Input(apply_pointer_settings(&self.pointer, self.remap.event(e)))
+                }
             },
         };
 
@@ -373,7 +557,134 @@ impl CaptureTask {
         Ok(())
     }
 
+    /// Hold back crossings until the edge guard is satisfied.
+    /// Returns the event to process, if any.
+    async fn guard_crossing(
+        &mut self,
+        capture: &mut InputCapture,
+        handle: CaptureHandle,
+        event: CaptureEvent,
+    ) -> Result<Option<CaptureEvent>, CaptureError> {
+        let verdict = match (&mut self.guarded_crossing, event) {
+            // hotkeys are not guarded
+            (_, CaptureEvent::Begin(_)) if !capture.crossed_edge() => {
+                self.guarded_crossing = None;
+                self.held_back.clear();
+                return Ok(Some(event));
+            }
+            (_, CaptureEvent::Begin(crossing)) => {
+                self.held_back.clear();
+                let guarded = GuardedCrossing::new(
+                    self.edge_guard,
+                    self.get_pos(handle),
+                    crossing,
+                    capture.buttons_held(),
+                    capture.modifiers_held(),
+                );
+                let verdict = guarded.verdict();
+                self.guarded_crossing = Some((handle, guarded));
+                verdict
+            }
+            (Some((h, guarded)), CaptureEvent::Input(e)) if *h == handle => guarded.update(e),
+            _ => return Ok(Some(event)),
+        };
+        self.apply_verdict(capture, verdict).await
+    }
+
+    /// the dwell time of the held back crossing is over
+    async fn dwell_time_over(&mut self, capture: &mut InputCapture) -> Result<(), CaptureError> {
+        let Some((handle, guarded)) = &self.guarded_crossing else {
+            return Ok(());
+        };
+        let handle = *handle;
+        let verdict = guarded.verdict();
+        match self.apply_verdict(capture, verdict).await? {
+            Some(event) => self.send_event(capture, handle, event).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn apply_verdict(
+        &mut self,
+        capture: &mut InputCapture,
+        verdict: Verdict,
+    ) -> Result<Option<CaptureEvent>, CaptureError> {
+        match verdict {
+            Verdict::Wait => Ok(None),
+            Verdict::Cross => {
+                let (_, guarded) = self.guarded_crossing.take().expect("no crossing");
+                let crossing = guarded.crossing();
+                self.held_back = guarded.held_back();
+                Ok(Some(CaptureEvent::Begin(crossing)))
+            }
+            Verdict::Cancel => {
+                log::info!("releasing capture: edge guard not satisfied");
+                self.guarded_crossing = None;
+                capture.release().await?;
+                Ok(None)
+            }
+        }
+    }
+
+    /// push the local clipboard to the client that was entered
+    fn send_clipboard(&mut self, handle: CaptureHandle) {
+        if let Some(clipboard) = &self.clipboard {
+            self.clipboard_sender.send(handle, clipboard);
+        }
+    }
+
+    async fn handle_clipboard_event(&mut self, handle: CaptureHandle, event: ProtoEvent) {
+        let Some(clipboard) = &self.clipboard else {
+            return;
+        };
+        if let ProtoEvent::ClipboardAck { id } = event {
+            self.clipboard_sender.ack(handle, id);
+            return;
+        }
+        let receiver = self.clipboard_receivers.entry(handle).or_default();
+        let Some(received) = receiver.receive(event) else {
+            return;
+        };
+        if let Some(text) = received.text {
+            self.clipboard_sender.received(handle, &text);
+            clipboard::set_clipboard(clipboard, text);
+        }
+        let ack = ProtoEvent::ClipboardAck { id: received.id };
+        if let Err(e) = self.conn.send(ack, handle).await {
+            log::warn!("failed to acknowledge clipboard of client {handle}: {e}");
+        }
+    }
+
+    /// capture input for the given client directly, leaving the active client
+    async fn capture_client(
+        &mut self,
+        capture: &mut InputCapture,
+        handle: CaptureHandle,
+    ) -> Result<(), CaptureError> {
+        if !self
+            .captures
+            .iter()
+            .any(|&(h, .., t)| h == handle && t == CaptureType::Default)
+        {
+            log::warn!("can not capture input for client {handle}: client not active");
+            return Ok(());
+        }
+        self.leave_client(capture).await;
+        if let Err(e) = capture.capture(handle).await {
+            log::warn!("can not capture input for client {handle}: {e}");
+        }
+        Ok(())
+    }
+
     async fn release_capture(&mut self, capture: &mut InputCapture) -> Result<(), CaptureError> {
+        self.guarded_crossing = None;
+        self.held_back.clear();
+        self.leave_client(capture).await;
+        capture.release().await
+    }
+
+    /// notify the active client, that it is no longer captured
+    async fn leave_client(&mut self, capture: &mut InputCapture) {
         // If we have an active client, notify them we're leaving
         if let Some(handle) = self.active_client.take() {
             // Synthesize key-up events for every key still held in the
@@ -386,10 +697,12 @@ impl CaptureTask {
             // then runs every subsequent keystroke through those held
             // mods until its watchdog times out (1+ s) or our Leave
             // arrives — and Leave can be lost over UDP/DTLS.
+            // The capture tracks the original keys, the client
+            // received the remapped ones.
             for key in capture.take_pressed_keys() {
                 let key_up = ProtoEvent::Input(Event::Keyboard(KeyboardEvent::Key {
                     time: 0,
-                    key: key as u32,
+                    key: self.remap.key(key as u32),
                     state: 0,
                 }));
                 if let Err(e) = self.conn.send(key_up, handle).await {
@@ -410,13 +723,135 @@ impl CaptureTask {
             if let Err(e) = self.conn.send(mods_zero, handle).await {
                 log::warn!("failed to reset modifiers on client {handle}: {e}");
             }
+            // Same for mouse buttons, e.g. when a drag is interrupted
+            // by the release bind.
+            for button in capture.take_pressed_buttons() {
+                let button_up =
+                    ProtoEvent::Input(self.remap.event(Event::Pointer(PointerEvent::Button {
+                        time: 0,
+                        button,
+                        state: 0,
+                    })));
+                if let Err(e) = self.conn.send(button_up, handle).await {
+                    log::warn!("failed to send button-up to client {handle}: {e}");
+                }
+            }
 
             log::info!("sending Leave event to client {handle}");
             if let Err(e) = self.conn.send(ProtoEvent::Leave(0), handle).await {
                 log::warn!("failed to send Leave to client {handle}: {e}");
             }
+            self.event_tx
+                .send(ICaptureEvent::ClientLeft(handle))
+                .expect("channel closed");
+        }
+    }
+}
+
+/// keys and buttons replaced before sending events to a client
+#[derive(Default)]
+struct Remap {
+    keys: HashMap<u32, u32>,
+    buttons: HashMap<u32, u32>,
+    /// modifier mask bits of remapped modifier keys (from, to)
+    modifiers: Vec<(u32, u32)>,
+}
+
+impl Remap {
+    fn new(keys: HashMap<u32, u32>, buttons: HashMap<u32, u32>) -> Self {
+        // left and right modifiers share a bit in the modifier mask,
+        // so remapping one of them remaps the mask of both.
+        // A modifier mapped to a regular key is removed from the mask.
+        let mask = |key| Modifier::of_key(key).map(Modifier::mask);
+        let modifiers = keys
+            .iter()
+            .filter_map(|(&from, &to)| Some((mask(from)?, mask(to).unwrap_or(0))))
+            .collect();
+        Self {
+            keys,
+            buttons,
+            modifiers,
+        }
+    }
+
+    fn key(&self, key: u32) -> u32 {
+        self.keys.get(&key).copied().unwrap_or(key)
+    }
+
+    /// remap the modifiers of a modifier mask,
+    /// all remapped modifiers are replaced at once, e.g. for swaps
+    fn mask(&self, mask: u32) -> u32 {
+        let all_from = self.modifiers.iter().fold(0, |all, (from, _)| all | from);
+        self.modifiers
+            .iter()
+            .filter(|(from, _)| mask & from != 0)
+            .fold(mask & !all_from, |remapped, (_, to)| remapped | to)
+    }
+
+    fn event(&self, event: Event) -> Event {
+        match event {
+            Event::Pointer(PointerEvent::Button {
+                time,
+                button,
+                state,
+            }) => Event::Pointer(PointerEvent::Button {
+                time,
+                button: self.buttons.get(&button).copied().unwrap_or(button),
+                state,
+            }),
+            Event::Keyboard(KeyboardEvent::Key { time, key, state }) => {
+                Event::Keyboard(KeyboardEvent::Key {
+                    time,
+                    key: self.key(key),
+                    state,
+                })
+            }
+            Event::Keyboard(KeyboardEvent::Modifiers {
+                depressed,
+                latched,
+                locked,
+                group,
+            }) => Event::Keyboard(KeyboardEvent::Modifiers {
+                depressed: self.mask(depressed),
+                latched: self.mask(latched),
+                locked,
+                group,
+            }),
+            event => event,
         }
-        capture.release().await
+    }
+}
+
+/// scale pointer motion and scroll events according to the settings of a client
+fn apply_pointer_settings(settings: &PointerSettings, event: Event) -> Event {
+    let scroll = if settings.natural_scroll {
+        -settings.scroll_multiplier
+    } else {
+        settings.scroll_multiplier
+    };
+    match event {
+        Event::Pointer(PointerEvent::Motion { time, dx, dy }) => {
+            let gain = settings.motion_multiplier * (1.0 + settings.acceleration * dx.hypot(dy));
+            Event::Pointer(PointerEvent::Motion {
+                time,
+                dx: dx * gain,
+                dy: dy * gain,
+            })
+        }
+        Event::Pointer(PointerEvent::Axis { time, axis, value }) => {
+            Event::Pointer(PointerEvent::Axis {
+                time,
+                axis,
+                value: value * scroll,
+            })
+        }
+        Event::Pointer(PointerEvent::AxisDiscrete120 { axis, value }) => {
+            Event::Pointer(PointerEvent::AxisDiscrete120 {
+                axis,
+                value: (value as f64 * scroll).round() as i32,
+            })
+        }
+        event => event,
     }
 }
 
@@ -440,6 +875,30 @@ fn to_capture_pos(pos: lan_mouse_ipc::Position) -> input_capture::Position {
     }
 }
 
+fn to_capture_segment(segment: lan_mouse_ipc::EdgeSegment) -> Segment {
+    Segment {
+        offset: segment.offset,
+        length: segment.length,
+    }
+}
+
+/// resolves once the dwell time of the held back crossing is over
+async fn dwell_time_over(guarded_crossing: &Option<(CaptureHandle, GuardedCrossing)>) {
+    match guarded_crossing.as_ref().and_then(|(_, g)| g.deadline()) {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// whether the crossing lies within the segment, missing segments
+/// cover the whole edge and unknown crossings match any segment
+fn segment_contains(segment: Option<Segment>, crossing: Option<Crossing>) -> bool {
+    match (segment, crossing) {
+        (Some(segment), Some(crossing)) => segment.contains(&crossing),
+        _ => true,
+    }
+}
+
 fn to_proto_pos(pos: input_capture::Position) -> lan_mouse_proto::Position {
     match pos {
         input_capture::Position::Left => lan_mouse_proto::Position::Left,
@@ -469,3 +928,160 @@ impl<T> Drop for DropGuard<T> {
             .expect("channel closed");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn remap(keys: &[(scancode::Linux, scancode::Linux)]) -> Remap {
+        let keys = keys.iter().map(|&(f, t)| (f as u32, t as u32)).collect();
+        let buttons = [(input_event::BTN_BACK, input_event::BTN_FORWARD)].into();
+        Remap::new(keys, buttons)
+    }
+
+    #[test]
+    fn remap_keys_and_buttons() {
+        let remap = remap(&[(scancode::Linux::KeyA, scancode::Linux::KeyB)]);
+        let key = |key: scancode::Linux| {
+            Event::Keyboard(KeyboardEvent::Key {
+                time: 0,
+                key: key as u32,
+                state: 1,
+            })
+        };
+        let button = |button| {
+            Event::Pointer(PointerEvent::Button {
+                time: 0,
+                button,
+                state: 1,
+            })
+        };
+        assert_eq!(
+            remap.event(key(scancode::Linux::KeyA)),
+            key(scancode::Linux::KeyB)
+        );
+        assert_eq!(
+            remap.event(key(scancode::Linux::KeyB)),
+            key(scancode::Linux::KeyB)
+        );
+        assert_eq!(
+            remap.event(button(input_event::BTN_BACK)),
+            button(input_event::BTN_FORWARD)
+        );
+        assert_eq!(
+            remap.event(button(input_event::BTN_LEFT)),
+            button(input_event::BTN_LEFT)
+        );
+    }
+
+    #[test]
+    fn remap_mask() {
+        let [shift, ctrl, alt, sup] = [
+            Modifier::Shift,
+            Modifier::Ctrl,
+            Modifier::Alt,
+            Modifier::Super,
+        ]
+        .map(Modifier::mask);
+
+        let swap = remap(&[
+            (scancode::Linux::KeyLeftCtrl, scancode::Linux::KeyLeftMeta),
+            (scancode::Linux::KeyLeftMeta, scancode::Linux::KeyLeftCtrl),
+        ]);
+        assert_eq!(swap.mask(ctrl), sup);
+        assert_eq!(swap.mask(sup | shift), ctrl | shift);
+        assert_eq!(swap.mask(ctrl | sup), ctrl | sup);
+        assert_eq!(swap.mask(alt), alt);
+
+        // both modifiers are replaced by the same one
+        let merge = remap(&[
+            (scancode::Linux::KeyLeftCtrl, scancode::Linux::KeyLeftAlt),
+            (scancode::Linux::KeyLeftMeta, scancode::Linux::KeyLeftAlt),
+        ]);
+        assert_eq!(merge.mask(ctrl | sup), alt);
+
+        // left and right share a bit
+        let right = remap(&[(scancode::Linux::KeyRightCtrl, scancode::Linux::KeyLeftAlt)]);
+        assert_eq!(right.mask(ctrl), alt);
+
+        // a modifier mapped to a regular key
+        let regular = remap(&[(scancode::Linux::KeyLeftShift, scancode::Linux::KeyA)]);
+        assert_eq!(regular.mask(shift | ctrl), ctrl);
+    }
+
+    fn motion(dx: f64, dy: f64) -> Event {
+        Event::Pointer(PointerEvent::Motion { time: 0, dx, dy })
+    }
+
+    #[test]
+    fn pointer_settings() {
+        let default = PointerSettings::default();
+        assert_eq!(
+            apply_pointer_settings(&default, motion(3., 4.)),
+            motion(3., 4.)
+        );
+
+        let fast = PointerSettings {
+            motion_multiplier: 2.,
+            ..default
+        };
+        assert_eq!(
+            apply_pointer_settings(&fast, motion(3., 4.)),
+            motion(6., 8.)
+        );
+
+        // the gain grows with the distance moved per event
+        let accelerated = PointerSettings {
+            acceleration: 0.1,
+            ..default
+        };
+        assert_eq!(
+            apply_pointer_settings(&accelerated, motion(3., 4.)),
+            motion(4.5, 6.)
+        );
+        assert_eq!(
+            apply_pointer_settings(&accelerated, motion(0., 0.)),
+            motion(0., 0.)
+        );
+
+        // buttons are not affected
+        let button = Event::Pointer(PointerEvent::Button {
+            time: 0,
+            button: input_event::BTN_LEFT,
+            state: 1,
+        });
+        assert_eq!(apply_pointer_settings(&fast, button), button);
+    }
+
+    #[test]
+    fn scroll_settings() {
+        let axis = |value| {
+            Event::Pointer(PointerEvent::Axis {
+                time: 0,
+                axis: 0,
+                value,
+            })
+        };
+        let discrete = |value| Event::Pointer(PointerEvent::AxisDiscrete120 { axis: 0, value });
+        let settings = PointerSettings {
+            motion_multiplier: 2.,
+            scroll_multiplier: 1.5,
+            ..Default::default()
+        };
+        assert_eq!(apply_pointer_settings(&settings, axis(2.)), axis(3.));
+        assert_eq!(
+            apply_pointer_settings(&settings, discrete(120)),
+            discrete(180)
+        );
+        let natural = PointerSettings {
+            natural_scroll: true,
+            ..settings
+        };
+        assert_eq!(apply_pointer_settings(&natural, axis(2.)), axis(-3.));
+        // fractions of a discrete step are rounded
+        assert_eq!(
+            apply_pointer_settings(&natural, discrete(15)),
+            discrete(-23)
+        );
+    }
+}
@@ -1,26 +1,40 @@
+use std::path::PathBuf;
+
 use crate::config::Config;
 use clap::Args;
 use futures::StreamExt;
-use input_capture::{self, CaptureError, CaptureEvent, InputCapture, InputCaptureError, Position};
+use input_capture::{
+    self, CaptureError, CaptureEvent, DummySource, InputCapture, InputCaptureError, Position,
+};
 use input_event::{Event, KeyboardEvent};
 
 #[derive(Args, Clone, Debug, Eq, PartialEq)]
-pub struct TestCaptureArgs {}
+pub struct TestCaptureArgs {
+    /// replay a script with the dummy backend (a file, a unix socket or `-` for stdin)
+    #[arg(long)]
+    script: Option<PathBuf>,
+}
 
-pub async fn run(config: Config, _args: TestCaptureArgs) -> Result<(), InputCaptureError> {
+pub async fn run(config: Config, args: TestCaptureArgs) -> Result<(), InputCaptureError> {
     log::info!("running input capture test");
     log::info!("creating input capture");
-    let backend = config.capture_backend().map(|b| b.into());
+    let backend = match args.script {
+        Some(path) => Some(input_capture::Backend::Dummy(DummySource::Script(path))),
+        None => config.capture_backend(),
+    };
     loop {
-        let mut input_capture = InputCapture::new(backend).await?;
+        let mut input_capture = InputCapture::new(backend.clone()).await?;
         log::info!("creating clients");
-        input_capture.create(0, Position::Left).await?;
-        input_capture.create(4, Position::Left).await?;
-        input_capture.create(1, Position::Right).await?;
-        input_capture.create(2, Position::Top).await?;
-        input_capture.create(3, Position::Bottom).await?;
-        if let Err(e) = do_capture(&mut input_capture).await {
-            log::warn!("{e} - recreating capture");
+        input_capture.create(0, Position::Left, None).await?;
+        input_capture.create(4, Position::Left, None).await?;
+        input_capture.create(1, Position::Right, None).await?;
+        input_capture.create(2, Position::Top, None).await?;
+        input_capture.create(3, Position::Bottom, None).await?;
+        match do_capture(&mut input_capture).await {
+            // running an invalid script again does not help
+            Err(e @ CaptureError::Script(_)) => return Err(e.into()),
+            Err(e) => log::warn!("{e} - recreating capture"),
+            Ok(()) => {}
         }
         let _ = input_capture.terminate().await;
     }
@@ -1,13 +1,17 @@
 use std::{
     cell::RefCell,
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     net::{IpAddr, SocketAddr},
     rc::Rc,
 };
 
 use slab::Slab;
 
-use lan_mouse_ipc::{ClientConfig, ClientHandle, ClientState, Position};
+use lan_mouse_ipc::{
+    Capability, ClientConfig, ClientHandle, ClientState, EdgeSegment, HookEvent, PointerSettings,
+    Position,
+};
+use lan_mouse_proto::Capabilities;
 
 use crate::config::ConfigClient;
 
@@ -32,11 +36,20 @@ impl ClientManager {
             fix_ips: config_client.ips.into_iter().collect(),
             port: config_client.port,
             pos: config_client.pos,
+            segment: config_client.segment,
             cmd: config_client.enter_hook,
+            leave_hook: config_client.leave_hook,
+            connect_hook: config_client.connect_hook,
+            disconnect_hook: config_client.disconnect_hook,
+            fingerprint: config_client.fingerprint,
+            key_map: config_client.key_map,
+            button_map: config_client.button_map,
+            hotkey: config_client.hotkey,
         };
         let state = ClientState {
             active: config_client.active,
             ips: HashSet::from_iter(config.fix_ips.iter().cloned()),
+            pointer: config_client.pointer,
             ..Default::default()
         };
         let handle = self.add_client();
@@ -108,18 +121,26 @@ impl ClientManager {
     }
 
     /// get the client at the given position
-    pub fn client_at(&self, pos: Position) -> Option<ClientHandle> {
-        self.clients
-            .borrow()
+    /// active clients at the same position as `handle`,
+    /// whose segments of the edge overlap with the one of `handle`
+    pub(crate) fn overlapping_clients(&self, handle: ClientHandle) -> Vec<ClientHandle> {
+        let clients = self.clients.borrow();
+        let Some((config, _)) = clients.get(handle as usize) else {
+            return vec![];
+        };
+        clients
             .iter()
-            .find_map(|(k, (c, s))| {
-                if s.active && c.pos == pos {
-                    Some(k)
-                } else {
-                    None
-                }
+            .filter(|&(k, (c, s))| {
+                k as ClientHandle != handle
+                    && s.active
+                    && c.pos == config.pos
+                    && match (c.segment, config.segment) {
+                        (Some(a), Some(b)) => a.overlaps(&b),
+                        _ => true,
+                    }
             })
-            .map(|p| p as ClientHandle)
+            .map(|(k, _)| k as ClientHandle)
+            .collect()
     }
 
     pub(crate) fn get_hostname(&self, handle: ClientHandle) -> Option<String> {
@@ -137,6 +158,13 @@ impl ClientManager {
             .map(|(c, _)| c.pos)
     }
 
+    pub(crate) fn get_segment(&self, handle: ClientHandle) -> Option<EdgeSegment> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .and_then(|(c, _)| c.segment)
+    }
+
     /// remove a client from the list
     pub fn remove_client(&self, client: ClientHandle) -> Option<(ClientConfig, ClientState)> {
         // remove id from occupied ids
@@ -229,13 +257,85 @@ impl ClientManager {
         }
     }
 
-    /// update the enter hook command of the client
-    pub(crate) fn set_enter_hook(&self, handle: ClientHandle, enter_hook: Option<String>) {
+    /// update the claimed segment of the edge, returns whether the client is active
+    pub(crate) fn set_segment(&self, handle: ClientHandle, segment: Option<EdgeSegment>) -> bool {
+        match self.clients.borrow_mut().get_mut(handle as usize) {
+            Some((c, s)) if c.segment != segment => {
+                c.segment = segment;
+                s.active
+            }
+            _ => false,
+        }
+    }
+
+    /// update the command executed on the given event,
+    /// hooks not belonging to a client are ignored
+    pub(crate) fn set_hook(&self, handle: ClientHandle, event: HookEvent, cmd: Option<String>) {
+        if let Some((c, _s)) = self.clients.borrow_mut().get_mut(handle as usize) {
+            match event {
+                HookEvent::Enter => c.cmd = cmd,
+                HookEvent::Leave => c.leave_hook = cmd,
+                HookEvent::Connect => c.connect_hook = cmd,
+                HookEvent::Disconnect => c.disconnect_hook = cmd,
+                HookEvent::IncomingConnect | HookEvent::IncomingDisconnect => {}
+            }
+        }
+    }
+
+    /// update the expected certificate fingerprint of the client
+    pub(crate) fn set_fingerprint(&self, handle: ClientHandle, fingerprint: Option<String>) {
         if let Some((c, _s)) = self.clients.borrow_mut().get_mut(handle as usize) {
-            c.cmd = enter_hook;
+            c.fingerprint = fingerprint;
         }
     }
 
+    /// get the expected certificate fingerprint of the client
+    pub(crate) fn get_fingerprint(&self, handle: ClientHandle) -> Option<String> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .and_then(|(c, _)| c.fingerprint.clone())
+    }
+
+    /// get the keys replaced before sending them to the client
+    pub(crate) fn get_key_map(&self, handle: ClientHandle) -> Option<HashMap<u32, u32>> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .map(|(c, _)| c.key_map.clone())
+    }
+
+    /// get the mouse buttons replaced before sending them to the client
+    pub(crate) fn get_button_map(&self, handle: ClientHandle) -> Option<HashMap<u32, u32>> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .map(|(c, _)| c.button_map.clone())
+    }
+
+    /// get the keys capturing input for the client directly
+    pub(crate) fn get_hotkey(&self, handle: ClientHandle) -> Option<Vec<u32>> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .map(|(c, _)| c.hotkey.clone())
+    }
+
+    /// update the pointer speed and scroll settings of the client
+    pub(crate) fn set_pointer_settings(&self, handle: ClientHandle, pointer: PointerSettings) {
+        if let Some((_, s)) = self.clients.borrow_mut().get_mut(handle as usize) {
+            s.pointer = pointer;
+        }
+    }
+
+    /// get the pointer speed and scroll settings of the client
+    pub(crate) fn get_pointer_settings(&self, handle: ClientHandle) -> Option<PointerSettings> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .map(|(_, s)| s.pointer)
+    }
+
     /// set resolving status of the client
     pub(crate) fn set_resolving(&self, handle: ClientHandle, status: bool) {
         if let Some((_, s)) = self.clients.borrow_mut().get_mut(handle as usize) {
@@ -243,12 +343,18 @@ impl ClientManager {
         }
     }
 
-    /// get the enter hook command
-    pub(crate) fn get_enter_cmd(&self, handle: ClientHandle) -> Option<String> {
+    /// get the command executed on the given event
+    pub(crate) fn get_hook(&self, handle: ClientHandle, event: HookEvent) -> Option<String> {
         self.clients
             .borrow()
             .get(handle as usize)
-            .and_then(|(c, _)| c.cmd.clone())
+            .and_then(|(c, _)| match event {
+                HookEvent::Enter => c.cmd.clone(),
+                HookEvent::Leave => c.leave_hook.clone(),
+                HookEvent::Connect => c.connect_hook.clone(),
+                HookEvent::Disconnect => c.disconnect_hook.clone(),
+                HookEvent::IncomingConnect | HookEvent::IncomingDisconnect => None,
+            })
     }
 
     /// returns all clients that are currently registered
@@ -288,6 +394,26 @@ impl ClientManager {
         }
     }
 
+    /// set the protocol version and negotiated capabilities of a client
+    pub(crate) fn set_capabilities(&self, handle: ClientHandle, peer: Option<(u16, Capabilities)>) {
+        if let Some((_, s)) = self.clients.borrow_mut().get_mut(handle as usize) {
+            s.protocol_version = peer.map(|(v, _)| v);
+            s.capabilities = peer
+                .map(|(_, c)| to_ipc_capabilities(c))
+                .unwrap_or_default();
+        }
+    }
+
+    /// capabilities negotiated with the given client,
+    /// `None` if no handshake took place (yet)
+    pub(crate) fn capabilities(&self, handle: ClientHandle) -> Option<Capabilities> {
+        self.clients
+            .borrow()
+            .get(handle as usize)
+            .filter(|(_, s)| s.protocol_version.is_some())
+            .map(|(_, s)| from_ipc_capabilities(&s.capabilities))
+    }
+
     pub(crate) fn active_addr(&self, handle: ClientHandle) -> Option<SocketAddr> {
         self.clients
             .borrow()
@@ -317,3 +443,28 @@ impl ClientManager {
             .map(|(_, s)| s.ips.clone())
     }
 }
+
+const CAPABILITIES: [(Capabilities, Capability); 3] = [
+    (Capabilities::CLIPBOARD, Capability::Clipboard),
+    (Capabilities::ABSOLUTE_MOTION, Capability::AbsoluteMotion),
+    (Capabilities::HIGH_RES_SCROLL, Capability::HighResScroll),
+];
+
+fn to_ipc_capabilities(capabilities: Capabilities) -> Vec<Capability> {
+    CAPABILITIES
+        .into_iter()
+        .filter(|&(c, _)| capabilities.contains(c))
+        .map(|(_, c)| c)
+        .collect()
+}
+
+fn from_ipc_capabilities(capabilities: &[Capability]) -> Capabilities {
+    let mut caps = Capabilities::empty();
+    for (c, _) in CAPABILITIES
+        .iter()
+        .filter(|(_, c)| capabilities.contains(c))
+    {
+        caps.insert(*c);
+    }
+    caps
+}
@@ -0,0 +1,489 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash, Hasher, RandomState},
+    rc::Rc,
+    time::Duration,
+};
+
+use lan_mouse_clipboard::Clipboard;
+use lan_mouse_proto::{CLIPBOARD_CHUNK_SIZE, ProtoEvent};
+use tokio::{
+    task::{JoinHandle, spawn_local},
+    time::Instant,
+};
+
+/// clipboard content larger than this is not shared
+const MAX_CLIPBOARD_SIZE: usize = 64 * 1024;
+
+/// number of events sent at once
+const BURST_SIZE: usize = 16;
+
+/// pause between two bursts, so the socket buffers
+/// of both ends are not flooded
+const BURST_INTERVAL: Duration = Duration::from_millis(2);
+
+/// time to wait for the [`ProtoEvent::ClipboardAck`] before sending the content again
+const ACK_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// number of times the content is sent before giving up
+const MAX_ATTEMPTS: u32 = 3;
+
+/// splits clipboard content into [`ProtoEvent::ClipboardOffer`]
+/// and [`ProtoEvent::ClipboardData`] events and sends them to a peer
+/// in paced bursts (see [`ClipboardSender::next`]), until it is acknowledged.
+///
+/// Content is only sent if it differs from the content last exchanged with the peer.
+pub(crate) struct ClipboardSender<K> {
+    next_id: u16,
+    /// hash of the content last exchanged with each peer
+    exchanged: HashMap<K, u64>,
+    /// clipboard being read for a peer
+    read: Option<(K, JoinHandle<Option<String>>)>,
+    transfer: Option<Transfer<K>>,
+}
+
+struct Transfer<K> {
+    peer: K,
+    id: u16,
+    hash: u64,
+    /// offer followed by the data chunks
+    events: Vec<ProtoEvent>,
+    /// number of events sent in the current attempt
+    sent: usize,
+    attempt: u32,
+    next_burst: Instant,
+}
+
+impl<K: Copy + Eq + Hash> Default for ClipboardSender<K> {
+    fn default() -> Self {
+        Self {
+            // ids of a previous run may still be known to the peer
+            next_id: RandomState::new().hash_one(0) as u16,
+            exchanged: Default::default(),
+            read: None,
+            transfer: None,
+        }
+    }
+}
+
+impl<K: Copy + Eq + Hash> ClipboardSender<K> {
+    /// send the local clipboard to `peer`, cancelling any transfer in progress.
+    /// The clipboard is read in the background.
+    pub(crate) fn send(&mut self, peer: K, clipboard: &Rc<dyn Clipboard>) {
+        self.cancel();
+        let clipboard = clipboard.clone();
+        let read = spawn_local(async move {
+            match clipboard.get_text().await {
+                Ok(text) => text,
+                Err(e) => {
+                    log::warn!("could not read clipboard: {e}");
+                    None
+                }
+            }
+        });
+        self.read = Some((peer, read));
+    }
+
+    /// the next burst of events to send and the peer to send them to.
+    /// Cancel safe, pending forever while there is nothing to send.
+    pub(crate) async fn next(&mut self) -> (K, Vec<ProtoEvent>) {
+        loop {
+            if let Some((peer, read)) = self.read.as_mut() {
+                let text = read.await.ok().flatten();
+                let peer = *peer;
+                self.read = None;
+                if let Some(text) = text {
+                    self.start(peer, text);
+                }
+                continue;
+            }
+            let Some(transfer) = self.transfer.as_mut() else {
+                return std::future::pending().await;
+            };
+            tokio::time::sleep_until(transfer.next_burst).await;
+            match transfer.burst() {
+                Some(events) => return (transfer.peer, events),
+                None => {
+                    log::warn!("clipboard transfer {} was not acknowledged", transfer.id);
+                    self.transfer = None;
+                }
+            }
+        }
+    }
+
+    /// the peer acknowledged the content with the given id
+    pub(crate) fn ack(&mut self, peer: K, id: u16) {
+        if let Some(t) = self.transfer.take_if(|t| t.peer == peer && t.id == id) {
+            log::debug!("clipboard transfer {id} acknowledged");
+            self.exchanged.insert(peer, t.hash);
+        }
+    }
+
+    /// `text` was received from `peer`, so it does not need to be sent back
+    pub(crate) fn received(&mut self, peer: K, text: &str) {
+        self.exchanged.insert(peer, hash(text));
+    }
+
+    /// the peer disconnected
+    pub(crate) fn forget(&mut self, peer: K) {
+        self.exchanged.remove(&peer);
+        if self.read.as_ref().is_some_and(|(p, _)| *p == peer)
+            || self.transfer.as_ref().is_some_and(|t| t.peer == peer)
+        {
+            self.cancel();
+        }
+    }
+
+    fn cancel(&mut self) {
+        if let Some((_, read)) = self.read.take() {
+            read.abort();
+        }
+        self.transfer = None;
+    }
+
+    fn start(&mut self, peer: K, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        if text.len() > MAX_CLIPBOARD_SIZE {
+            log::info!("not sharing clipboard: {} bytes", text.len());
+            return;
+        }
+        let hash = hash(&text);
+        if self.exchanged.get(&peer) == Some(&hash) {
+            log::debug!("clipboard unchanged, not sending it again");
+            return;
+        }
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.transfer = Some(Transfer {
+            peer,
+            id,
+            hash,
+            events: events(id, &text),
+            sent: 0,
+            attempt: 1,
+            next_burst: Instant::now(),
+        });
+    }
+}
+
+impl<K> Transfer<K> {
+    /// the next burst of events, `None` after the last attempt
+    fn burst(&mut self) -> Option<Vec<ProtoEvent>> {
+        if self.sent == self.events.len() {
+            if self.attempt == MAX_ATTEMPTS {
+                return None;
+            }
+            log::debug!("clipboard transfer {} not acknowledged, retrying", self.id);
+            self.attempt += 1;
+            self.sent = 0;
+        }
+        let end = (self.sent + BURST_SIZE).min(self.events.len());
+        let burst = self.events[self.sent..end].to_vec();
+        self.sent = end;
+        self.next_burst = Instant::now()
+            + match self.sent == self.events.len() {
+                true => ACK_TIMEOUT,
+                false => BURST_INTERVAL,
+            };
+        Some(burst)
+    }
+}
+
+fn hash(text: &str) -> u64 {
+    let mut hasher = std::hash::DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// split the text into an offer and its data chunks
+fn events(id: u16, text: &str) -> Vec<ProtoEvent> {
+    let offer = ProtoEvent::ClipboardOffer {
+        id,
+        len: text.len() as u32,
+    };
+    let chunks = text.as_bytes().chunks(CLIPBOARD_CHUNK_SIZE);
+    let data = chunks.enumerate().map(|(i, chunk)| {
+        let mut data = Box::new([0u8; CLIPBOARD_CHUNK_SIZE]);
+        data[..chunk.len()].copy_from_slice(chunk);
+        let offset = (i * CLIPBOARD_CHUNK_SIZE) as u32;
+        ProtoEvent::ClipboardData { id, offset, data }
+    });
+    std::iter::once(offer).chain(data).collect()
+}
+
+struct Incoming {
+    id: u16,
+    content: Vec<u8>,
+    received: Vec<bool>,
+    missing: usize,
+}
+
+/// a completely received clipboard content,
+/// to be acknowledged with [`ProtoEvent::ClipboardAck`]
+#[derive(Debug, PartialEq)]
+pub(crate) struct Received {
+    pub(crate) id: u16,
+    /// `None` if the content was received before and sent again,
+    /// because the acknowledgement got lost
+    pub(crate) text: Option<String>,
+}
+
+/// reassembles clipboard content from [`ProtoEvent::ClipboardOffer`]
+/// and [`ProtoEvent::ClipboardData`] events.
+/// Chunks may get lost, in which case the sender sends the content again:
+/// chunks received in a previous attempt are kept.
+#[derive(Default)]
+pub(crate) struct ClipboardReceiver {
+    transfer: Option<Incoming>,
+    /// id of the last completed transfer
+    completed: Option<u16>,
+}
+
+impl ClipboardReceiver {
+    /// handle a clipboard event, returns the clipboard content once complete
+    pub(crate) fn receive(&mut self, event: ProtoEvent) -> Option<Received> {
+        match event {
+            ProtoEvent::ClipboardOffer { id, .. } if self.completed == Some(id) => {
+                return Some(Received { id, text: None });
+            }
+            ProtoEvent::ClipboardOffer { id, len } => {
+                let len = len as usize;
+                if len == 0 || len > MAX_CLIPBOARD_SIZE {
+                    log::warn!("ignoring clipboard offer: {len} bytes");
+                    self.transfer.take();
+                    return None;
+                }
+                // a repeated offer keeps the chunks received so far
+                if self
+                    .transfer
+                    .as_ref()
+                    .is_none_or(|t| t.id != id || t.content.len() != len)
+                {
+                    let chunks = len.div_ceil(CLIPBOARD_CHUNK_SIZE);
+                    self.transfer.replace(Incoming {
+                        id,
+                        content: vec![0; len],
+                        received: vec![false; chunks],
+                        missing: chunks,
+                    });
+                }
+            }
+            ProtoEvent::ClipboardData { id, offset, data } => {
+                let transfer = self.transfer.as_mut().filter(|t| t.id == id)?;
+                let offset = offset as usize;
+                let idx = offset / CLIPBOARD_CHUNK_SIZE;
+                if !offset.is_multiple_of(CLIPBOARD_CHUNK_SIZE) || idx >= transfer.received.len() {
+                    return None;
+                }
+                if !transfer.received[idx] {
+                    let len = (transfer.content.len() - offset).min(CLIPBOARD_CHUNK_SIZE);
+                    transfer.content[offset..offset + len].copy_from_slice(&data[..len]);
+                    transfer.received[idx] = true;
+                    transfer.missing -= 1;
+                }
+            }
+            _ => return None,
+        }
+        let t = self.transfer.take_if(|t| t.missing == 0)?;
+        self.completed = Some(t.id);
+        Some(Received {
+            id: t.id,
+            text: Some(String::from_utf8_lossy(&t.content).into_owned()),
+        })
+    }
+}
+
+/// set the clipboard content in the background
+pub(crate) fn set_clipboard(clipboard: &Rc<dyn Clipboard>, text: String) {
+    let clipboard = clipboard.clone();
+    spawn_local(async move {
+        if let Err(e) = clipboard.set_text(text).await {
+            log::warn!("could not set clipboard: {e}");
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(len: usize) -> String {
+        (0..len).map(|i| (b'a' + (i % 26) as u8) as char).collect()
+    }
+
+    fn receive_all<'a>(
+        receiver: &mut ClipboardReceiver,
+        events: impl IntoIterator<Item = &'a ProtoEvent>,
+    ) -> Vec<Received> {
+        events
+            .into_iter()
+            .filter_map(|e| receiver.receive(e.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn reassemble() {
+        let text = text(3 * CLIPBOARD_CHUNK_SIZE + 7);
+        let events = events(1, &text);
+        assert_eq!(events.len(), 5);
+        let mut receiver = ClipboardReceiver::default();
+        let received = receive_all(&mut receiver, &events);
+        assert_eq!(
+            received,
+            [Received {
+                id: 1,
+                text: Some(text)
+            }]
+        );
+    }
+
+    #[test]
+    fn reassemble_out_of_order() {
+        let text = text(2 * CLIPBOARD_CHUNK_SIZE);
+        let mut events = events(1, &text);
+        events[1..].reverse();
+        let mut receiver = ClipboardReceiver::default();
+        let received = receive_all(&mut receiver, &events);
+        assert_eq!(received[0].text.as_deref(), Some(text.as_str()));
+    }
+
+    #[test]
+    fn lost_chunk_is_filled_in_by_retransmission() {
+        let text = text(3 * CLIPBOARD_CHUNK_SIZE);
+        let events = events(7, &text);
+        let mut receiver = ClipboardReceiver::default();
+        let first = events.iter().enumerate().filter(|&(i, _)| i != 2);
+        assert!(receive_all(&mut receiver, first.map(|(_, e)| e)).is_empty());
+        // the second attempt only needs to deliver the offer and the lost chunk
+        let received = receive_all(&mut receiver, [&events[0], &events[2]]);
+        assert_eq!(received[0].text.as_deref(), Some(text.as_str()));
+    }
+
+    #[test]
+    fn repeated_offer_of_completed_transfer_is_acknowledged_again() {
+        let events = events(3, "hello");
+        let mut receiver = ClipboardReceiver::default();
+        receive_all(&mut receiver, &events);
+        let received = receive_all(&mut receiver, &events);
+        assert_eq!(received, [Received { id: 3, text: None }]);
+    }
+
+    #[test]
+    fn new_offer_discards_incomplete_transfer() {
+        let old = events(1, &text(2 * CLIPBOARD_CHUNK_SIZE));
+        let new = events(2, "new");
+        let mut receiver = ClipboardReceiver::default();
+        receive_all(&mut receiver, &old[..2]);
+        let received = receive_all(&mut receiver, new.iter().chain([&old[2]]));
+        assert_eq!(
+            received,
+            [Received {
+                id: 2,
+                text: Some("new".into())
+            }]
+        );
+    }
+
+    #[test]
+    fn invalid_offers_and_chunks_are_ignored() {
+        let mut receiver = ClipboardReceiver::default();
+        let empty = ProtoEvent::ClipboardOffer { id: 1, len: 0 };
+        let huge = ProtoEvent::ClipboardOffer {
+            id: 2,
+            len: MAX_CLIPBOARD_SIZE as u32 + 1,
+        };
+        assert!(receive_all(&mut receiver, &[empty, huge]).is_empty());
+
+        let events = events(3, "hello");
+        let data = |offset| ProtoEvent::ClipboardData {
+            id: 3,
+            offset,
+            data: Box::new([b'x'; CLIPBOARD_CHUNK_SIZE]),
+        };
+        let invalid = [data(1), data(CLIPBOARD_CHUNK_SIZE as u32)];
+        assert!(receive_all(&mut receiver, [&events[0]].into_iter().chain(&invalid)).is_empty());
+        let received = receive_all(&mut receiver, &events[1..]);
+        assert_eq!(received[0].text.as_deref(), Some("hello"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sender_retransmits_until_acknowledged() {
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let clipboard: Rc<dyn Clipboard> =
+                    lan_mouse_clipboard::create(Some(lan_mouse_clipboard::Backend::Memory))
+                        .await
+                        .expect("clipboard")
+                        .into();
+                let text = text(20 * CLIPBOARD_CHUNK_SIZE);
+                clipboard.set_text(text.clone()).await.expect("set");
+
+                let mut sender = ClipboardSender::default();
+                let mut receiver = ClipboardReceiver::default();
+                sender.send(0u8, &clipboard);
+                let mut bursts = vec![];
+                // first attempt, everything but the first chunk is lost
+                while bursts.iter().map(Vec::len).sum::<usize>() < 21 {
+                    let (peer, events) = sender.next().await;
+                    assert_eq!(peer, 0);
+                    assert!(events.len() <= BURST_SIZE);
+                    bursts.push(events);
+                }
+                let first: Vec<_> = bursts.concat();
+                assert!(receive_all(&mut receiver, &first[..2]).is_empty());
+
+                // second attempt after the ack timeout
+                let (_, events) = sender.next().await;
+                assert!(matches!(events[0], ProtoEvent::ClipboardOffer { .. }));
+                let mut received = receive_all(&mut receiver, &events);
+                while received.is_empty() {
+                    let (_, events) = sender.next().await;
+                    received = receive_all(&mut receiver, &events);
+                }
+                assert_eq!(received[0].text.as_deref(), Some(text.as_str()));
+                sender.ack(0, received[0].id);
+
+                // nothing left to send, the unchanged content is not sent again
+                sender.send(0, &clipboard);
+                let next = tokio::time::timeout(Duration::from_secs(5), sender.next());
+                assert!(next.await.is_err());
+            })
+            .await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sender_gives_up_and_skips_empty_clipboard() {
+        tokio::task::LocalSet::new()
+            .run_until(async {
+                let clipboard: Rc<dyn Clipboard> =
+                    lan_mouse_clipboard::create(Some(lan_mouse_clipboard::Backend::Memory))
+                        .await
+                        .expect("clipboard")
+                        .into();
+                let mut sender = ClipboardSender::default();
+
+                // empty clipboard
+                sender.send(0u8, &clipboard);
+                let next = tokio::time::timeout(Duration::from_secs(5), sender.next());
+                assert!(next.await.is_err());
+
+                clipboard.set_text("hello".into()).await.expect("set");
+                sender.send(0, &clipboard);
+                for _ in 0..MAX_ATTEMPTS {
+                    let (_, events) = sender.next().await;
+                    assert_eq!(events.len(), 2);
+                }
+                let next = tokio::time::timeout(Duration::from_secs(5), sender.next());
+                assert!(next.await.is_err());
+
+                // content received from the peer is not sent back
+                sender.received(0, "hello");
+                sender.send(0, &clipboard);
+                let next = tokio::time::timeout(Duration::from_secs(5), sender.next());
+                assert!(next.await.is_err());
+            })
+            .await;
+    }
+}
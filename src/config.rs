@@ -1,27 +1,35 @@
 use crate::capture_test::TestCaptureArgs;
 use crate::emulation_test::TestEmulationArgs;
+use crate::hooks::{self, Hooks};
+use crate::recording::{RecordArgs, ReplayArgs};
 use clap::{Parser, Subcommand, ValueEnum};
 use notify::event::ModifyKind;
 use notify::{EventKind, RecommendedWatcher, Watcher};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::env::{self, VarError};
+use std::ffi::OsString;
 use std::fmt::Display;
 use std::fs::{self, File};
 use std::io::Write;
-use std::net::IpAddr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
 use std::{collections::HashSet, io};
 use thiserror::Error;
 use toml;
 use toml_edit::{self, DocumentMut};
 
 use lan_mouse_cli::CliArgs;
-use lan_mouse_ipc::{DEFAULT_PORT, Position};
+use lan_mouse_ipc::{DEFAULT_PORT, EdgeSegment, PointerSettings, Position};
 
-use input_event::scancode::{
-    self,
-    Linux::{KeyLeftAlt, KeyLeftCtrl, KeyLeftMeta, KeyLeftShift},
+use input_event::{
+    Button, Modifier,
+    scancode::{
+        self,
+        Linux::{KeyLeftAlt, KeyLeftCtrl, KeyLeftMeta, KeyLeftShift},
+    },
 };
 
 use shadow_rs::shadow;
@@ -63,23 +71,124 @@ fn default_path() -> Result<PathBuf, VarError> {
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
 struct ConfigToml {
     capture_backend: Option<CaptureBackend>,
+    screen_size: Option<ScreenSize>,
+    capture_script: Option<PathBuf>,
     emulation_backend: Option<EmulationBackend>,
+    emulation_trace: Option<PathBuf>,
+    clipboard_backend: Option<ClipboardBackend>,
+    clipboard: Option<bool>,
     port: Option<u16>,
+    listen_addresses: Option<Vec<SocketAddr>>,
+    discovery: Option<bool>,
+    dbus: Option<bool>,
     release_bind: Option<Vec<scancode::Linux>>,
+    edge_guard: Option<TomlEdgeGuard>,
+    hooks: Option<TomlHooks>,
     cert_path: Option<PathBuf>,
     clients: Option<Vec<TomlClient>>,
-    authorized_fingerprints: Option<HashMap<String, String>>,
+    authorized_fingerprints: Option<HashMap<String, AuthorizedKey>>,
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 struct TomlClient {
     hostname: Option<String>,
     host_name: Option<String>,
     ips: Option<Vec<IpAddr>>,
     port: Option<u16>,
     position: Option<Position>,
+    segment: Option<EdgeSegment>,
     activate_on_startup: Option<bool>,
     enter_hook: Option<String>,
+    leave_hook: Option<String>,
+    connect_hook: Option<String>,
+    disconnect_hook: Option<String>,
+    fingerprint: Option<String>,
+    key_map: Option<HashMap<scancode::Linux, scancode::Linux>>,
+    button_map: Option<HashMap<Button, Button>>,
+    motion_multiplier: Option<f64>,
+    acceleration: Option<f64>,
+    scroll_multiplier: Option<f64>,
+    natural_scroll: Option<bool>,
+    hotkey: Option<Vec<scancode::Linux>>,
+}
+
+/// entry in `authorized_fingerprints`
+#[derive(Clone, Serialize, Deserialize, Debug, Eq, PartialEq)]
+#[serde(from = "TomlAuthorizedKey", into = "TomlAuthorizedKey")]
+struct AuthorizedKey {
+    description: String,
+    permissions: Permissions,
+    /// written as a plain description, as long as the permissions are the default.
+    /// Entries written as a table are kept as such.
+    plain: bool,
+}
+
+/// older versions map fingerprints to a plain description
+#[derive(Deserialize, Serialize)]
+#[serde(untagged)]
+enum TomlAuthorizedKey {
+    Description(String),
+    Entry {
+        description: String,
+        #[serde(flatten)]
+        permissions: Permissions,
+    },
+}
+
+impl From<TomlAuthorizedKey> for AuthorizedKey {
+    fn from(key: TomlAuthorizedKey) -> Self {
+        match key {
+            TomlAuthorizedKey::Description(description) => Self {
+                description,
+                permissions: Default::default(),
+                plain: true,
+            },
+            TomlAuthorizedKey::Entry {
+                description,
+                permissions,
+            } => Self {
+                description,
+                permissions,
+                plain: false,
+            },
+        }
+    }
+}
+
+impl From<AuthorizedKey> for TomlAuthorizedKey {
+    fn from(key: AuthorizedKey) -> Self {
+        if key.plain && key.permissions == Permissions::default() {
+            Self::Description(key.description)
+        } else {
+            Self::Entry {
+                description: key.description,
+                permissions: key.permissions,
+            }
+        }
+    }
+}
+
+/// what an authorized device may do on this device
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Access {
+    /// connections are accepted but input is never emulated
+    Presence,
+    /// pointer input only
+    Pointer,
+    /// pointer and keyboard input
+    #[default]
+    Full,
+}
+
+/// permissions of an authorized device
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Permissions {
+    #[serde(default)]
+    pub access: Access,
+    /// key combinations that are never emulated, e.g. `["KeyLeftMeta", "KeyL"]`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub blocked_keys: Vec<Vec<scancode::Linux>>,
 }
 
 impl ConfigToml {
@@ -96,6 +205,18 @@ struct Args {
     #[arg(short, long)]
     port: Option<u16>,
 
+    /// addresses to listen on (defaults to all interfaces, ipv4 + ipv6)
+    #[arg(long, value_delimiter = ',')]
+    listen_addresses: Option<Vec<SocketAddr>>,
+
+    /// do not advertise this instance or browse for others via mDNS
+    #[arg(long)]
+    no_discovery: bool,
+
+    /// do not export the d-bus interface
+    #[arg(long)]
+    no_dbus: bool,
+
     /// non-default config file location
     #[arg(short, long)]
     config: Option<PathBuf>,
@@ -104,10 +225,30 @@ struct Args {
     #[arg(long)]
     capture_backend: Option<CaptureBackend>,
 
+    /// screen size used by the evdev capture backend (e.g. 1920x1080)
+    #[arg(long)]
+    screen_size: Option<ScreenSize>,
+
+    /// script replayed by the dummy capture backend (a file, a unix socket or `-` for stdin)
+    #[arg(long)]
+    capture_script: Option<PathBuf>,
+
     /// emulation backend override
     #[arg(long)]
     emulation_backend: Option<EmulationBackend>,
 
+    /// trace file (JSON lines) written by the dummy emulation backend
+    #[arg(long)]
+    emulation_trace: Option<PathBuf>,
+
+    /// clipboard backend override
+    #[arg(long)]
+    clipboard_backend: Option<ClipboardBackend>,
+
+    /// do not share the clipboard with other devices
+    #[arg(long)]
+    no_clipboard: bool,
+
     /// path to non-default certificate location
     #[arg(long)]
     cert_path: Option<PathBuf>,
@@ -117,7 +258,7 @@ struct Args {
     command: Option<Command>,
 }
 
-#[derive(Subcommand, Clone, Debug, Eq, PartialEq)]
+#[derive(Subcommand, Clone, Debug, PartialEq)]
 pub enum Command {
     /// test input emulation
     TestEmulation(TestEmulationArgs),
@@ -125,6 +266,10 @@ pub enum Command {
     TestCapture(TestCaptureArgs),
     /// Lan Mouse commandline interface
     Cli(CliArgs),
+    /// record the events sent by a device (instead of emulating them)
+    Record(RecordArgs),
+    /// replay a recording with the configured emulation backend
+    Replay(ReplayArgs),
     /// run in daemon mode
     Daemon,
 }
@@ -140,6 +285,9 @@ pub enum CaptureBackend {
     #[cfg(x11_capture)]
     #[serde(rename = "x11")]
     X11,
+    #[cfg(evdev_capture)]
+    #[serde(rename = "evdev")]
+    Evdev,
     #[cfg(windows)]
     #[serde(rename = "windows")]
     Windows,
@@ -159,6 +307,8 @@ impl Display for CaptureBackend {
             CaptureBackend::LayerShell => write!(f, "layer-shell"),
             #[cfg(x11_capture)]
             CaptureBackend::X11 => write!(f, "X11"),
+            #[cfg(evdev_capture)]
+            CaptureBackend::Evdev => write!(f, "evdev"),
             #[cfg(windows)]
             CaptureBackend::Windows => write!(f, "windows"),
             #[cfg(target_os = "macos")]
@@ -168,24 +318,126 @@ impl Display for CaptureBackend {
     }
 }
 
-impl From<CaptureBackend> for input_capture::Backend {
-    fn from(backend: CaptureBackend) -> Self {
-        match backend {
+impl CaptureBackend {
+    /// the corresponding [`input_capture::Backend`],
+    /// `screen_size` is required by the evdev backend,
+    /// the dummy backend replays `script` if given
+    fn into_backend(
+        self,
+        #[allow(unused)] screen_size: Option<ScreenSize>,
+        script: Option<PathBuf>,
+    ) -> input_capture::Backend {
+        match self {
             #[cfg(libei_capture)]
-            CaptureBackend::InputCapturePortal => Self::InputCapturePortal,
+            CaptureBackend::InputCapturePortal => input_capture::Backend::InputCapturePortal,
             #[cfg(layer_shell_capture)]
-            CaptureBackend::LayerShell => Self::LayerShell,
+            CaptureBackend::LayerShell => input_capture::Backend::LayerShell,
             #[cfg(x11_capture)]
-            CaptureBackend::X11 => Self::X11,
+            CaptureBackend::X11 => input_capture::Backend::X11,
+            #[cfg(evdev_capture)]
+            CaptureBackend::Evdev => {
+                let ScreenSize { width, height } = screen_size.unwrap_or_else(|| {
+                    log::warn!(
+                        "evdev capture: no screen_size configured, assuming {DEFAULT_SCREEN_SIZE}"
+                    );
+                    DEFAULT_SCREEN_SIZE
+                });
+                input_capture::Backend::Evdev { width, height }
+            }
             #[cfg(windows)]
-            CaptureBackend::Windows => Self::Windows,
+            CaptureBackend::Windows => input_capture::Backend::Windows,
             #[cfg(target_os = "macos")]
-            CaptureBackend::MacOs => Self::MacOs,
-            CaptureBackend::Dummy => Self::Dummy,
+            CaptureBackend::MacOs => input_capture::Backend::MacOs,
+            CaptureBackend::Dummy => input_capture::Backend::Dummy(match script {
+                Some(path) => input_capture::DummySource::Script(path),
+                None => input_capture::DummySource::Circle,
+            }),
+        }
+    }
+}
+
+/// guards against crossing an edge by accident
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+struct TomlEdgeGuard {
+    /// milliseconds
+    dwell_time: Option<u64>,
+    /// pixels
+    push_distance: Option<f64>,
+    /// pixels
+    corner_size: Option<f64>,
+    modifier: Option<Modifier>,
+    block_drags: Option<bool>,
+}
+
+impl From<TomlEdgeGuard> for input_capture::EdgeGuard {
+    fn from(guard: TomlEdgeGuard) -> Self {
+        let pixels = |v: Option<f64>| v.filter(|v| *v > 0.).unwrap_or(0.);
+        Self {
+            dwell_time: Duration::from_millis(guard.dwell_time.unwrap_or(0)),
+            push_distance: pixels(guard.push_distance),
+            corner_size: pixels(guard.corner_size),
+            modifier: guard.modifier,
+            block_drags: guard.block_drags.unwrap_or(false),
         }
     }
 }
 
+/// hooks not belonging to a client
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+struct TomlHooks {
+    /// seconds
+    timeout: Option<u64>,
+    incoming_connect: Option<String>,
+    incoming_disconnect: Option<String>,
+}
+
+impl From<TomlHooks> for Hooks {
+    fn from(hooks: TomlHooks) -> Self {
+        Self {
+            timeout: hooks
+                .timeout
+                .map(Duration::from_secs)
+                .unwrap_or(hooks::DEFAULT_TIMEOUT),
+            incoming_connect: hooks.incoming_connect,
+            incoming_disconnect: hooks.incoming_disconnect,
+        }
+    }
+}
+
+/// size of the screen in pixels, given as `<width>x<height>` on the command line
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ScreenSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+#[cfg(evdev_capture)]
+const DEFAULT_SCREEN_SIZE: ScreenSize = ScreenSize {
+    width: 1920,
+    height: 1080,
+};
+
+impl Display for ScreenSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+impl FromStr for ScreenSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |n: &str| n.trim().parse::<u32>().map_err(|e| format!("{n}: {e}"));
+        let (width, height) = s
+            .split_once('x')
+            .ok_or(format!("expected <width>x<height>, got {s}"))?;
+        Ok(Self {
+            width: parse(width)?,
+            height: parse(height)?,
+        })
+    }
+}
+
 #[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
 pub enum EmulationBackend {
     #[cfg(wlroots_emulation)]
@@ -200,6 +452,9 @@ pub enum EmulationBackend {
     #[cfg(x11_emulation)]
     #[serde(rename = "x11")]
     X11,
+    #[cfg(uinput_emulation)]
+    #[serde(rename = "uinput")]
+    Uinput,
     #[cfg(windows)]
     #[serde(rename = "windows")]
     Windows,
@@ -210,22 +465,29 @@ pub enum EmulationBackend {
     Dummy,
 }
 
-impl From<EmulationBackend> for input_emulation::Backend {
-    fn from(backend: EmulationBackend) -> Self {
-        match backend {
+impl EmulationBackend {
+    /// the corresponding [`input_emulation::Backend`],
+    /// the dummy backend writes a trace to `trace` if given
+    fn into_backend(self, trace: Option<PathBuf>) -> input_emulation::Backend {
+        match self {
             #[cfg(wlroots_emulation)]
-            EmulationBackend::Wlroots => Self::Wlroots,
+            EmulationBackend::Wlroots => input_emulation::Backend::Wlroots,
             #[cfg(libei_emulation)]
-            EmulationBackend::Libei => Self::Libei,
+            EmulationBackend::Libei => input_emulation::Backend::Libei,
             #[cfg(rdp_emulation)]
-            EmulationBackend::Xdp => Self::Xdp,
+            EmulationBackend::Xdp => input_emulation::Backend::Xdp,
             #[cfg(x11_emulation)]
-            EmulationBackend::X11 => Self::X11,
+            EmulationBackend::X11 => input_emulation::Backend::X11,
+            #[cfg(uinput_emulation)]
+            EmulationBackend::Uinput => input_emulation::Backend::Uinput,
             #[cfg(windows)]
-            EmulationBackend::Windows => Self::Windows,
+            EmulationBackend::Windows => input_emulation::Backend::Windows,
             #[cfg(target_os = "macos")]
-            EmulationBackend::MacOs => Self::MacOs,
-            EmulationBackend::Dummy => Self::Dummy,
+            EmulationBackend::MacOs => input_emulation::Backend::MacOs,
+            EmulationBackend::Dummy => input_emulation::Backend::Dummy(match trace {
+                Some(path) => input_emulation::DummySink::Trace(path),
+                None => input_emulation::DummySink::Log,
+            }),
         }
     }
 }
@@ -241,6 +503,8 @@ impl Display for EmulationBackend {
             EmulationBackend::Xdp => write!(f, "xdg-desktop-portal"),
             #[cfg(x11_emulation)]
             EmulationBackend::X11 => write!(f, "X11"),
+            #[cfg(uinput_emulation)]
+            EmulationBackend::Uinput => write!(f, "uinput"),
             #[cfg(windows)]
             EmulationBackend::Windows => write!(f, "windows"),
             #[cfg(target_os = "macos")]
@@ -250,6 +514,35 @@ impl Display for EmulationBackend {
     }
 }
 
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize, ValueEnum)]
+pub enum ClipboardBackend {
+    #[cfg(data_control_clipboard)]
+    #[serde(rename = "wlr-data-control")]
+    DataControl,
+    #[serde(rename = "memory")]
+    Memory,
+}
+
+impl From<ClipboardBackend> for lan_mouse_clipboard::Backend {
+    fn from(backend: ClipboardBackend) -> Self {
+        match backend {
+            #[cfg(data_control_clipboard)]
+            ClipboardBackend::DataControl => Self::DataControl,
+            ClipboardBackend::Memory => Self::Memory,
+        }
+    }
+}
+
+impl Display for ClipboardBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            #[cfg(data_control_clipboard)]
+            ClipboardBackend::DataControl => write!(f, "wlr-data-control"),
+            ClipboardBackend::Memory => write!(f, "memory"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Config {
     /// command line arguments
@@ -273,25 +566,83 @@ pub struct ConfigClient {
     pub hostname: Option<String>,
     pub port: u16,
     pub pos: Position,
+    pub segment: Option<EdgeSegment>,
     pub active: bool,
     pub enter_hook: Option<String>,
+    pub leave_hook: Option<String>,
+    pub connect_hook: Option<String>,
+    pub disconnect_hook: Option<String>,
+    pub fingerprint: Option<String>,
+    /// keys (linux scancodes) replaced before sending them to the client
+    pub key_map: HashMap<u32, u32>,
+    /// mouse buttons replaced before sending them to the client
+    pub button_map: HashMap<u32, u32>,
+    /// pointer speed and scroll settings
+    pub pointer: PointerSettings,
+    /// keys (linux scancodes) capturing input for the client directly
+    pub hotkey: Vec<u32>,
 }
 
 impl From<TomlClient> for ConfigClient {
     fn from(toml: TomlClient) -> Self {
         let active = toml.activate_on_startup.unwrap_or(false);
         let enter_hook = toml.enter_hook;
+        let leave_hook = toml.leave_hook;
+        let connect_hook = toml.connect_hook;
+        let disconnect_hook = toml.disconnect_hook;
         let hostname = toml.hostname;
         let ips = HashSet::from_iter(toml.ips.into_iter().flatten());
         let port = toml.port.unwrap_or(DEFAULT_PORT);
         let pos = toml.position.unwrap_or_default();
+        let segment = toml.segment;
+        let fingerprint = toml.fingerprint;
+        let key_map = toml
+            .key_map
+            .into_iter()
+            .flatten()
+            .map(|(from, to)| (from as u32, to as u32))
+            .collect();
+        let button_map = toml
+            .button_map
+            .into_iter()
+            .flatten()
+            .map(|(from, to)| (from.into(), to.into()))
+            .collect();
+        let default = PointerSettings::default();
+        let pointer = PointerSettings {
+            motion_multiplier: toml.motion_multiplier.unwrap_or(default.motion_multiplier),
+            acceleration: toml.acceleration.unwrap_or(default.acceleration),
+            scroll_multiplier: toml.scroll_multiplier.unwrap_or(default.scroll_multiplier),
+            natural_scroll: toml.natural_scroll.unwrap_or(default.natural_scroll),
+        };
+        let pointer = if pointer.is_valid() {
+            pointer
+        } else {
+            log::warn!("invalid pointer settings: {pointer:?}, using defaults");
+            default
+        };
+        let hotkey = toml
+            .hotkey
+            .into_iter()
+            .flatten()
+            .map(|key| key as u32)
+            .collect();
         Self {
             ips,
             hostname,
             port,
             pos,
+            segment,
             active,
             enter_hook,
+            leave_hook,
+            connect_hook,
+            disconnect_hook,
+            fingerprint,
+            key_map,
+            button_map,
+            pointer,
+            hotkey,
         }
     }
 }
@@ -309,16 +660,66 @@ impl From<ConfigClient> for TomlClient {
             Some(client.port)
         };
         let position = Some(client.pos);
+        let segment = client.segment;
         let activate_on_startup = if client.active { Some(true) } else { None };
         let enter_hook = client.enter_hook;
+        let leave_hook = client.leave_hook;
+        let connect_hook = client.connect_hook;
+        let disconnect_hook = client.disconnect_hook;
+        let fingerprint = client.fingerprint;
+        let key_map = client
+            .key_map
+            .into_iter()
+            .filter_map(|(from, to)| {
+                Some((
+                    scancode::Linux::try_from(from).ok()?,
+                    scancode::Linux::try_from(to).ok()?,
+                ))
+            })
+            .collect::<HashMap<_, _>>();
+        let key_map = (!key_map.is_empty()).then_some(key_map);
+        let button_map = client
+            .button_map
+            .into_iter()
+            .filter_map(|(from, to)| Some((from.try_into().ok()?, to.try_into().ok()?)))
+            .collect::<HashMap<_, _>>();
+        let button_map = (!button_map.is_empty()).then_some(button_map);
+        // only write settings that differ from the default
+        let default = PointerSettings::default();
+        let pointer = client.pointer;
+        let motion_multiplier = (pointer.motion_multiplier != default.motion_multiplier)
+            .then_some(pointer.motion_multiplier);
+        let acceleration =
+            (pointer.acceleration != default.acceleration).then_some(pointer.acceleration);
+        let scroll_multiplier = (pointer.scroll_multiplier != default.scroll_multiplier)
+            .then_some(pointer.scroll_multiplier);
+        let natural_scroll = pointer.natural_scroll.then_some(true);
+        let hotkey = client
+            .hotkey
+            .into_iter()
+            .filter_map(|key| scancode::Linux::try_from(key).ok())
+            .collect::<Vec<_>>();
+        let hotkey = (!hotkey.is_empty()).then_some(hotkey);
         Self {
             hostname,
             host_name,
             ips,
             port,
             position,
+            segment,
             activate_on_startup,
             enter_hook,
+            leave_hook,
+            connect_hook,
+            disconnect_hook,
+            fingerprint,
+            key_map,
+            button_map,
+            motion_multiplier,
+            acceleration,
+            scroll_multiplier,
+            natural_scroll,
+            hotkey,
         }
     }
 }
@@ -340,7 +741,17 @@ const DEFAULT_RELEASE_KEYS: [scancode::Linux; 4] =
 
 impl Config {
     pub fn new() -> Result<Self, ConfigError> {
-        let args = Args::parse();
+        Self::from_args(env::args_os())
+    }
+
+    /// configuration from the given command line arguments
+    /// (including the program name)
+    pub fn from_args<I, T>(args: I) -> Result<Self, ConfigError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let args = Args::parse_from(args);
 
         // --config <file> overrules default location
         let config_path = args
@@ -443,8 +854,22 @@ impl Config {
     pub fn authorized_fingerprints(&self) -> HashMap<String, String> {
         self.config_toml
             .as_ref()
-            .and_then(|c| c.authorized_fingerprints.clone())
-            .unwrap_or_default()
+            .and_then(|c| c.authorized_fingerprints.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|(fp, key)| (fp.clone(), key.description.clone()))
+            .collect()
+    }
+
+    /// permissions of the authorized fingerprints
+    pub fn permissions(&self) -> HashMap<String, Permissions> {
+        self.config_toml
+            .as_ref()
+            .and_then(|c| c.authorized_fingerprints.as_ref())
+            .into_iter()
+            .flatten()
+            .map(|(fp, key)| (fp.clone(), key.permissions.clone()))
+            .collect()
     }
 
     /// path to certificate
@@ -453,17 +878,49 @@ impl Config {
     }
 
     /// optional input-capture backend override
-    pub fn capture_backend(&self) -> Option<CaptureBackend> {
+    pub fn capture_backend(&self) -> Option<input_capture::Backend> {
         self.args
             .capture_backend
             .or(self.config_toml.as_ref().and_then(|c| c.capture_backend))
+            .map(|b| b.into_backend(self.screen_size(), self.capture_script()))
+    }
+
+    /// script replayed by the dummy capture backend
+    pub fn capture_script(&self) -> Option<PathBuf> {
+        self.args.capture_script.clone().or(self
+            .config_toml
+            .as_ref()
+            .and_then(|c| c.capture_script.clone()))
+    }
+
+    /// screen size for input capture backends that can not query it
+    pub fn screen_size(&self) -> Option<ScreenSize> {
+        self.args
+            .screen_size
+            .or(self.config_toml.as_ref().and_then(|c| c.screen_size))
     }
 
     /// optional input-emulation backend override
-    pub fn emulation_backend(&self) -> Option<EmulationBackend> {
+    pub fn emulation_backend(&self) -> Option<input_emulation::Backend> {
         self.args
             .emulation_backend
             .or(self.config_toml.as_ref().and_then(|c| c.emulation_backend))
+            .map(|b| b.into_backend(self.emulation_trace()))
+    }
+
+    /// trace file written by the dummy emulation backend
+    pub fn emulation_trace(&self) -> Option<PathBuf> {
+        self.args.emulation_trace.clone().or(self
+            .config_toml
+            .as_ref()
+            .and_then(|c| c.emulation_trace.clone()))
+    }
+
+    /// optional clipboard backend override
+    pub fn clipboard_backend(&self) -> Option<ClipboardBackend> {
+        self.args
+            .clipboard_backend
+            .or(self.config_toml.as_ref().and_then(|c| c.clipboard_backend))
     }
 
     /// the port to use (initially)
@@ -471,9 +928,66 @@ impl Config {
         self.args
             .port
             .or(self.config_toml.as_ref().and_then(|c| c.port))
+            .or(self.configured_listen_addresses().map(|a| a[0].port()))
             .unwrap_or(DEFAULT_PORT)
     }
 
+    /// the listen addresses set explicitly on the command line or in the
+    /// config file, `None` if the defaults are used
+    pub fn configured_listen_addresses(&self) -> Option<Vec<SocketAddr>> {
+        self.args
+            .listen_addresses
+            .clone()
+            .or(self
+                .config_toml
+                .as_ref()
+                .and_then(|c| c.listen_addresses.clone()))
+            .filter(|a| !a.is_empty())
+    }
+
+    /// the addresses to listen on (initially).
+    /// Defaults to the unspecified ipv6 and ipv4 addresses
+    /// (dual-stack) on [`Config::port`]
+    pub fn listen_addresses(&self) -> Vec<SocketAddr> {
+        self.configured_listen_addresses().unwrap_or_else(|| {
+            let port = self.port();
+            vec![
+                SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), port),
+                SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), port),
+            ]
+        })
+    }
+
+    /// whether to share the clipboard with other devices
+    pub fn clipboard(&self) -> bool {
+        !self.args.no_clipboard
+            && self
+                .config_toml
+                .as_ref()
+                .and_then(|c| c.clipboard)
+                .unwrap_or(true)
+    }
+
+    /// whether to advertise this instance and browse for others via mDNS
+    pub fn discovery(&self) -> bool {
+        !self.args.no_discovery
+            && self
+                .config_toml
+                .as_ref()
+                .and_then(|c| c.discovery)
+                .unwrap_or(true)
+    }
+
+    /// whether to export the d-bus interface (if built with it)
+    pub fn dbus(&self) -> bool {
+        !self.args.no_dbus
+            && self
+                .config_toml
+                .as_ref()
+                .and_then(|c| c.dbus)
+                .unwrap_or(true)
+    }
+
     /// list of configured clients
     pub fn clients(&self) -> Vec<ConfigClient> {
         self.config_toml
@@ -494,6 +1008,23 @@ impl Config {
             .unwrap_or(Vec::from_iter(DEFAULT_RELEASE_KEYS.iter().cloned()))
     }
 
+    /// guards against crossing an edge by accident
+    pub fn edge_guard(&self) -> input_capture::EdgeGuard {
+        self.config_toml
+            .as_ref()
+            .and_then(|c| c.edge_guard)
+            .unwrap_or_default()
+            .into()
+    }
+
+    pub(crate) fn hooks(&self) -> Hooks {
+        self.config_toml
+            .as_ref()
+            .and_then(|c| c.hooks.clone())
+            .unwrap_or_default()
+            .into()
+    }
+
     /// set configured clients
     pub fn set_clients(&mut self, clients: Vec<ConfigClient>) {
         if clients.is_empty() {
@@ -506,8 +1037,27 @@ impl Config {
             Some(clients.into_iter().map(|c| c.into()).collect::<Vec<_>>());
     }
 
-    /// set authorized keys
+    /// set authorized keys, keeping the permissions and format of existing keys
     pub fn set_authorized_keys(&mut self, fingerprints: HashMap<String, String>) {
+        let mut existing = self
+            .config_toml
+            .as_mut()
+            .and_then(|c| c.authorized_fingerprints.take())
+            .unwrap_or_default();
+        let fingerprints = fingerprints
+            .into_iter()
+            .map(|(fp, description)| {
+                let key = match existing.remove(&fp) {
+                    Some(key) => AuthorizedKey { description, ..key },
+                    None => AuthorizedKey {
+                        description,
+                        permissions: Default::default(),
+                        plain: true,
+                    },
+                };
+                (fp, key)
+            })
+            .collect();
         if self.config_toml.is_none() {
             self.config_toml = Some(Default::default());
         }
@@ -578,3 +1128,67 @@ impl Config {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn authorized_fingerprints_keep_their_format() {
+        let config = r#"
+[authorized_fingerprints]
+"aa:aa" = "iridium"
+
+[authorized_fingerprints."bb:bb"]
+description = "cerium"
+access = "full"
+"#;
+        let mut config = toml::from_str::<ConfigToml>(config).expect("config");
+        let keys = config.authorized_fingerprints.as_mut().expect("keys");
+        assert_eq!(keys["aa:aa"].permissions, Permissions::default());
+        assert_eq!(keys["bb:bb"].permissions, Permissions::default());
+        keys.get_mut("aa:aa").expect("key").description = "argon".into();
+        let written = toml_edit::ser::to_string_pretty(&config).expect("config");
+        assert!(written.contains(r#""aa:aa" = "argon""#), "{written}");
+        assert!(written.contains(r#"description = "cerium""#), "{written}");
+
+        let mut keys = config.authorized_fingerprints.take().expect("keys");
+        keys.get_mut("aa:aa").expect("key").permissions.access = Access::Pointer;
+        config.authorized_fingerprints = Some(keys);
+        let written = toml_edit::ser::to_string_pretty(&config).expect("config");
+        assert!(written.contains(r#"access = "pointer""#), "{written}");
+        let read = toml::from_str::<ConfigToml>(&written).expect("config");
+        let keys = read.authorized_fingerprints.expect("keys");
+        assert_eq!(keys["aa:aa"].description, "argon");
+        assert_eq!(keys["aa:aa"].permissions.access, Access::Pointer);
+    }
+
+    #[test]
+    fn buttons_and_modifiers_by_name() {
+        let config = r#"
+[edge_guard]
+modifier = "super"
+
+[[clients]]
+position = "left"
+button_map = { back = "forward" }
+"#;
+        let config = toml::from_str::<ConfigToml>(config).expect("config");
+        let guard = config.edge_guard.expect("edge guard");
+        assert_eq!(guard.modifier, Some(Modifier::Super));
+        let clients = config.clients.as_ref().expect("clients");
+        let button_map = clients[0].button_map.as_ref().expect("button map");
+        assert_eq!(button_map[&Button::Back], Button::Forward);
+
+        let written = toml_edit::ser::to_string_pretty(&config).expect("config");
+        assert!(written.contains(r#"modifier = "super""#), "{written}");
+        assert!(written.contains(r#"back = "forward""#), "{written}");
+
+        let invalid = "[edge_guard]\nmodifier = \"hyper\"\n";
+        let err = toml::from_str::<ConfigToml>(invalid).expect_err("invalid modifier");
+        assert!(
+            err.to_string().contains("invalid modifier `hyper`"),
+            "{err}"
+        );
+    }
+}
@@ -1,13 +1,16 @@
 use crate::client::ClientManager;
 use crate::config::local_commit;
+use crate::crypto::{self, VerifyPeerCertificateFn};
+use crate::pairing::Pairing;
 use lan_mouse_ipc::{ClientHandle, DEFAULT_PORT};
-use lan_mouse_proto::{MAX_EVENT_SIZE, ProtoEvent};
+use lan_mouse_proto::{Capabilities, MAX_EVENT_SIZE, PROTOCOL_VERSION, ProtoEvent};
 use local_channel::mpsc::{Receiver, Sender, channel};
+use rustls::pki_types::CertificateDer;
 use std::{
     cell::RefCell,
     collections::{HashMap, HashSet},
     io,
-    net::SocketAddr,
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     rc::Rc,
     sync::Arc,
     time::Duration,
@@ -39,53 +42,131 @@ pub(crate) enum LanMouseConnectionError {
     TargetEmulationDisabled,
     #[error("Connection timed out")]
     Timeout,
+    #[error("certificate fingerprint mismatch: expected `{expected}`, got `{actual}`")]
+    FingerprintMismatch { expected: String, actual: String },
+}
+
+/// events concerning the identity of outgoing connections
+#[derive(Debug)]
+pub(crate) enum ConnectionEvent {
+    /// a connection to the client was established
+    Connected {
+        handle: ClientHandle,
+        addr: SocketAddr,
+    },
+    /// the connection to the client was closed
+    Disconnected {
+        handle: ClientHandle,
+        addr: SocketAddr,
+    },
+    /// the fingerprint of the client was pinned on first use
+    FingerprintPinned(ClientHandle),
+    /// the pairing code was exchanged with the client
+    PairingCode {
+        handle: ClientHandle,
+        fingerprint: String,
+        code: String,
+    },
+    /// the client presented a certificate not matching the pinned fingerprint
+    FingerprintMismatch {
+        handle: ClientHandle,
+        expected: String,
+        actual: String,
+    },
 }
 
 const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
 
+type Connected = (Arc<dyn Conn + Send + Sync>, SocketAddr, String);
+
 async fn connect(
     addr: SocketAddr,
     cert: Certificate,
-) -> Result<(Arc<dyn Conn + Sync + Send>, SocketAddr), (SocketAddr, LanMouseConnectionError)> {
+    expected: Option<String>,
+) -> Result<Connected, (SocketAddr, LanMouseConnectionError)> {
     log::info!("connecting to {addr} ...");
+    let bind_addr = match addr {
+        SocketAddr::V4(_) => SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0),
+        SocketAddr::V6(_) => SocketAddr::new(Ipv6Addr::UNSPECIFIED.into(), 0),
+    };
     let conn = Arc::new(
-        UdpSocket::bind("0.0.0.0:0")
+        UdpSocket::bind(bind_addr)
             .await
             .map_err(|e| (addr, e.into()))?,
     );
     conn.connect(addr).await.map_err(|e| (addr, e.into()))?;
+
+    // the peer certificate is self-signed, so the chain can not be verified
+    // (insecure_skip_verify) -> verify the fingerprint instead
+    let peer_fingerprint: Arc<std::sync::Mutex<Option<String>>> = Default::default();
+    let verify_peer_certificate: VerifyPeerCertificateFn = {
+        let peer_fingerprint = peer_fingerprint.clone();
+        let expected = expected.clone();
+        Arc::new(
+            move |certs: &[Vec<u8>], _chains: &[CertificateDer<'static>]| {
+                let fingerprint = certs
+                    .iter()
+                    .map(|c| crypto::generate_fingerprint(c))
+                    .next()
+                    .ok_or(webrtc_dtls::Error::ErrNoCertificates)?;
+                let matches = expected.as_ref().is_none_or(|e| *e == fingerprint);
+                peer_fingerprint.lock().expect("lock").replace(fingerprint);
+                if matches {
+                    Ok(())
+                } else {
+                    Err(webrtc_dtls::Error::ErrVerifyDataMismatch)
+                }
+            },
+        )
+    };
     let config = Config {
         certificates: vec![cert],
         server_name: "ignored".to_owned(),
         insecure_skip_verify: true,
+        verify_peer_certificate: Some(verify_peer_certificate),
         extended_master_secret: ExtendedMasterSecretType::Require,
         ..Default::default()
     };
     let timeout = tokio::time::sleep(DEFAULT_CONNECTION_TIMEOUT);
-    tokio::select! {
-        _ = timeout => Err((addr, LanMouseConnectionError::Timeout)),
-        result = DTLSConn::new(conn, config, true, None) => match result {
-            Ok(dtls_conn) => Ok((Arc::new(dtls_conn), addr)),
-            Err(e) => Err((addr, e.into())),
-        }
+    let result = tokio::select! {
+        _ = timeout => return Err((addr, LanMouseConnectionError::Timeout)),
+        result = DTLSConn::new(conn, config, true, None) => result,
+    };
+    let actual = peer_fingerprint.lock().expect("lock").take();
+    match (result, actual) {
+        (Ok(dtls_conn), Some(actual)) => Ok((Arc::new(dtls_conn), addr, actual)),
+        (Ok(_), None) => Err((addr, webrtc_dtls::Error::ErrNoCertificates.into())),
+        (Err(e), actual) => match (expected, actual) {
+            (Some(expected), Some(actual)) if expected != actual => Err((
+                addr,
+                LanMouseConnectionError::FingerprintMismatch { expected, actual },
+            )),
+            _ => Err((addr, e.into())),
+        },
     }
 }
 
 async fn connect_any(
     addrs: &[SocketAddr],
     cert: Certificate,
-) -> Result<(Arc<dyn Conn + Send + Sync>, SocketAddr), LanMouseConnectionError> {
+    expected: Option<String>,
+) -> Result<Connected, LanMouseConnectionError> {
     let mut joinset = JoinSet::new();
     for &addr in addrs {
-        joinset.spawn_local(connect(addr, cert.clone()));
+        joinset.spawn_local(connect(addr, cert.clone(), expected.clone()));
     }
+    let mut mismatch = None;
     loop {
         match joinset.join_next().await {
-            None => return Err(LanMouseConnectionError::NotConnected),
+            // a fingerprint mismatch takes precedence over other errors
+            None => return Err(mismatch.unwrap_or(LanMouseConnectionError::NotConnected)),
             Some(r) => match r.expect("join error") {
                 Ok(conn) => return Ok(conn),
                 Err((a, e)) => {
-                    log::warn!("failed to connect to {a}: `{e}`")
+                    log::warn!("failed to connect to {a}: `{e}`");
+                    if let LanMouseConnectionError::FingerprintMismatch { .. } = e {
+                        mismatch = Some(e);
+                    }
                 }
             },
         };
@@ -100,14 +181,26 @@ pub(crate) struct LanMouseConnection {
     recv_rx: Receiver<(ClientHandle, ProtoEvent)>,
     recv_tx: Sender<(ClientHandle, ProtoEvent)>,
     ping_response: Rc<RefCell<HashSet<SocketAddr>>>,
+    event_tx: Sender<ConnectionEvent>,
+    capabilities: Capabilities,
+    pairing: Pairing,
 }
 
 impl LanMouseConnection {
-    pub(crate) fn new(cert: Certificate, client_manager: ClientManager) -> Self {
+    pub(crate) fn new(
+        cert: Certificate,
+        client_manager: ClientManager,
+        capabilities: Capabilities,
+        pairing: Pairing,
+        event_tx: Sender<ConnectionEvent>,
+    ) -> Self {
         let (recv_tx, recv_rx) = channel();
         Self {
             cert,
+            capabilities,
+            pairing,
             client_manager,
+            event_tx,
             conns: Default::default(),
             connecting: Default::default(),
             recv_rx,
@@ -125,7 +218,16 @@ impl LanMouseConnection {
         event: ProtoEvent,
         handle: ClientHandle,
     ) -> Result<(), LanMouseConnectionError> {
-        let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = event.into();
+        // avoid sending events the peer does not support
+        let capabilities = self
+            .client_manager
+            .capabilities(handle)
+            .unwrap_or(self.capabilities.intersection(Capabilities::LEGACY));
+        let Some(event) = event.clone().restrict_to(capabilities) else {
+            log::trace!("client {handle} does not support {event}");
+            return Ok(());
+        };
+        let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = event.clone().into();
         let buf = &buf[..len];
         if let Some(addr) = self.client_manager.active_addr(handle) {
             let conn = {
@@ -140,7 +242,14 @@ impl LanMouseConnection {
                     Ok(_) => {}
                     Err(e) => {
                         log::warn!("client {handle} failed to send: {e}");
-                        disconnect(&self.client_manager, handle, addr, &self.conns).await;
+                        disconnect(
+                            &self.client_manager,
+                            handle,
+                            addr,
+                            &self.conns,
+                            &self.event_tx,
+                        )
+                        .await;
                     }
                 }
                 log::trace!("{event} >->->->->- {addr}");
@@ -161,12 +270,16 @@ impl LanMouseConnection {
                 self.connecting.clone(),
                 self.recv_tx.clone(),
                 self.ping_response.clone(),
+                self.event_tx.clone(),
+                self.capabilities,
+                self.pairing.clone(),
             ));
         }
         Err(LanMouseConnectionError::NotConnected)
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn connect_to_handle(
     client_manager: ClientManager,
     cert: Certificate,
@@ -175,6 +288,9 @@ async fn connect_to_handle(
     connecting: Rc<Mutex<HashSet<ClientHandle>>>,
     tx: Sender<(ClientHandle, ProtoEvent)>,
     ping_response: Rc<RefCell<HashSet<SocketAddr>>>,
+    event_tx: Sender<ConnectionEvent>,
+    capabilities: Capabilities,
+    pairing: Pairing,
 ) -> Result<(), LanMouseConnectionError> {
     log::info!("client {handle} connecting ...");
     // sending did not work, figure out active conn.
@@ -185,15 +301,36 @@ async fn connect_to_handle(
             .map(|a| SocketAddr::new(a, port))
             .collect::<Vec<_>>();
         log::info!("client ({handle}) connecting ... (ips: {addrs:?})");
-        let res = connect_any(&addrs, cert).await;
-        let (conn, addr) = match res {
+        let expected = client_manager.get_fingerprint(handle);
+        let res = connect_any(&addrs, cert, expected.clone()).await;
+        let (conn, addr, fingerprint) = match res {
             Ok(c) => c,
             Err(e) => {
                 connecting.lock().await.remove(&handle);
+                if let LanMouseConnectionError::FingerprintMismatch { expected, actual } = &e {
+                    log::error!("client ({handle}): {e}");
+                    let event = ConnectionEvent::FingerprintMismatch {
+                        handle,
+                        expected: expected.clone(),
+                        actual: actual.clone(),
+                    };
+                    event_tx.send(event).expect("channel closed");
+                }
                 return Err(e);
             }
         };
         log::info!("client ({handle}) connected @ {addr}");
+        if expected.is_none() {
+            // trust on first use
+            log::info!("client ({handle}): pinning fingerprint {fingerprint}");
+            client_manager.set_fingerprint(handle, Some(fingerprint.clone()));
+            event_tx
+                .send(ConnectionEvent::FingerprintPinned(handle))
+                .expect("channel closed");
+        }
+        event_tx
+            .send(ConnectionEvent::Connected { handle, addr })
+            .expect("channel closed");
         client_manager.set_active_addr(handle, Some(addr));
         conns.lock().await.insert(addr, conn.clone());
         connecting.lock().await.remove(&handle);
@@ -201,10 +338,13 @@ async fn connect_to_handle(
         // Best-effort version handshake. Send our commit hash once
         // immediately after the DTLS handshake; the listen side
         // mirrors a Hello back so the receive loop can populate
-        // `peer_commit`. Old peers will silently skip this event
-        // per the forward-compat handler in [`receive_loop`].
+        // `peer_commit` and the negotiated capabilities. Old peers
+        // will silently skip this event per the forward-compat
+        // handler in [`receive_loop`].
         let (buf, len) = ProtoEvent::Hello {
             commit: local_commit(),
+            version: PROTOCOL_VERSION,
+            capabilities,
         }
         .into();
         if let Err(e) = conn.send(&buf[..len]).await {
@@ -212,7 +352,13 @@ async fn connect_to_handle(
         }
 
         // poll connection for active
-        spawn_local(ping_pong(addr, conn.clone(), ping_response.clone()));
+        spawn_local(ping_pong(
+            addr,
+            conn.clone(),
+            ping_response.clone(),
+            fingerprint.clone(),
+            pairing.clone(),
+        ));
 
         // receiver
         spawn_local(receive_loop(
@@ -223,6 +369,10 @@ async fn connect_to_handle(
             conns,
             tx,
             ping_response.clone(),
+            event_tx,
+            capabilities,
+            fingerprint,
+            pairing,
         ));
         return Ok(());
     }
@@ -234,12 +384,19 @@ async fn ping_pong(
     addr: SocketAddr,
     conn: Arc<dyn Conn + Send + Sync>,
     ping_response: Rc<RefCell<HashSet<SocketAddr>>>,
+    fingerprint: String,
+    pairing: Pairing,
 ) {
     loop {
         let (buf, len) = ProtoEvent::Ping.into();
 
         // send 4 pings, at least one must be answered
         for _ in 0..4 {
+            // the peer answers with its nonce, see [`Pairing::exchange`]
+            if let Some(commit) = pairing.commit(&fingerprint) {
+                let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = commit.into();
+                let _ = conn.send(&buf[..len]).await;
+            }
             if let Err(e) = conn.send(&buf[..len]).await {
                 log::warn!("{addr}: send error `{e}`, closing connection");
                 let _ = conn.close().await;
@@ -258,6 +415,7 @@ async fn ping_pong(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn receive_loop(
     client_manager: ClientManager,
     handle: ClientHandle,
@@ -266,10 +424,14 @@ async fn receive_loop(
     conns: Rc<Mutex<HashMap<SocketAddr, Arc<dyn Conn + Send + Sync>>>>,
     tx: Sender<(ClientHandle, ProtoEvent)>,
     ping_response: Rc<RefCell<HashSet<SocketAddr>>>,
+    event_tx: Sender<ConnectionEvent>,
+    local_capabilities: Capabilities,
+    fingerprint: String,
+    pairing: Pairing,
 ) {
     let mut buf = [0u8; MAX_EVENT_SIZE];
-    while conn.recv(&mut buf).await.is_ok() {
-        match buf.try_into() {
+    while let Ok(len) = conn.recv(&mut buf).await {
+        match ProtoEvent::try_from(&buf[..len]) {
             Ok(event) => {
                 log::trace!("{addr} <==<==<== {event}");
                 match event {
@@ -278,8 +440,33 @@ async fn receive_loop(
                         client_manager.set_alive(handle, b);
                         ping_response.borrow_mut().insert(addr);
                     }
-                    ProtoEvent::Hello { commit } => {
+                    ProtoEvent::Hello {
+                        commit,
+                        version,
+                        capabilities,
+                    } => {
+                        let capabilities = local_capabilities.negotiate(version, capabilities);
+                        log::info!(
+                            "client ({handle}): protocol version {version}, capabilities {capabilities:?}"
+                        );
                         client_manager.set_peer_commit(handle, Some(commit));
+                        client_manager.set_capabilities(handle, Some((version, capabilities)));
+                    }
+                    ProtoEvent::PairingCommit { .. } | ProtoEvent::PairingNonce { .. } => {
+                        let exchanged = pairing.exchange(&fingerprint, &event);
+                        for reply in exchanged.replies {
+                            let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = reply.into();
+                            let _ = conn.send(&buf[..len]).await;
+                        }
+                        if let Some(code) = exchanged.code {
+                            let fingerprint = fingerprint.clone();
+                            let event = ConnectionEvent::PairingCode {
+                                handle,
+                                fingerprint,
+                                code,
+                            };
+                            event_tx.send(event).expect("channel closed");
+                        }
                     }
                     event => tx.send((handle, event)).expect("channel closed"),
                 }
@@ -292,7 +479,7 @@ async fn receive_loop(
         }
     }
     log::warn!("recv error");
-    disconnect(&client_manager, handle, addr, &conns).await;
+    disconnect(&client_manager, handle, addr, &conns, &event_tx).await;
 }
 
 async fn disconnect(
@@ -300,11 +487,18 @@ async fn disconnect(
     handle: ClientHandle,
     addr: SocketAddr,
     conns: &Mutex<HashMap<SocketAddr, Arc<dyn Conn + Send + Sync>>>,
+    event_tx: &Sender<ConnectionEvent>,
 ) {
     log::warn!("client ({handle}) @ {addr} connection closed");
-    conns.lock().await.remove(&addr);
+    // a failed send and the receive loop may both close the connection
+    if conns.lock().await.remove(&addr).is_some() {
+        event_tx
+            .send(ConnectionEvent::Disconnected { handle, addr })
+            .expect("channel closed");
+    }
     client_manager.set_active_addr(handle, None);
     client_manager.set_peer_commit(handle, None);
+    client_manager.set_capabilities(handle, None);
     let active: Vec<SocketAddr> = conns.lock().await.keys().copied().collect();
     log::info!("active connections: {active:?}");
 }
@@ -6,7 +6,9 @@ use std::{fs::File, io::BufReader};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
 
+use rustls::pki_types::CertificateDer;
 use sha2::{Digest, Sha256};
+use std::sync::Arc;
 use thiserror::Error;
 use webrtc_dtls::crypto::Certificate;
 
@@ -18,6 +20,12 @@ pub enum Error {
     Dtls(#[from] webrtc_dtls::Error),
 }
 
+pub(crate) type VerifyPeerCertificateFn = Arc<
+    dyn (Fn(&[Vec<u8>], &[CertificateDer<'static>]) -> Result<(), webrtc_dtls::Error>)
+        + Send
+        + Sync,
+>;
+
 pub fn generate_fingerprint(cert: &[u8]) -> String {
     let mut hash = Sha256::new();
     hash.update(cert);
@@ -29,6 +37,39 @@ pub fn generate_fingerprint(cert: &[u8]) -> String {
     bytes.join(":").to_lowercase()
 }
 
+/// random nonce contributing to a pairing code
+pub(crate) fn pairing_nonce() -> [u8; 32] {
+    rand::random()
+}
+
+/// commitment to a pairing nonce, revealed later
+pub(crate) fn pairing_commitment(nonce: &[u8; 32]) -> [u8; 32] {
+    Sha256::digest(nonce).into()
+}
+
+/// Short authentication string derived from the fingerprints and the
+/// nonces of both peers. The result does not depend on the order of the
+/// peers, so both sides of a pairing display the same code.
+///
+/// As each side commits to its nonce before learning the other one,
+/// a man in the middle can not search for a certificate producing
+/// a matching code, but only has a chance of one in a million.
+pub(crate) fn pairing_code(local: (&str, &[u8; 32]), remote: (&str, &[u8; 32])) -> String {
+    let (first, second) = if local.0 <= remote.0 {
+        (local, remote)
+    } else {
+        (remote, local)
+    };
+    let mut hash = Sha256::new();
+    for (fingerprint, nonce) in [first, second] {
+        hash.update(fingerprint.as_bytes());
+        hash.update(nonce);
+    }
+    let digest = hash.finalize();
+    let code = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 1_000_000;
+    format!("{:03} {:03}", code / 1000, code % 1000)
+}
+
 pub fn certificate_fingerprint(cert: &Certificate) -> String {
     let certificate = cert.certificate.first().expect("certificate missing");
     generate_fingerprint(certificate)
@@ -0,0 +1,46 @@
+//! D-Bus interface of the service for desktop integration
+//! (shell extensions, panel widgets, status bar modules).
+//!
+//! The service is exported as `de.feschber.LanMouse` at `/de/feschber/LanMouse`
+//! on the session bus (or the bus given by `DBUS_SESSION_BUS_ADDRESS`).
+//! Methods mirror [`FrontendRequest`]s, signals and properties mirror [`FrontendEvent`]s.
+//! Optional arguments are passed as empty strings.
+
+#[cfg(dbus)]
+mod interface;
+
+use lan_mouse_ipc::{FrontendEvent, FrontendRequest, RequestError, Response};
+use local_channel::mpsc::Sender;
+use tokio::{
+    sync::{mpsc::UnboundedReceiver, oneshot},
+    task::JoinHandle,
+};
+
+/// replies to the method call that issued a request
+pub(crate) type Responder = oneshot::Sender<Result<Response, RequestError>>;
+
+#[cfg_attr(not(dbus), allow(dead_code))]
+pub(crate) struct DBus {
+    /// requests received through method calls
+    request_rx: UnboundedReceiver<(FrontendRequest, Responder)>,
+    /// events emitted as signals
+    event_tx: Sender<FrontendEvent>,
+    task: JoinHandle<()>,
+}
+
+#[cfg_attr(not(dbus), allow(dead_code))]
+impl DBus {
+    /// wait for the next request received through a method call
+    pub(crate) async fn request(&mut self) -> (FrontendRequest, Responder) {
+        self.request_rx.recv().await.expect("channel closed")
+    }
+
+    /// emit the signals and property changes corresponding to the event
+    pub(crate) fn notify(&self, event: FrontendEvent) {
+        self.event_tx.send(event).expect("channel closed");
+    }
+
+    pub(crate) fn terminate(&mut self) {
+        self.task.abort();
+    }
+}
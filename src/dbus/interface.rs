@@ -0,0 +1,725 @@
+use std::{collections::HashMap, net::IpAddr};
+
+use lan_mouse_ipc::{
+    ClientConfig, ClientHandle, ClientState, DiscoveredPeer, EdgeSegment, FrontendEvent,
+    FrontendRequest, HookEvent, RequestError, Response,
+};
+use local_channel::mpsc::{Receiver, channel};
+use tokio::{
+    sync::{
+        mpsc::{self, UnboundedSender},
+        oneshot,
+    },
+    task::spawn_local,
+};
+use zbus::{
+    connection, fdo, interface,
+    object_server::{InterfaceRef, SignalEmitter},
+    zvariant::{OwnedValue, Str, Value},
+};
+
+use super::{DBus, Responder};
+
+const NAME: &str = "de.feschber.LanMouse";
+const PATH: &str = "/de/feschber/LanMouse";
+
+impl DBus {
+    /// connect to the session bus and export the interface
+    pub(crate) async fn new() -> Result<Self, zbus::Error> {
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = channel();
+        // populate the properties
+        let (responder, _) = oneshot::channel();
+        request_tx
+            .send((FrontendRequest::Sync, responder))
+            .expect("channel closed");
+        let iface = LanMouse {
+            request_tx,
+            capture_status: false,
+            emulation_status: false,
+            pairing_status: false,
+            port: 0,
+            public_key_fingerprint: String::new(),
+            authorized_keys: HashMap::new(),
+            clients: HashMap::new(),
+        };
+        let conn = connection::Builder::session()?
+            .name(NAME)?
+            .serve_at(PATH, iface)?
+            .build()
+            .await?;
+        let iface = conn.object_server().interface(PATH).await?;
+        let task = spawn_local(async move {
+            // keep the connection alive
+            let _conn = conn;
+            emit_events(iface, event_rx).await;
+        });
+        Ok(Self {
+            request_rx,
+            event_tx,
+            task,
+        })
+    }
+}
+
+struct LanMouse {
+    request_tx: UnboundedSender<(FrontendRequest, Responder)>,
+    capture_status: bool,
+    emulation_status: bool,
+    pairing_status: bool,
+    port: u16,
+    public_key_fingerprint: String,
+    authorized_keys: HashMap<String, String>,
+    clients: HashMap<ClientHandle, HashMap<String, OwnedValue>>,
+}
+
+impl LanMouse {
+    /// forward the request to the service and wait until it is carried out
+    async fn request(&self, request: FrontendRequest) -> fdo::Result<()> {
+        self.call(request).await.map(|_| ())
+    }
+
+    /// forward a request creating a client and return the handle of the client
+    async fn create_client(&self, request: FrontendRequest) -> fdo::Result<ClientHandle> {
+        match self.call(request).await? {
+            Response::Created(handle) => Ok(handle),
+            response => Err(fdo::Error::Failed(format!(
+                "unexpected response: {response:?}"
+            ))),
+        }
+    }
+
+    /// forward the request to the service and wait for its response
+    async fn call(&self, request: FrontendRequest) -> fdo::Result<Response> {
+        let terminated = || fdo::Error::Failed("service terminated".into());
+        let (responder, response) = oneshot::channel();
+        self.request_tx
+            .send((request, responder))
+            .map_err(|_| terminated())?;
+        match response.await.map_err(|_| terminated())? {
+            Ok(response) => Ok(response),
+            Err(
+                e @ (RequestError::NoSuchClient(_)
+                | RequestError::ClientNotActive(_)
+                | RequestError::NoSuchPeer(_)
+                | RequestError::NoPairingRequest(_)
+                | RequestError::InvalidArgument(_)),
+            ) => Err(fdo::Error::InvalidArgs(e.to_string())),
+            Err(e) => Err(fdo::Error::Failed(e.to_string())),
+        }
+    }
+}
+
+#[interface(name = "de.feschber.LanMouse")]
+impl LanMouse {
+    async fn activate(&self, handle: ClientHandle, active: bool) -> fdo::Result<()> {
+        self.request(FrontendRequest::Activate(handle, active))
+            .await
+    }
+
+    /// returns the handle of the new client
+    async fn create(&self) -> fdo::Result<ClientHandle> {
+        self.create_client(FrontendRequest::Create).await
+    }
+
+    async fn change_port(&self, port: u16) -> fdo::Result<()> {
+        self.request(FrontendRequest::ChangePort(port)).await
+    }
+
+    async fn delete(&self, handle: ClientHandle) -> fdo::Result<()> {
+        self.request(FrontendRequest::Delete(handle)).await
+    }
+
+    async fn enumerate(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::Enumerate()).await
+    }
+
+    async fn resolve_dns(&self, handle: ClientHandle) -> fdo::Result<()> {
+        self.request(FrontendRequest::ResolveDns(handle)).await
+    }
+
+    async fn update_hostname(&self, handle: ClientHandle, hostname: String) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateHostname(handle, optional(hostname)))
+            .await
+    }
+
+    async fn update_port(&self, handle: ClientHandle, port: u16) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdatePort(handle, port))
+            .await
+    }
+
+    async fn update_position(&self, handle: ClientHandle, pos: String) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdatePosition(handle, parse(&pos)?))
+            .await
+    }
+
+    /// a length of 0 claims the whole edge
+    async fn update_segment(
+        &self,
+        handle: ClientHandle,
+        offset: u32,
+        length: u32,
+    ) -> fdo::Result<()> {
+        let segment = (length > 0).then_some(EdgeSegment { offset, length });
+        self.request(FrontendRequest::UpdateSegment(handle, segment))
+            .await
+    }
+
+    async fn update_fix_ips(&self, handle: ClientHandle, ips: Vec<String>) -> fdo::Result<()> {
+        let ips = ips
+            .iter()
+            .map(|ip| parse::<IpAddr>(ip))
+            .collect::<Result<_, _>>()?;
+        self.request(FrontendRequest::UpdateFixIps(handle, ips))
+            .await
+    }
+
+    async fn update_motion_multiplier(
+        &self,
+        handle: ClientHandle,
+        multiplier: f64,
+    ) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateMotionMultiplier(
+            handle,
+            multiplier.into(),
+        ))
+        .await
+    }
+
+    async fn update_acceleration(
+        &self,
+        handle: ClientHandle,
+        acceleration: f64,
+    ) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateAcceleration(
+            handle,
+            acceleration.into(),
+        ))
+        .await
+    }
+
+    async fn update_scroll_multiplier(
+        &self,
+        handle: ClientHandle,
+        multiplier: f64,
+    ) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateScrollMultiplier(
+            handle,
+            multiplier.into(),
+        ))
+        .await
+    }
+
+    async fn update_natural_scroll(&self, handle: ClientHandle, enabled: bool) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateNaturalScroll(handle, enabled))
+            .await
+    }
+
+    async fn capture(&self, handle: ClientHandle) -> fdo::Result<()> {
+        self.request(FrontendRequest::Capture(handle)).await
+    }
+
+    async fn enable_capture(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::EnableCapture).await
+    }
+
+    async fn enable_emulation(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::EnableEmulation).await
+    }
+
+    async fn sync(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::Sync).await
+    }
+
+    async fn authorize_key(&self, description: String, fingerprint: String) -> fdo::Result<()> {
+        self.request(FrontendRequest::AuthorizeKey(description, fingerprint))
+            .await
+    }
+
+    async fn remove_authorized_key(&self, fingerprint: String) -> fdo::Result<()> {
+        self.request(FrontendRequest::RemoveAuthorizedKey(fingerprint))
+            .await
+    }
+
+    async fn update_hook(
+        &self,
+        handle: ClientHandle,
+        event: String,
+        command: String,
+    ) -> fdo::Result<()> {
+        let event = parse::<HookEvent>(&event)?;
+        self.request(FrontendRequest::UpdateHook(
+            handle,
+            event,
+            optional(command),
+        ))
+        .await
+    }
+
+    async fn update_fingerprint(
+        &self,
+        handle: ClientHandle,
+        fingerprint: String,
+    ) -> fdo::Result<()> {
+        self.request(FrontendRequest::UpdateFingerprint(
+            handle,
+            optional(fingerprint),
+        ))
+        .await
+    }
+
+    async fn enumerate_peers(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::EnumeratePeers).await
+    }
+
+    /// returns the handle of the new client
+    async fn add_discovered_peer(&self, name: String, pos: String) -> fdo::Result<ClientHandle> {
+        self.create_client(FrontendRequest::AddDiscoveredPeer(name, parse(&pos)?))
+            .await
+    }
+
+    async fn save_configuration(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::SaveConfiguration).await
+    }
+
+    async fn start_pairing(&self) -> fdo::Result<()> {
+        self.request(FrontendRequest::StartPairing).await
+    }
+
+    async fn confirm_pairing(&self, fingerprint: String) -> fdo::Result<()> {
+        self.request(FrontendRequest::ConfirmPairing(fingerprint))
+            .await
+    }
+
+    #[zbus(property)]
+    async fn capture_status(&self) -> bool {
+        self.capture_status
+    }
+
+    #[zbus(property)]
+    async fn emulation_status(&self) -> bool {
+        self.emulation_status
+    }
+
+    #[zbus(property)]
+    async fn pairing_status(&self) -> bool {
+        self.pairing_status
+    }
+
+    #[zbus(property)]
+    async fn port(&self) -> u16 {
+        self.port
+    }
+
+    #[zbus(property)]
+    async fn public_key_fingerprint(&self) -> String {
+        self.public_key_fingerprint.clone()
+    }
+
+    /// fingerprint -> description
+    #[zbus(property)]
+    async fn authorized_keys(&self) -> HashMap<String, String> {
+        self.authorized_keys.clone()
+    }
+
+    /// handle -> configuration and state of the client
+    #[zbus(property)]
+    async fn clients(&self) -> HashMap<ClientHandle, HashMap<String, OwnedValue>> {
+        self.clients.clone()
+    }
+
+    #[zbus(signal)]
+    async fn client_created(emitter: &SignalEmitter<'_>, handle: ClientHandle) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn client_changed(emitter: &SignalEmitter<'_>, handle: ClientHandle) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn client_deleted(emitter: &SignalEmitter<'_>, handle: ClientHandle) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn no_such_client(emitter: &SignalEmitter<'_>, handle: ClientHandle) -> zbus::Result<()>;
+
+    /// the port could not be changed (the `Port` property keeps the current one)
+    #[zbus(signal)]
+    async fn port_change_failed(emitter: &SignalEmitter<'_>, error: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn error(emitter: &SignalEmitter<'_>, message: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn device_connected(
+        emitter: &SignalEmitter<'_>,
+        addr: &str,
+        fingerprint: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn device_entered(
+        emitter: &SignalEmitter<'_>,
+        fingerprint: &str,
+        addr: &str,
+        pos: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn incoming_disconnected(emitter: &SignalEmitter<'_>, addr: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn connection_attempt(emitter: &SignalEmitter<'_>, fingerprint: &str)
+    -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn fingerprint_mismatch(
+        emitter: &SignalEmitter<'_>,
+        handle: ClientHandle,
+        expected: &str,
+        actual: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn peer_discovered(
+        emitter: &SignalEmitter<'_>,
+        name: &str,
+        hostname: &str,
+        ips: Vec<String>,
+        port: u16,
+        fingerprint: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn peer_removed(emitter: &SignalEmitter<'_>, name: &str) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn pairing_code(
+        emitter: &SignalEmitter<'_>,
+        fingerprint: &str,
+        code: &str,
+    ) -> zbus::Result<()>;
+
+    /// `handle` is empty for hooks not belonging to a client
+    #[zbus(signal)]
+    async fn hook_finished(
+        emitter: &SignalEmitter<'_>,
+        event: &str,
+        handle: Vec<ClientHandle>,
+        command: &str,
+        success: bool,
+        status: &str,
+    ) -> zbus::Result<()>;
+}
+
+async fn emit_events(iface: InterfaceRef<LanMouse>, mut event_rx: Receiver<FrontendEvent>) {
+    while let Some(event) = event_rx.recv().await {
+        if let Err(e) = emit(&iface, event).await {
+            log::warn!("failed to emit d-bus signal: {e}");
+        }
+    }
+}
+
+async fn emit(iface: &InterfaceRef<LanMouse>, event: FrontendEvent) -> zbus::Result<()> {
+    let emitter = iface.signal_emitter();
+    let mut lan_mouse = iface.get_mut().await;
+    match event {
+        FrontendEvent::Created(handle, config, state) => {
+            lan_mouse.clients.insert(handle, client_info(config, state));
+            lan_mouse.clients_changed(emitter).await?;
+            LanMouse::client_created(emitter, handle).await?;
+        }
+        FrontendEvent::NoSuchClient(handle) => LanMouse::no_such_client(emitter, handle).await?,
+        FrontendEvent::State(handle, config, state) => {
+            lan_mouse.clients.insert(handle, client_info(config, state));
+            lan_mouse.clients_changed(emitter).await?;
+            LanMouse::client_changed(emitter, handle).await?;
+        }
+        FrontendEvent::Deleted(handle) => {
+            lan_mouse.clients.remove(&handle);
+            lan_mouse.clients_changed(emitter).await?;
+            LanMouse::client_deleted(emitter, handle).await?;
+        }
+        FrontendEvent::PortChanged(port, error) => {
+            lan_mouse.port = port;
+            lan_mouse.port_changed(emitter).await?;
+            if let Some(error) = error {
+                LanMouse::port_change_failed(emitter, &error).await?;
+            }
+        }
+        FrontendEvent::Enumerate(clients) => {
+            lan_mouse.clients = clients
+                .into_iter()
+                .map(|(handle, config, state)| (handle, client_info(config, state)))
+                .collect();
+            lan_mouse.clients_changed(emitter).await?;
+        }
+        FrontendEvent::Error(message) => LanMouse::error(emitter, &message).await?,
+        FrontendEvent::CaptureStatus(status) => {
+            lan_mouse.capture_status = status.into();
+            lan_mouse.capture_status_changed(emitter).await?;
+        }
+        FrontendEvent::EmulationStatus(status) => {
+            lan_mouse.emulation_status = status.into();
+            lan_mouse.emulation_status_changed(emitter).await?;
+        }
+        FrontendEvent::AuthorizedUpdated(keys) => {
+            lan_mouse.authorized_keys = keys;
+            lan_mouse.authorized_keys_changed(emitter).await?;
+        }
+        FrontendEvent::PublicKeyFingerprint(fingerprint) => {
+            lan_mouse.public_key_fingerprint = fingerprint;
+            lan_mouse.public_key_fingerprint_changed(emitter).await?;
+        }
+        FrontendEvent::DeviceConnected { addr, fingerprint } => {
+            LanMouse::device_connected(emitter, &addr.to_string(), &fingerprint).await?
+        }
+        FrontendEvent::DeviceEntered {
+            fingerprint,
+            addr,
+            pos,
+        } => {
+            let (addr, pos) = (addr.to_string(), pos.to_string());
+            LanMouse::device_entered(emitter, &fingerprint, &addr, &pos).await?
+        }
+        FrontendEvent::IncomingDisconnected(addr) => {
+            LanMouse::incoming_disconnected(emitter, &addr.to_string()).await?
+        }
+        FrontendEvent::ConnectionAttempt { fingerprint } => {
+            LanMouse::connection_attempt(emitter, &fingerprint).await?
+        }
+        FrontendEvent::FingerprintMismatch {
+            handle,
+            expected,
+            actual,
+        } => LanMouse::fingerprint_mismatch(emitter, handle, &expected, &actual).await?,
+        FrontendEvent::PeerDiscovered(peer) => emit_peer(emitter, peer).await?,
+        FrontendEvent::PeerRemoved(name) => LanMouse::peer_removed(emitter, &name).await?,
+        FrontendEvent::EnumeratePeers(peers) => {
+            for peer in peers {
+                emit_peer(emitter, peer).await?;
+            }
+        }
+        FrontendEvent::PairingStatus(status) => {
+            lan_mouse.pairing_status = status.into();
+            lan_mouse.pairing_status_changed(emitter).await?;
+        }
+        FrontendEvent::PairingCode { fingerprint, code } => {
+            LanMouse::pairing_code(emitter, &fingerprint, &code).await?
+        }
+        FrontendEvent::HookFinished {
+            event,
+            handle,
+            command,
+            status,
+        } => {
+            let (event, handle) = (event.to_string(), handle.into_iter().collect());
+            let (success, status) = (status.success(), status.to_string());
+            LanMouse::hook_finished(emitter, &event, handle, &command, success, &status).await?
+        }
+    }
+    Ok(())
+}
+
+async fn emit_peer(emitter: &SignalEmitter<'_>, peer: DiscoveredPeer) -> zbus::Result<()> {
+    let ips = peer.ips.iter().map(|ip| ip.to_string()).collect();
+    let fingerprint = peer.fingerprint.unwrap_or_default();
+    LanMouse::peer_discovered(
+        emitter,
+        &peer.name,
+        &peer.hostname,
+        ips,
+        peer.port,
+        &fingerprint,
+    )
+    .await
+}
+
+/// configuration and state of a client as a dictionary
+fn client_info(config: ClientConfig, state: ClientState) -> HashMap<String, OwnedValue> {
+    let string = |s: String| OwnedValue::from(Str::from(s));
+    let strings = |v: Vec<String>| Value::from(v).try_to_owned().expect("no file descriptors");
+    let ips = |ips: Vec<IpAddr>| strings(ips.iter().map(|ip| ip.to_string()).collect());
+    let mut fix_ips = config.fix_ips;
+    fix_ips.sort();
+    let mut all_ips = state.ips.into_iter().collect::<Vec<_>>();
+    all_ips.sort();
+    let active_addr = state.active_addr.map(|a| a.to_string());
+    let segment = config
+        .segment
+        .map(|s| (s.offset, s.length))
+        .unwrap_or((0, 0));
+    let pointer = state.pointer;
+    HashMap::from([
+        (
+            "hostname".into(),
+            string(config.hostname.unwrap_or_default()),
+        ),
+        ("port".into(), config.port.into()),
+        ("position".into(), string(config.pos.to_string())),
+        ("segment-offset".into(), segment.0.into()),
+        ("segment-length".into(), segment.1.into()),
+        ("fix-ips".into(), ips(fix_ips)),
+        ("ips".into(), ips(all_ips)),
+        (
+            "fingerprint".into(),
+            string(config.fingerprint.unwrap_or_default()),
+        ),
+        ("enter-hook".into(), string(config.cmd.unwrap_or_default())),
+        (
+            "leave-hook".into(),
+            string(config.leave_hook.unwrap_or_default()),
+        ),
+        (
+            "connect-hook".into(),
+            string(config.connect_hook.unwrap_or_default()),
+        ),
+        (
+            "disconnect-hook".into(),
+            string(config.disconnect_hook.unwrap_or_default()),
+        ),
+        ("motion-multiplier".into(), pointer.motion_multiplier.into()),
+        ("acceleration".into(), pointer.acceleration.into()),
+        ("scroll-multiplier".into(), pointer.scroll_multiplier.into()),
+        ("natural-scroll".into(), pointer.natural_scroll.into()),
+        ("active".into(), state.active.into()),
+        (
+            "active-addr".into(),
+            string(active_addr.unwrap_or_default()),
+        ),
+        ("alive".into(), state.alive.into()),
+        ("resolving".into(), state.resolving.into()),
+        (
+            "protocol-version".into(),
+            state.protocol_version.unwrap_or(0).into(),
+        ),
+        (
+            "capabilities".into(),
+            strings(state.capabilities.iter().map(|c| c.to_string()).collect()),
+        ),
+    ])
+}
+
+fn optional(s: String) -> Option<String> {
+    (!s.is_empty()).then_some(s)
+}
+
+fn parse<T>(s: &str) -> fdo::Result<T>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    s.parse()
+        .map_err(|e: T::Err| fdo::Error::InvalidArgs(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader},
+        process::{Child, Command, Stdio},
+    };
+
+    use futures::StreamExt;
+    use tokio::task::LocalSet;
+    use zbus::proxy::CacheProperties;
+
+    use super::*;
+
+    /// private session bus, terminated when dropped
+    struct Bus(Child);
+
+    impl Bus {
+        fn spawn() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--print-address", "--nofork"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let stdout = daemon.stdout.take().expect("stdout");
+            let mut address = String::new();
+            BufReader::new(stdout).read_line(&mut address).ok()?;
+            unsafe { std::env::set_var("DBUS_SESSION_BUS_ADDRESS", address.trim()) };
+            Some(Self(daemon))
+        }
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.0.kill();
+            let _ = self.0.wait();
+        }
+    }
+
+    #[tokio::test]
+    async fn session_bus() {
+        let Some(_bus) = Bus::spawn() else {
+            eprintln!("dbus-daemon not available, skipping");
+            return;
+        };
+        LocalSet::new()
+            .run_until(async {
+                let mut dbus = DBus::new().await.expect("export interface");
+                let (request, _) = dbus.request().await;
+                assert_eq!(request, FrontendRequest::Sync);
+
+                let conn = zbus::Connection::session().await.expect("connect");
+                let proxy = zbus::proxy::Builder::<zbus::Proxy>::new(&conn)
+                    .destination(NAME)
+                    .expect("destination")
+                    .path(PATH)
+                    .expect("path")
+                    .interface(NAME)
+                    .expect("interface")
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await
+                    .expect("proxy");
+
+                // methods are forwarded to the service and return its response
+                let call = proxy.call::<_, _, ()>("Activate", &(3u64, true));
+                let service = async {
+                    let (request, responder) = dbus.request().await;
+                    assert_eq!(request, FrontendRequest::Activate(3, true));
+                    responder.send(Ok(Response::Done)).expect("respond");
+                };
+                let (result, ()) = tokio::join!(call, service);
+                result.expect("activate");
+                let call = proxy.call::<_, _, ()>("Delete", &(4u64,));
+                let service = async {
+                    let (_, responder) = dbus.request().await;
+                    responder
+                        .send(Err(RequestError::NoSuchClient(4)))
+                        .expect("respond");
+                };
+                let (result, ()) = tokio::join!(call, service);
+                let Err(zbus::Error::MethodError(name, _, _)) = result else {
+                    panic!("expected an error, got {result:?}");
+                };
+                assert_eq!(name.as_str(), "org.freedesktop.DBus.Error.InvalidArgs");
+
+                // clients are created with their handle returned
+                let call = proxy.call::<_, _, ClientHandle>("Create", &());
+                let service = async {
+                    let (request, responder) = dbus.request().await;
+                    assert_eq!(request, FrontendRequest::Create);
+                    responder.send(Ok(Response::Created(6))).expect("respond");
+                };
+                let (result, ()) = tokio::join!(call, service);
+                assert_eq!(result.expect("create"), 6);
+
+                // events update properties and emit signals
+                let mut deleted = proxy
+                    .receive_signal("ClientDeleted")
+                    .await
+                    .expect("subscribe");
+                dbus.notify(FrontendEvent::PortChanged(4242, None));
+                dbus.notify(FrontendEvent::Deleted(5));
+                let signal = deleted.next().await.expect("signal");
+                let handle: ClientHandle = signal.body().deserialize().expect("handle");
+                assert_eq!(handle, 5);
+                let port: u16 = proxy.get_property("Port").await.expect("property");
+                assert_eq!(port, 4242);
+                dbus.terminate();
+            })
+            .await;
+    }
+}
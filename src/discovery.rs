@@ -0,0 +1,236 @@
+use std::net::IpAddr;
+
+use lan_mouse_ipc::DiscoveredPeer;
+use mdns_sd::{IfKind, Receiver, ServiceDaemon, ServiceEvent, ServiceInfo};
+use thiserror::Error;
+
+/// DNS-SD service type lan-mouse instances advertise themselves as
+const SERVICE_TYPE: &str = "_lan-mouse._udp.local.";
+
+/// TXT record key of the certificate fingerprint
+const FINGERPRINT_KEY: &str = "fingerprint";
+
+#[derive(Debug, Error)]
+pub(crate) enum DiscoveryError {
+    #[error(transparent)]
+    Mdns(#[from] mdns_sd::Error),
+    #[error("could not determine hostname")]
+    Hostname,
+}
+
+pub(crate) enum DiscoveryEvent {
+    /// a peer was discovered or its service record changed
+    Discovered(DiscoveredPeer),
+    /// a peer (identified by its instance name) is no longer available
+    Removed(String),
+}
+
+/// Advertises this instance via mDNS / DNS-SD
+/// and browses for other instances on the local network.
+pub(crate) struct Discovery {
+    daemon: ServiceDaemon,
+    browse_rx: Receiver<ServiceEvent>,
+    /// instance name used for advertising
+    name: String,
+    /// full name of the registered service
+    fullname: Option<String>,
+    /// certificate fingerprint advertised in the TXT record
+    fingerprint: String,
+    /// addresses to advertise, all addresses of all interfaces if `None`
+    addrs: Option<Vec<IpAddr>>,
+}
+
+impl Discovery {
+    /// advertise this instance on `port` with the addresses in `addrs`
+    /// (e.g. the configured listen addresses).
+    /// Unspecified addresses or `None` advertise all addresses of all interfaces.
+    pub(crate) fn new(
+        port: u16,
+        fingerprint: String,
+        addrs: Option<Vec<IpAddr>>,
+    ) -> Result<Self, DiscoveryError> {
+        let name = hostname::get()
+            .ok()
+            .and_then(|h| h.into_string().ok())
+            .ok_or(DiscoveryError::Hostname)?;
+        Self::with_name(name, port, fingerprint, addrs)
+    }
+
+    fn with_name(
+        name: String,
+        port: u16,
+        fingerprint: String,
+        addrs: Option<Vec<IpAddr>>,
+    ) -> Result<Self, DiscoveryError> {
+        let addrs = addrs.filter(|a| !a.iter().any(|ip| ip.is_unspecified()));
+        let daemon = ServiceDaemon::new()?;
+        // only announce and browse on the interfaces we are listening on
+        if let Some(addrs) = addrs.as_ref() {
+            daemon.disable_interface(IfKind::All)?;
+            daemon.enable_interface(addrs.clone())?;
+        }
+        let browse_rx = daemon.browse(SERVICE_TYPE)?;
+        let mut discovery = Self {
+            daemon,
+            browse_rx,
+            name,
+            fullname: None,
+            fingerprint,
+            addrs,
+        };
+        discovery.advertise(port)?;
+        Ok(discovery)
+    }
+
+    /// (re-)register the service with the given port
+    pub(crate) fn advertise(&mut self, port: u16) -> Result<(), DiscoveryError> {
+        if let Some(fullname) = self.fullname.take() {
+            self.daemon.unregister(&fullname)?;
+        }
+        let host_name = format!("{}.local.", self.name);
+        let properties = [(FINGERPRINT_KEY, self.fingerprint.as_str())];
+        let info = match self.addrs.as_ref() {
+            Some(addrs) => ServiceInfo::new(
+                SERVICE_TYPE,
+                &self.name,
+                &host_name,
+                &addrs[..],
+                port,
+                &properties[..],
+            )?,
+            None => ServiceInfo::new(
+                SERVICE_TYPE,
+                &self.name,
+                &host_name,
+                "",
+                port,
+                &properties[..],
+            )?
+            .enable_addr_auto(),
+        };
+        self.fullname = Some(info.get_fullname().to_owned());
+        log::info!("advertising {} on port {port}", info.get_fullname());
+        self.daemon.register(info)?;
+        Ok(())
+    }
+
+    pub(crate) async fn event(&mut self) -> DiscoveryEvent {
+        loop {
+            let event = self.browse_rx.recv_async().await.expect("channel closed");
+            match event {
+                ServiceEvent::ServiceResolved(service) => {
+                    if Some(service.get_fullname()) == self.fullname.as_deref()
+                        || service.get_property_val_str(FINGERPRINT_KEY)
+                            == Some(self.fingerprint.as_str())
+                    {
+                        continue;
+                    }
+                    let name = instance_name(service.get_fullname());
+                    let hostname = service.get_hostname().trim_end_matches('.').to_owned();
+                    let mut ips = service
+                        .get_addresses()
+                        .iter()
+                        .map(|ip| ip.to_ip_addr())
+                        .filter(is_routable)
+                        .collect::<Vec<_>>();
+                    ips.sort();
+                    let port = service.get_port();
+                    let fingerprint = service
+                        .get_property_val_str(FINGERPRINT_KEY)
+                        .map(|f| f.to_owned());
+                    return DiscoveryEvent::Discovered(DiscoveredPeer {
+                        name,
+                        hostname,
+                        ips,
+                        port,
+                        fingerprint,
+                    });
+                }
+                ServiceEvent::ServiceRemoved(_, fullname) => {
+                    return DiscoveryEvent::Removed(instance_name(&fullname));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub(crate) fn terminate(&mut self) {
+        if let Some(fullname) = self.fullname.take() {
+            let _ = self.daemon.unregister(&fullname);
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// loopback addresses of a remote peer are useless and so are
+/// link-local ipv6 addresses without a scope id
+fn is_routable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => !ip.is_loopback(),
+        IpAddr::V6(ip) => !ip.is_loopback() && !ip.is_unicast_link_local(),
+    }
+}
+
+fn instance_name(fullname: &str) -> String {
+    fullname
+        .strip_suffix(SERVICE_TYPE)
+        .map(|n| n.trim_end_matches('.'))
+        .unwrap_or(fullname)
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn discover_over_loopback() {
+        let pid = std::process::id();
+        let loopback = Some(vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]);
+        let mut a = Discovery::with_name(
+            format!("lan-mouse-test-a-{pid}"),
+            4243,
+            "aa:aa".into(),
+            loopback.clone(),
+        )
+        .expect("discovery a");
+        let mut b = Discovery::with_name(
+            format!("lan-mouse-test-b-{pid}"),
+            4244,
+            "bb:bb".into(),
+            loopback,
+        )
+        .expect("discovery b");
+
+        let discovered = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let DiscoveryEvent::Discovered(peer) = b.event().await {
+                    if peer.name == a.name {
+                        break peer;
+                    }
+                }
+            }
+        })
+        .await
+        .expect("a was not discovered");
+        assert_eq!(discovered.port, 4243);
+        assert_eq!(discovered.fingerprint.as_deref(), Some("aa:aa"));
+        // loopback addresses are not reported
+        assert!(discovered.ips.is_empty());
+
+        a.terminate();
+        let removed = tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                if let DiscoveryEvent::Removed(name) = b.event().await {
+                    break name;
+                }
+            }
+        })
+        .await
+        .expect("removal of a was not noticed");
+        assert_eq!(removed, a.name);
+        b.terminate();
+    }
+}
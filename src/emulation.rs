@@ -1,13 +1,15 @@
-use crate::config::local_commit;
+use crate::clipboard::{self, ClipboardReceiver, ClipboardSender};
+use crate::config::{Access, Permissions, local_commit};
 use crate::listen::{LanMouseListener, ListenEvent, ListenerCreationError};
 use futures::StreamExt;
 use input_emulation::{EmulationHandle, InputEmulation, InputEmulationError};
-use input_event::Event;
-use lan_mouse_proto::{Position, ProtoEvent};
+use input_event::{Event, KeyboardEvent, Modifier};
+use lan_mouse_clipboard::Clipboard;
+use lan_mouse_proto::{Capabilities, PROTOCOL_VERSION, Position, ProtoEvent};
 use local_channel::mpsc::{Receiver, Sender, channel};
 use std::{
     cell::Cell,
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     net::SocketAddr,
     rc::Rc,
     time::{Duration, Instant},
@@ -32,6 +34,13 @@ pub(crate) enum EmulationEvent {
     ConnectionAttempt {
         fingerprint: String,
     },
+    /// the pairing code was exchanged with a device
+    /// with an unknown certificate, connected in pairing mode
+    PairingRequest {
+        addr: SocketAddr,
+        fingerprint: String,
+        code: String,
+    },
     /// new connection
     Entered {
         /// address of the connection
@@ -63,6 +72,8 @@ pub(crate) enum EmulationEvent {
     PeerHello {
         addr: SocketAddr,
         commit: [u8; 8],
+        version: u16,
+        capabilities: Capabilities,
     },
 }
 
@@ -70,19 +81,38 @@ enum EmulationRequest {
     Reenable,
     Release(SocketAddr),
     ChangePort(u16),
+    SetPermissions(HashMap<String, Permissions>),
     Terminate,
 }
 
+/// permissions of authorized devices without an explicit configuration
+static FULL_ACCESS: Permissions = Permissions {
+    access: Access::Full,
+    blocked_keys: Vec::new(),
+};
+
 impl Emulation {
     pub(crate) fn new(
         backend: Option<input_emulation::Backend>,
         listener: LanMouseListener,
+        clipboard: Option<Rc<dyn Clipboard>>,
+        capabilities: Capabilities,
+        permissions: HashMap<String, Permissions>,
     ) -> Self {
         let emulation_proxy = EmulationProxy::new(backend);
         let (request_tx, request_rx) = channel();
         let (event_tx, event_rx) = channel();
         let emulation_task = ListenTask {
             listener,
+            clipboard,
+            clipboard_receivers: Default::default(),
+            clipboard_sender: Default::default(),
+            capabilities,
+            peer_capabilities: Default::default(),
+            permissions,
+            fingerprints: Default::default(),
+            pressed_keys: Default::default(),
+            entered: Default::default(),
             emulation_proxy,
             request_rx,
             event_tx,
@@ -107,6 +137,13 @@ impl Emulation {
             .expect("channel closed");
     }
 
+    /// update the permissions of authorized devices (by fingerprint)
+    pub(crate) fn set_permissions(&self, permissions: HashMap<String, Permissions>) {
+        self.request_tx
+            .send(EmulationRequest::SetPermissions(permissions))
+            .expect("channel closed")
+    }
+
     pub(crate) fn request_port_change(&self, port: u16) {
         self.request_tx
             .send(EmulationRequest::ChangePort(port))
@@ -131,6 +168,19 @@ impl Emulation {
 
 struct ListenTask {
     listener: LanMouseListener,
+    clipboard: Option<Rc<dyn Clipboard>>,
+    clipboard_receivers: HashMap<SocketAddr, ClipboardReceiver>,
+    clipboard_sender: ClipboardSender<SocketAddr>,
+    capabilities: Capabilities,
+    peer_capabilities: HashMap<SocketAddr, Capabilities>,
+    /// permissions of authorized devices by fingerprint
+    permissions: HashMap<String, Permissions>,
+    /// certificate fingerprints of the connected devices
+    fingerprints: HashMap<SocketAddr, String>,
+    /// keys currently held down by the connected devices
+    pressed_keys: HashMap<SocketAddr, HashSet<u32>>,
+    /// devices that entered this device and have not left it yet
+    entered: HashSet<SocketAddr>,
     emulation_proxy: EmulationProxy,
     request_rx: Receiver<EmulationRequest>,
     event_tx: Sender<EmulationEvent>,
@@ -148,19 +198,40 @@ impl ListenTask {
                         log::trace!("{event} <-<-<-<-<- {addr}");
                         last_response.insert(addr, Instant::now());
                         match event {
-                            ProtoEvent::Enter(pos) => {
+                            ProtoEvent::Enter(pos, offset) => {
                                 if let Some(fingerprint) = self.listener.get_certificate_fingerprint(addr).await {
+                                    self.listener.reply(addr, ProtoEvent::Ack(0)).await;
+                                    // a device with presence access does not take over this device
+                                    if self.is_presence(addr) {
+                                        continue;
+                                    }
                                     log::info!("releasing capture: {addr} entered this device");
                                     self.event_tx.send(EmulationEvent::ReleaseNotify).expect("channel closed");
-                                    self.listener.reply(addr, ProtoEvent::Ack(0)).await;
+                                    // continue at the point where the cursor left the other device.
+                                    // `Enter` is repeated until acknowledged, only warp once.
+                                    if let (Some(offset), true) = (offset, self.entered.insert(addr)) {
+                                        self.emulation_proxy.warp_to_edge(to_emulation_pos(pos), offset, addr);
+                                    }
                                     self.event_tx.send(EmulationEvent::Entered{addr, pos: to_ipc_pos(pos), fingerprint}).expect("channel closed");
                                 }
                             }
                             ProtoEvent::Leave(_) => {
+                                self.entered.remove(&addr);
                                 self.emulation_proxy.remove(addr);
+                                self.pressed_keys.remove(&addr);
                                 self.listener.reply(addr, ProtoEvent::Ack(0)).await;
+                                if !self.is_presence(addr) {
+                                    self.send_clipboard(addr);
+                                }
+                            }
+                            ProtoEvent::ClipboardOffer { .. } | ProtoEvent::ClipboardData { .. } if !self.is_presence(addr) => {
+                                self.receive_clipboard(addr, event).await;
                             }
-                            ProtoEvent::Input(event) => self.emulation_proxy.consume(event, addr),
+                            ProtoEvent::ClipboardAck { id } => self.clipboard_sender.ack(addr, id),
+                            ProtoEvent::Input(event) => match self.permit(addr, event) {
+                                Some(event) => self.emulation_proxy.consume(event, addr),
+                                None => log::trace!("{addr} is not permitted to emulate {event}"),
+                            },
                             ProtoEvent::Ping => self.listener.reply(addr, ProtoEvent::Pong(self.emulation_proxy.emulation_active.get())).await,
                             // Peer's version handshake. Echo our own
                             // commit back so the peer's connect-side
@@ -173,16 +244,28 @@ impl ListenTask {
                             // listener down) the version display would
                             // otherwise silently say "unknown" while
                             // the peer is in fact happily talking to us.
-                            ProtoEvent::Hello { commit } => {
-                                self.listener.reply(addr, ProtoEvent::Hello { commit: local_commit() }).await;
-                                self.event_tx.send(EmulationEvent::PeerHello { addr, commit }).expect("channel closed");
+                            ProtoEvent::Hello { commit, version, capabilities } => {
+                                let hello = ProtoEvent::Hello {
+                                    commit: local_commit(),
+                                    version: PROTOCOL_VERSION,
+                                    capabilities: self.local_capabilities(),
+                                };
+                                self.listener.reply(addr, hello).await;
+                                let capabilities = self.local_capabilities().negotiate(version, capabilities);
+                                self.peer_capabilities.insert(addr, capabilities);
+                                self.event_tx.send(EmulationEvent::PeerHello { addr, commit, version, capabilities }).expect("channel closed");
                             }
                             _ => {}
                         }
                     }
                     Some(ListenEvent::Accept { addr, fingerprint }) => {
+                        self.fingerprints.insert(addr, fingerprint.clone());
                         self.event_tx.send(EmulationEvent::Connected { addr, fingerprint }).expect("channel closed");
                     }
+                    Some(ListenEvent::PairingRequest { addr, fingerprint, code }) => {
+                        self.fingerprints.insert(addr, fingerprint.clone());
+                        self.event_tx.send(EmulationEvent::PairingRequest { addr, fingerprint, code }).expect("channel closed");
+                    }
                     Some(ListenEvent::Rejected { fingerprint }) => {
                         if rejected_connections.insert(fingerprint.clone(), Instant::now())
                             .is_none_or(|i| i.elapsed() >= Duration::from_secs(2)) {
@@ -191,6 +274,11 @@ impl ListenTask {
                     }
                     None => break
                 }}
+                (addr, events) = self.clipboard_sender.next() => {
+                    for event in events {
+                        self.listener.reply(addr, event).await;
+                    }
+                }
                 event = self.emulation_proxy.event() => {
                     self.event_tx.send(event).expect("channel closed");
                 }
@@ -198,12 +286,16 @@ impl ListenTask {
                     // reenable emulation
                     EmulationRequest::Reenable => self.emulation_proxy.reenable(),
                     // notify the other end that we hit a barrier (should release capture)
-                    EmulationRequest::Release(addr) => self.listener.reply(addr, ProtoEvent::Leave(0)).await,
+                    EmulationRequest::Release(addr) => {
+                        self.entered.remove(&addr);
+                        self.listener.reply(addr, ProtoEvent::Leave(0)).await;
+                    }
                     EmulationRequest::ChangePort(port) => {
                         self.listener.request_port_change(port);
                         let result = self.listener.port_changed().await;
                         self.event_tx.send(EmulationEvent::PortChanged(result)).expect("channel closed");
                     }
+                    EmulationRequest::SetPermissions(permissions) => self.permissions = permissions,
                     EmulationRequest::Terminate => break,
                 },
                 _ = interval.tick() => {
@@ -211,6 +303,11 @@ impl ListenTask {
                         if instant.elapsed() > Duration::from_secs(1) {
                             log::warn!("releasing keys: {addr} not responding!");
                             self.emulation_proxy.remove(addr);
+                            self.pressed_keys.remove(&addr);
+                            self.entered.remove(&addr);
+                            self.clipboard_receivers.remove(&addr);
+                            self.clipboard_sender.forget(addr);
+                            self.peer_capabilities.remove(&addr);
                             self.event_tx.send(EmulationEvent::Disconnected { addr }).expect("channel closed");
                             false
                         } else {
@@ -223,12 +320,76 @@ impl ListenTask {
         self.listener.terminate().await;
         self.emulation_proxy.terminate().await;
     }
+
+    fn peer_permissions(&self, addr: SocketAddr) -> Option<&Permissions> {
+        let fingerprint = self.fingerprints.get(&addr)?;
+        Some(self.permissions.get(fingerprint).unwrap_or(&FULL_ACCESS))
+    }
+
+    /// the device is accepted, but does not have any effect on this device
+    fn is_presence(&self, addr: SocketAddr) -> bool {
+        self.peer_permissions(addr)
+            .is_none_or(|p| p.access == Access::Presence)
+    }
+
+    /// the part of `event` the device at `addr` may emulate, see [`permit`]
+    fn permit(&mut self, addr: SocketAddr, event: Event) -> Option<Event> {
+        let fingerprint = self.fingerprints.get(&addr)?;
+        let permissions = self.permissions.get(fingerprint).unwrap_or(&FULL_ACCESS);
+        let pressed_keys = self.pressed_keys.entry(addr).or_default();
+        permit(permissions, pressed_keys, event)
+    }
+
+    /// capabilities announced to connecting devices:
+    /// the entry point carried by `Enter` is only of use, if the emulation can warp the pointer
+    fn local_capabilities(&self) -> Capabilities {
+        let mut capabilities = self.capabilities;
+        if !self.emulation_proxy.absolute_motion.get() {
+            capabilities.remove(Capabilities::ABSOLUTE_MOTION);
+        }
+        capabilities
+    }
+
+    /// push the local clipboard to a client leaving this device
+    fn send_clipboard(&mut self, addr: SocketAddr) {
+        let Some(clipboard) = &self.clipboard else {
+            return;
+        };
+        let capabilities = self
+            .peer_capabilities
+            .get(&addr)
+            .copied()
+            .unwrap_or(self.capabilities.intersection(Capabilities::LEGACY));
+        if !capabilities.contains(Capabilities::CLIPBOARD) {
+            return;
+        }
+        self.clipboard_sender.send(addr, clipboard);
+    }
+
+    /// reassemble the clipboard sent by a client entering this device
+    async fn receive_clipboard(&mut self, addr: SocketAddr, event: ProtoEvent) {
+        let Some(clipboard) = &self.clipboard else {
+            return;
+        };
+        let receiver = self.clipboard_receivers.entry(addr).or_default();
+        let Some(received) = receiver.receive(event) else {
+            return;
+        };
+        if let Some(text) = received.text {
+            self.clipboard_sender.received(addr, &text);
+            clipboard::set_clipboard(clipboard, text);
+        }
+        let ack = ProtoEvent::ClipboardAck { id: received.id };
+        self.listener.reply(addr, ack).await;
+    }
 }
 
 /// proxy handling the actual input emulation,
 /// discarding events when it is disabled
 pub(crate) struct EmulationProxy {
     emulation_active: Rc<Cell<bool>>,
+    /// whether the emulation backend supports absolute motion
+    absolute_motion: Rc<Cell<bool>>,
     exit_requested: Rc<Cell<bool>>,
     request_tx: Sender<ProxyRequest>,
     event_rx: Receiver<EmulationEvent>,
@@ -237,6 +398,7 @@ pub(crate) struct EmulationProxy {
 
 enum ProxyRequest {
     Input(Event, SocketAddr),
+    WarpToEdge(input_emulation::Position, f64, SocketAddr),
     Remove(SocketAddr),
     Terminate,
     Reenable,
@@ -247,8 +409,10 @@ impl EmulationProxy {
         let (request_tx, request_rx) = channel();
         let (event_tx, event_rx) = channel();
         let emulation_active = Rc::new(Cell::new(false));
+        let absolute_motion = Rc::new(Cell::new(false));
         let exit_requested = Rc::new(Cell::new(false));
         let emulation_task = EmulationTask {
+            absolute_motion: absolute_motion.clone(),
             backend,
             exit_requested: exit_requested.clone(),
             request_rx,
@@ -259,6 +423,7 @@ impl EmulationProxy {
         let task = spawn_local(emulation_task.run());
         Self {
             emulation_active,
+            absolute_motion,
             exit_requested,
             request_tx,
             task,
@@ -286,6 +451,14 @@ impl EmulationProxy {
         }
     }
 
+    fn warp_to_edge(&self, pos: input_emulation::Position, offset: f64, addr: SocketAddr) {
+        if self.emulation_active.get() {
+            self.request_tx
+                .send(ProxyRequest::WarpToEdge(pos, offset, addr))
+                .expect("channel closed");
+        }
+    }
+
     fn remove(&self, addr: SocketAddr) {
         self.request_tx
             .send(ProxyRequest::Remove(addr))
@@ -308,6 +481,7 @@ impl EmulationProxy {
 }
 
 struct EmulationTask {
+    absolute_motion: Rc<Cell<bool>>,
     backend: Option<input_emulation::Backend>,
     exit_requested: Rc<Cell<bool>>,
     request_rx: Receiver<ProxyRequest>,
@@ -331,6 +505,7 @@ impl EmulationTask {
                     ProxyRequest::Reenable => break,
                     ProxyRequest::Terminate => return,
                     ProxyRequest::Input(..) => { /* emulation inactive => ignore */ }
+                    ProxyRequest::WarpToEdge(..) => { /* emulation inactive => ignore */ }
                     ProxyRequest::Remove(..) => { /* emulation inactive => ignore */ }
                 }
             }
@@ -340,10 +515,11 @@ impl EmulationTask {
     async fn do_emulation(&mut self) -> Result<(), InputEmulationError> {
         log::info!("creating input emulation ...");
         let mut emulation = tokio::select! {
-            r = InputEmulation::new(self.backend) => r?,
+            r = InputEmulation::new(self.backend.clone()) => r?,
             // allow termination event while requesting input emulation
             _ = wait_for_termination(&mut self.request_rx) => return Ok(()),
         };
+        self.absolute_motion.set(emulation.absolute_motion());
 
         // used to send enabled and disabled events
         let _emulation_guard = DropGuard::new(
@@ -377,6 +553,23 @@ impl EmulationTask {
         Ok(())
     }
 
+    async fn get_or_create_handle(
+        &mut self,
+        emulation: &mut InputEmulation,
+        addr: SocketAddr,
+    ) -> EmulationHandle {
+        match self.handles.get(&addr) {
+            Some(&handle) => handle,
+            None => {
+                let handle = self.next_id;
+                self.next_id += 1;
+                emulation.create(handle).await;
+                self.handles.insert(addr, handle);
+                handle
+            }
+        }
+    }
+
     async fn do_emulation_session(
         &mut self,
         emulation: &mut InputEmulation,
@@ -385,18 +578,13 @@ impl EmulationTask {
             tokio::select! {
                 e = self.request_rx.recv() => match e.expect("channel closed") {
                     ProxyRequest::Input(event, addr) => {
-                        let handle = match self.handles.get(&addr) {
-                            Some(&handle) => handle,
-                            None => {
-                                let handle = self.next_id;
-                                self.next_id += 1;
-                                emulation.create(handle).await;
-                                self.handles.insert(addr, handle);
-                                handle
-                            }
-                        };
+                        let handle = self.get_or_create_handle(emulation, addr).await;
                         emulation.consume(event, handle).await?;
                     },
+                    ProxyRequest::WarpToEdge(pos, offset, addr) => {
+                        let handle = self.get_or_create_handle(emulation, addr).await;
+                        emulation.warp_to_edge(pos, offset, handle).await?;
+                    }
                     ProxyRequest::Remove(addr) => {
                         if let Some(handle) = self.handles.remove(&addr) {
                             emulation.destroy(handle).await;
@@ -410,6 +598,68 @@ impl EmulationTask {
     }
 }
 
+/// The part of `event` a device with the given permissions may emulate,
+/// `pressed_keys` are the keys currently held by the device.
+/// The last key completing a blocked combination is dropped and
+/// modifiers completing it are removed from the modifier mask.
+fn permit(
+    permissions: &Permissions,
+    pressed_keys: &mut HashSet<u32>,
+    event: Event,
+) -> Option<Event> {
+    match (permissions.access, event) {
+        (Access::Presence, _) => None,
+        (Access::Pointer, Event::Keyboard(_)) => None,
+        (_, Event::Keyboard(KeyboardEvent::Key { key, state, .. })) => {
+            if state == 0 {
+                pressed_keys.remove(&key);
+                return Some(event);
+            }
+            pressed_keys.insert(key);
+            let blocked = permissions.blocked_keys.iter().any(|combination| {
+                combination.iter().any(|k| *k as u32 == key)
+                    && combination
+                        .iter()
+                        .all(|k| pressed_keys.contains(&(*k as u32)))
+            });
+            (!blocked).then_some(event)
+        }
+        (
+            _,
+            Event::Keyboard(KeyboardEvent::Modifiers {
+                depressed,
+                latched,
+                locked,
+                group,
+            }),
+        ) => {
+            let active = depressed | latched;
+            let mut blocked = 0;
+            for combination in permissions.blocked_keys.iter() {
+                // keys of the combination not covered by the mask must be held
+                let mut mask = 0;
+                let mut held = true;
+                for &k in combination {
+                    match Modifier::of_key(k as u32) {
+                        Some(m) => mask |= m.mask(),
+                        None => held &= pressed_keys.contains(&(k as u32)),
+                    }
+                }
+                if held && mask != 0 && active & mask == mask {
+                    blocked |= mask;
+                }
+            }
+            Some(Event::Keyboard(KeyboardEvent::Modifiers {
+                depressed: depressed & !blocked,
+                latched: latched & !blocked,
+                locked,
+                group,
+            }))
+        }
+        _ => Some(event),
+    }
+}
+
 fn to_ipc_pos(pos: Position) -> lan_mouse_ipc::Position {
     match pos {
         Position::Left => lan_mouse_ipc::Position::Left,
@@ -419,11 +669,21 @@ fn to_ipc_pos(pos: Position) -> lan_mouse_ipc::Position {
     }
 }
 
+pub(crate) fn to_emulation_pos(pos: Position) -> input_emulation::Position {
+    match pos {
+        Position::Left => input_emulation::Position::Left,
+        Position::Right => input_emulation::Position::Right,
+        Position::Top => input_emulation::Position::Top,
+        Position::Bottom => input_emulation::Position::Bottom,
+    }
+}
+
 async fn wait_for_termination(rx: &mut Receiver<ProxyRequest>) {
     loop {
         match rx.recv().await.expect("channel closed") {
             ProxyRequest::Terminate => return,
             ProxyRequest::Input(_, _) => continue,
+            ProxyRequest::WarpToEdge(..) => continue,
             ProxyRequest::Remove(_) => continue,
             ProxyRequest::Reenable => continue,
         }
@@ -450,3 +710,95 @@ impl<T> Drop for DropGuard<T> {
             .expect("channel closed");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use input_event::{PointerEvent, scancode::Linux};
+
+    fn key(key: Linux, state: u8) -> Event {
+        Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: key as u32,
+            state,
+        })
+    }
+
+    fn modifiers(depressed: u32) -> Event {
+        Event::Keyboard(KeyboardEvent::Modifiers {
+            depressed,
+            latched: 0,
+            locked: 0,
+            group: 0,
+        })
+    }
+
+    fn permissions(access: Access) -> Permissions {
+        Permissions {
+            access,
+            blocked_keys: vec![vec![
+                Linux::KeyLeftCtrl,
+                Linux::KeyLeftAlt,
+                Linux::KeyDelete,
+            ]],
+        }
+    }
+
+    #[test]
+    fn access() {
+        let motion = Event::Pointer(PointerEvent::Motion {
+            time: 0,
+            dx: 1.,
+            dy: 1.,
+        });
+        let mut pressed = HashSet::new();
+        let presence = permissions(Access::Presence);
+        assert_eq!(permit(&presence, &mut pressed, motion), None);
+        let pointer = permissions(Access::Pointer);
+        assert_eq!(permit(&pointer, &mut pressed, motion), Some(motion));
+        let a = key(Linux::KeyA, 1);
+        assert_eq!(permit(&pointer, &mut pressed, a), None);
+        let ctrl = modifiers(Modifier::Ctrl.mask());
+        assert_eq!(permit(&pointer, &mut pressed, ctrl), None);
+        let full = permissions(Access::Full);
+        assert_eq!(permit(&full, &mut pressed, a), Some(a));
+    }
+
+    #[test]
+    fn last_key_of_blocked_combination_is_dropped() {
+        let full = permissions(Access::Full);
+        let mut pressed = HashSet::new();
+        for k in [Linux::KeyLeftCtrl, Linux::KeyLeftAlt] {
+            assert!(permit(&full, &mut pressed, key(k, 1)).is_some());
+        }
+        assert_eq!(permit(&full, &mut pressed, key(Linux::KeyDelete, 1)), None);
+        // releases are always emulated
+        let release = key(Linux::KeyDelete, 0);
+        assert_eq!(permit(&full, &mut pressed, release), Some(release));
+        let delete = key(Linux::KeyDelete, 1);
+        permit(&full, &mut pressed, key(Linux::KeyLeftAlt, 0));
+        assert_eq!(permit(&full, &mut pressed, delete), Some(delete));
+    }
+
+    #[test]
+    fn blocked_modifiers_are_removed_from_the_mask() {
+        let (shift, ctrl, alt) = (
+            Modifier::Shift.mask(),
+            Modifier::Ctrl.mask(),
+            Modifier::Alt.mask(),
+        );
+        let full = permissions(Access::Full);
+        let mut pressed = HashSet::new();
+        // not blocked while the other keys of the combination are not held
+        let event = modifiers(ctrl | alt | shift);
+        assert_eq!(permit(&full, &mut pressed, event), Some(event));
+        permit(&full, &mut pressed, key(Linux::KeyDelete, 1));
+        let event = modifiers(ctrl);
+        assert_eq!(permit(&full, &mut pressed, event), Some(event));
+        // only the modifiers completing the combination are removed
+        assert_eq!(
+            permit(&full, &mut pressed, modifiers(ctrl | alt | shift)),
+            Some(modifiers(shift))
+        );
+    }
+}
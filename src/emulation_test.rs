@@ -1,46 +1,240 @@
 use crate::config::Config;
 use clap::Args;
-use input_emulation::{InputEmulation, InputEmulationError};
-use input_event::{Event, PointerEvent};
+use input_emulation::{EmulationError, InputEmulation, InputEmulationError};
+use input_event::{Button, Event, KeyboardEvent, Modifier, PointerEvent, scancode::Linux};
 use std::f64::consts::PI;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 const FREQUENCY_HZ: f64 = 1.0;
 const RADIUS: f64 = 100.0;
 
+/// text typed by the keyboard test
+const TEXT: &str = "hello from lan-mouse 0123456789";
+
+const HANDLE: u64 = 0;
+
+/// Tests the selected kinds of input, all of them if none is selected.
 #[derive(Args, Clone, Debug, Eq, PartialEq)]
 pub struct TestEmulationArgs {
+    /// move the pointer in a circle and press every mouse button
     #[arg(long)]
     mouse: bool,
+    /// type a text and press every modifier (focus a text field first)
     #[arg(long)]
     keyboard: bool,
+    /// scroll in both directions on both axes, discrete and smooth
     #[arg(long)]
     scroll: bool,
 }
 
-pub async fn run(config: Config, _args: TestEmulationArgs) -> Result<(), InputEmulationError> {
+pub async fn run(config: Config, args: TestEmulationArgs) -> Result<(), InputEmulationError> {
     log::info!("running input emulation test");
 
-    let backend = config.emulation_backend().map(|b| b.into());
+    let backend = config.emulation_backend();
     let mut emulation = InputEmulation::new(backend).await?;
-    emulation.create(0).await;
+    emulation.create(HANDLE).await;
+
+    let all = !(args.mouse || args.keyboard || args.scroll);
+    let mut steps = vec![];
+    if args.mouse || all {
+        steps.extend(mouse_steps());
+    }
+    if args.keyboard || all {
+        steps.extend(keyboard_steps());
+    }
+    if args.scroll || all {
+        steps.extend(scroll_steps());
+    }
+    if args.keyboard || all {
+        log::info!("focus a text field, typing starts in a few seconds");
+        tokio::time::sleep(Duration::from_secs(3)).await;
+    }
+
+    let mut errors = vec![];
+    for step in steps {
+        match step.run(&mut emulation).await {
+            Ok(()) => log::info!("{}: ok", step.name),
+            Err(e) => {
+                log::error!("{}: {e}", step.name);
+                let _ = emulation.release_keys(HANDLE).await;
+                errors.push(e);
+            }
+        }
+    }
+    emulation.terminate().await;
+
+    match errors.into_iter().next() {
+        None => {
+            log::info!("all steps succeeded");
+            Ok(())
+        }
+        Some(e) => Err(e.into()),
+    }
+}
+
+/// events emulated one after another, stopping at the first error
+struct Step {
+    name: String,
+    events: Vec<Event>,
+    interval: Duration,
+}
+
+impl Step {
+    fn new(name: impl Into<String>, events: Vec<Event>, interval: Duration) -> Self {
+        Self {
+            name: name.into(),
+            events,
+            interval,
+        }
+    }
+
+    async fn run(&self, emulation: &mut InputEmulation) -> Result<(), EmulationError> {
+        for &event in &self.events {
+            emulation.consume(event, HANDLE).await?;
+            tokio::time::sleep(self.interval).await;
+        }
+        Ok(())
+    }
+}
+
+fn mouse_steps() -> Vec<Step> {
+    let mut steps = vec![Step::new(
+        "pointer motion",
+        circle(),
+        Duration::from_millis(1),
+    )];
+    for button in Button::all() {
+        let (name, button) = (button.name(), button.code());
+        let events = [1, 0]
+            .map(|state| {
+                Event::Pointer(PointerEvent::Button {
+                    time: 0,
+                    button,
+                    state,
+                })
+            })
+            .to_vec();
+        steps.push(Step::new(
+            format!("button {name}"),
+            events,
+            Duration::from_millis(50),
+        ));
+    }
+    steps
+}
 
-    let start = Instant::now();
+/// relative motions moving the pointer in a circle once
+fn circle() -> Vec<Event> {
     let mut offset = (0, 0);
-    loop {
-        tokio::time::sleep(Duration::from_millis(1)).await;
-        let elapsed = start.elapsed();
-        let elapsed_sec_f64 = elapsed.as_secs_f64();
-        let second_fraction = elapsed_sec_f64 - elapsed_sec_f64 as u64 as f64;
-        let radians = second_fraction * 2. * PI * FREQUENCY_HZ;
+    let mut events = vec![];
+    let samples = 1000;
+    for i in 0..=samples {
+        let radians = i as f64 / samples as f64 * 2. * PI * FREQUENCY_HZ;
         let new_offset_f = (radians.cos() * RADIUS * 2., (radians * 2.).sin() * RADIUS);
         let new_offset = (new_offset_f.0 as i32, new_offset_f.1 as i32);
         if new_offset != offset {
             let relative_motion = (new_offset.0 - offset.0, new_offset.1 - offset.1);
             offset = new_offset;
             let (dx, dy) = (relative_motion.0 as f64, relative_motion.1 as f64);
-            let event = Event::Pointer(PointerEvent::Motion { time: 0, dx, dy });
-            emulation.consume(event, 0).await?;
+            events.push(Event::Pointer(PointerEvent::Motion { time: 0, dx, dy }));
+        }
+    }
+    events
+}
+
+fn keyboard_steps() -> Vec<Step> {
+    let key = |key: Linux, state| {
+        Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: key as u32,
+            state,
+        })
+    };
+    let modifiers = |depressed| {
+        Event::Keyboard(KeyboardEvent::Modifiers {
+            depressed,
+            latched: 0,
+            locked: 0,
+            group: 0,
+        })
+    };
+    let typed = TEXT
+        .chars()
+        .map(|c| char_key(c).unwrap_or_else(|| panic!("no key for {c:?}")))
+        .flat_map(|k| [key(k, 1), key(k, 0)])
+        .collect();
+    let mut steps = vec![Step::new(
+        format!("type \"{TEXT}\""),
+        typed,
+        Duration::from_millis(20),
+    )];
+    for modifier in Modifier::all() {
+        let (name, [k, _], mask) = (modifier.name(), modifier.keys(), modifier.mask());
+        let events = vec![key(k, 1), modifiers(mask), key(k, 0), modifiers(0)];
+        steps.push(Step::new(
+            format!("modifier {name}"),
+            events,
+            Duration::from_millis(50),
+        ));
+    }
+    steps
+}
+
+/// the key producing `c` on a US layout
+fn char_key(c: char) -> Option<Linux> {
+    match c {
+        ' ' => Some(Linux::KeySpace),
+        '-' => Some(Linux::KeyMinus),
+        c if c.is_ascii_alphanumeric() => format!("Key{}", c.to_ascii_uppercase()).parse().ok(),
+        _ => None,
+    }
+}
+
+fn scroll_steps() -> Vec<Step> {
+    let mut steps = vec![];
+    for (name, axis) in [("vertical", 0), ("horizontal", 1)] {
+        let discrete = [120, -120]
+            .map(|value| Event::Pointer(PointerEvent::AxisDiscrete120 { axis, value }))
+            .to_vec();
+        steps.push(Step::new(
+            format!("discrete scroll {name}"),
+            discrete,
+            Duration::from_millis(100),
+        ));
+        let smooth = [15., -15.]
+            .map(|value| {
+                Event::Pointer(PointerEvent::Axis {
+                    time: 0,
+                    axis,
+                    value,
+                })
+            })
+            .to_vec();
+        steps.push(Step::new(
+            format!("smooth scroll {name}"),
+            smooth,
+            Duration::from_millis(100),
+        ));
+    }
+    steps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_character_has_a_key() {
+        for c in TEXT.chars() {
+            assert!(char_key(c).is_some(), "no key for {c:?}");
         }
+        assert_eq!(char_key('a'), Some(Linux::KeyA));
+        assert_eq!(char_key('z'), Some(Linux::KeyZ));
+        assert_eq!(char_key('0'), Some(Linux::Key0));
+        assert_eq!(char_key('9'), Some(Linux::Key9));
+        assert_eq!(char_key(' '), Some(Linux::KeySpace));
+        assert_eq!(char_key('-'), Some(Linux::KeyMinus));
+        assert_eq!(char_key('!'), None);
+        assert_eq!(char_key('é'), None);
     }
 }
@@ -0,0 +1,164 @@
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use lan_mouse_ipc::{ClientHandle, HookEvent, HookStatus, Position};
+use tokio::process::Command;
+
+/// time after which a hook command is killed
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// hooks not belonging to a client
+#[derive(Clone, Debug)]
+pub(crate) struct Hooks {
+    /// time after which a hook command is killed
+    pub(crate) timeout: Duration,
+    /// command executed when a device connects to this one
+    pub(crate) incoming_connect: Option<String>,
+    /// command executed when a device connected to this one disconnects
+    pub(crate) incoming_disconnect: Option<String>,
+}
+
+impl Default for Hooks {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            incoming_connect: None,
+            incoming_disconnect: None,
+        }
+    }
+}
+
+/// Hooks of the same peer run one after another, so that
+/// e.g. a leave hook does not overtake the enter hook before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum HookQueue {
+    /// hooks of a configured client
+    Client(ClientHandle),
+    /// hooks of a device connecting to this one, that is not a client
+    Incoming(IpAddr),
+}
+
+/// a hook command waiting to be run
+#[derive(Debug)]
+pub(crate) struct Hook {
+    pub(crate) event: HookEvent,
+    pub(crate) handle: Option<ClientHandle>,
+    pub(crate) command: String,
+    pub(crate) env: HookEnv,
+}
+
+/// information about the peer, passed to a hook
+/// in `LAN_MOUSE_*` environment variables
+#[derive(Debug, Default)]
+pub(crate) struct HookEnv {
+    pub(crate) handle: Option<ClientHandle>,
+    pub(crate) hostname: Option<String>,
+    pub(crate) addr: Option<SocketAddr>,
+    pub(crate) pos: Option<Position>,
+    pub(crate) fingerprint: Option<String>,
+}
+
+impl HookEnv {
+    fn vars(&self) -> Vec<(&'static str, String)> {
+        let mut vars = vec![];
+        if let Some(handle) = self.handle {
+            vars.push(("LAN_MOUSE_HANDLE", handle.to_string()));
+        }
+        if let Some(hostname) = &self.hostname {
+            vars.push(("LAN_MOUSE_HOSTNAME", hostname.clone()));
+        }
+        if let Some(addr) = self.addr {
+            vars.push(("LAN_MOUSE_ADDRESS", addr.ip().to_string()));
+            vars.push(("LAN_MOUSE_PORT", addr.port().to_string()));
+        }
+        if let Some(pos) = self.pos {
+            vars.push(("LAN_MOUSE_POSITION", pos.to_string()));
+        }
+        if let Some(fingerprint) = &self.fingerprint {
+            vars.push(("LAN_MOUSE_FINGERPRINT", fingerprint.clone()));
+        }
+        vars
+    }
+}
+
+/// Run the hook command in a shell and wait for it to finish.
+/// Once the timeout expires, the shell is killed together
+/// with all processes it started.
+pub(crate) async fn run(
+    event: HookEvent,
+    command: &str,
+    env: &HookEnv,
+    timeout: Duration,
+) -> HookStatus {
+    let mut command_builder = Command::new("sh");
+    command_builder
+        .arg("-c")
+        .arg(command)
+        .env("LAN_MOUSE_EVENT", event.to_string())
+        .envs(env.vars())
+        .kill_on_drop(true);
+    // run the hook in its own process group, so it can be killed as a whole
+    #[cfg(unix)]
+    command_builder.process_group(0);
+    let child = command_builder.spawn();
+    let mut child = match child {
+        Ok(c) => c,
+        Err(e) => return HookStatus::Failed(e.to_string()),
+    };
+    match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(Ok(status)) => status
+            .code()
+            .map(HookStatus::Exited)
+            .unwrap_or(HookStatus::Terminated),
+        Ok(Err(e)) => HookStatus::Failed(e.to_string()),
+        Err(_) => {
+            #[cfg(unix)]
+            if let Some(pid) = child.id() {
+                // the process group id is the pid of the shell
+                unsafe {
+                    libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+                }
+            }
+            if let Err(e) = child.kill().await {
+                log::warn!("failed to kill `{command}`: {e}");
+            }
+            HookStatus::TimedOut
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn timeout_kills_process_group() {
+        let dir = std::env::temp_dir().join(format!("lan-mouse-hook-{}", std::process::id()));
+        let marker = dir.display();
+        // a background job of the hook would survive killing only the shell
+        let command = format!("(sleep 0.5; touch {marker}) & sleep 10");
+        let status = run(
+            HookEvent::Enter,
+            &command,
+            &HookEnv::default(),
+            Duration::from_millis(100),
+        )
+        .await;
+        assert_eq!(status, HookStatus::TimedOut);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert!(!dir.exists(), "background job of the hook was not killed");
+    }
+
+    #[tokio::test]
+    async fn exit_status() {
+        let env = HookEnv {
+            handle: Some(3),
+            ..Default::default()
+        };
+        let command = "test \"$LAN_MOUSE_EVENT $LAN_MOUSE_HANDLE\" = \"leave 3\" && exit 7";
+        let status = run(HookEvent::Leave, command, &env, DEFAULT_TIMEOUT).await;
+        assert_eq!(status, HookStatus::Exited(7));
+    }
+}
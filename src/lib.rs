@@ -1,11 +1,17 @@
 mod capture;
 pub mod capture_test;
 pub mod client;
+mod clipboard;
 pub mod config;
 mod connect;
 mod crypto;
+mod dbus;
+mod discovery;
 mod dns;
 mod emulation;
 pub mod emulation_test;
+mod hooks;
 mod listen;
+mod pairing;
+pub mod recording;
 pub mod service;
@@ -22,7 +22,10 @@ use webrtc_dtls::{
 };
 use webrtc_util::{Conn, Error, conn::Listener};
 
-use crate::crypto;
+use crate::{
+    crypto::{self, VerifyPeerCertificateFn},
+    pairing::Pairing,
+};
 
 #[derive(Error, Debug)]
 pub enum ListenerCreationError {
@@ -46,6 +49,13 @@ pub(crate) enum ListenEvent {
     Rejected {
         fingerprint: String,
     },
+    /// the pairing code was exchanged with a device
+    /// with an unknown certificate, accepted in pairing mode
+    PairingRequest {
+        addr: SocketAddr,
+        fingerprint: String,
+        code: String,
+    },
 }
 
 pub(crate) struct LanMouseListener {
@@ -57,17 +67,15 @@ pub(crate) struct LanMouseListener {
     port_changed: Receiver<Result<u16, ListenerCreationError>>,
 }
 
-type VerifyPeerCertificateFn = Arc<
-    dyn (Fn(&[Vec<u8>], &[CertificateDer<'static>]) -> Result<(), webrtc_dtls::Error>)
-        + Send
-        + Sync,
->;
-
 impl LanMouseListener {
+    /// `explicit` listen addresses were configured by the user
+    /// and must all be bound, see [`listen_all`]
     pub(crate) async fn new(
-        port: u16,
+        listen_addrs: Vec<SocketAddr>,
+        explicit: bool,
         cert: Certificate,
         authorized_keys: Arc<RwLock<HashMap<String, String>>>,
+        pairing: Pairing,
     ) -> Result<Self, ListenerCreationError> {
         let (listen_tx, listen_rx) = channel();
         let (request_port_change, mut request_port_change_rx) = channel();
@@ -77,6 +85,7 @@ impl LanMouseListener {
         let authorized = authorized_keys.clone();
         let verify_peer_certificate: Option<VerifyPeerCertificateFn> = {
             let connection_attempts = connection_attempts.clone();
+            let pairing = pairing.clone();
             Some(Arc::new(
                 move |certs: &[Vec<u8>], _chains: &[CertificateDer<'static>]| {
                     assert!(certs.len() == 1);
@@ -84,10 +93,11 @@ impl LanMouseListener {
                         .iter()
                         .map(|c| crypto::generate_fingerprint(c))
                         .collect::<Vec<_>>();
-                    if authorized
-                        .read()
-                        .expect("lock")
-                        .contains_key(&fingerprints[0])
+                    if pairing.active()
+                        || authorized
+                            .read()
+                            .expect("lock")
+                            .contains_key(&fingerprints[0])
                     {
                         Ok(())
                     } else {
@@ -109,8 +119,8 @@ impl LanMouseListener {
             ..Default::default()
         };
 
-        let listen_addr = SocketAddr::new("0.0.0.0".parse().expect("invalid ip"), port);
-        let mut listener = listen(listen_addr, cfg.clone()).await?;
+        let mut listen_addrs = listen_addrs;
+        let mut listeners = listen_all(&listen_addrs, explicit, &cfg).await?;
 
         let conns: Rc<AsyncMutex<Vec<(SocketAddr, ArcConn)>>> =
             Rc::new(AsyncMutex::new(Vec::new()));
@@ -125,8 +135,10 @@ impl LanMouseListener {
                     tokio::select! {
                         /* workaround for https://github.com/webrtc-rs/webrtc/issues/614 */
                         _ = sleep => continue,
-                        c = listener.accept() => match c {
+                        c = accept_any(&listeners) => match c {
                             Ok((conn, addr)) => {
+                                // dual-stack sockets report ipv4 peers as ipv4-mapped ipv6 addresses
+                                let addr = SocketAddr::new(addr.ip().to_canonical(), addr.port());
                                 log::info!("dtls client connected, ip: {addr}");
                                 let mut conns = conns_clone.lock().await;
                                 conns.push((addr, conn.clone()));
@@ -134,8 +146,12 @@ impl LanMouseListener {
                                 let certs = dtls_conn.connection_state().await.peer_certificates;
                                 let cert = certs.first().expect("cert");
                                 let fingerprint = crypto::generate_fingerprint(cert);
-                                listen_tx.send(ListenEvent::Accept { addr, fingerprint }).expect("channel closed");
-                                spawn_local(read_loop(conns_clone.clone(), addr, conn, listen_tx.clone()));
+                                if authorized_keys.read().expect("lock").contains_key(&fingerprint) {
+                                    listen_tx.send(ListenEvent::Accept { addr, fingerprint: fingerprint.clone() }).expect("channel closed");
+                                } else {
+                                    spawn_local(close_unconfirmed(addr, conn.clone(), fingerprint.clone(), authorized_keys.clone(), pairing.clone()));
+                                }
+                                spawn_local(read_loop(conns_clone.clone(), addr, conn, fingerprint, authorized_keys.clone(), pairing.clone(), listen_tx.clone()));
                             },
                             Err(e) => {
                                 if let Error::Std(ref e) = e {
@@ -158,16 +174,22 @@ impl LanMouseListener {
                         },
                         port = request_port_change_rx.recv() => {
                             let port = port.expect("channel closed");
-                            let listen_addr = SocketAddr::new("0.0.0.0".parse().expect("invalid ip"), port);
-                            match listen(listen_addr, cfg.clone()).await {
-                                Ok(new_listener) => {
-                                    let _ = listener.close().await;
-                                    listener = new_listener;
+                            let new_addrs = listen_addrs
+                                .iter()
+                                .map(|a| SocketAddr::new(a.ip(), port))
+                                .collect::<Vec<_>>();
+                            match listen_all(&new_addrs, explicit, &cfg).await {
+                                Ok(new_listeners) => {
+                                    for listener in listeners.iter() {
+                                        let _ = listener.close().await;
+                                    }
+                                    listeners = new_listeners;
+                                    listen_addrs = new_addrs;
                                     port_changed_tx.send(Ok(port)).expect("channel closed");
                                 }
                                 Err(e) => {
                                     log::warn!("unable to change port: {e}");
-                                    port_changed_tx.send(Err(e.into())).expect("channel closed");
+                                    port_changed_tx.send(Err(e)).expect("channel closed");
                                 }
                             };
                         },
@@ -245,16 +267,115 @@ impl Stream for LanMouseListener {
     }
 }
 
+/// bind a listener to each of the given addresses.
+/// Explicitly configured addresses must all be bound.
+/// Failures binding the implicit defaults are tolerated, as binding `0.0.0.0`
+/// fails after `[::]`, if the ipv6 socket is already dual-stack;
+/// in this case it fails only if none of the addresses could be bound.
+async fn listen_all(
+    addrs: &[SocketAddr],
+    explicit: bool,
+    cfg: &Config,
+) -> Result<Vec<impl Listener>, ListenerCreationError> {
+    let mut listeners = vec![];
+    let mut error = None;
+    for &addr in addrs {
+        match listen(addr, cfg.clone()).await {
+            Ok(l) => {
+                log::info!("listening on {addr}");
+                listeners.push(l);
+            }
+            Err(e) if explicit => {
+                log::warn!("could not listen on {addr}: {e}");
+                for listener in listeners.iter() {
+                    let _ = listener.close().await;
+                }
+                return Err(e.into());
+            }
+            Err(e) => {
+                log::info!("could not listen on {addr}: {e}");
+                error.get_or_insert(e);
+            }
+        }
+    }
+    match error {
+        Some(e) if listeners.is_empty() => Err(e.into()),
+        _ => Ok(listeners),
+    }
+}
+
+async fn accept_any(listeners: &[impl Listener]) -> Result<(ArcConn, SocketAddr), Error> {
+    if listeners.is_empty() {
+        return std::future::pending().await;
+    }
+    let accept = listeners.iter().map(|l| l.accept());
+    let (result, _, _) = futures::future::select_all(accept).await;
+    result
+}
+
+/// close a connection accepted in pairing mode,
+/// if it was not authorized by the time pairing ends
+async fn close_unconfirmed(
+    addr: SocketAddr,
+    conn: ArcConn,
+    fingerprint: String,
+    authorized_keys: Arc<RwLock<HashMap<String, String>>>,
+    pairing: Pairing,
+) {
+    pairing.ended().await;
+    if !authorized_keys
+        .read()
+        .expect("lock")
+        .contains_key(&fingerprint)
+    {
+        log::info!("closing unconfirmed pairing connection {addr}");
+        let _ = conn.close().await;
+    }
+}
+
 async fn read_loop(
     conns: Rc<AsyncMutex<Vec<(SocketAddr, ArcConn)>>>,
     addr: SocketAddr,
     conn: ArcConn,
+    fingerprint: String,
+    authorized_keys: Arc<RwLock<HashMap<String, String>>>,
+    pairing: Pairing,
     dtls_tx: Sender<ListenEvent>,
 ) -> Result<(), Error> {
     let mut b = [0u8; MAX_EVENT_SIZE];
 
-    while conn.recv(&mut b).await.is_ok() {
-        match b.try_into() {
+    while let Ok(len) = conn.recv(&mut b).await {
+        let event = ProtoEvent::try_from(&b[..len]);
+        if let Ok(event @ (ProtoEvent::PairingCommit { .. } | ProtoEvent::PairingNonce { .. })) =
+            &event
+        {
+            let exchanged = pairing.exchange(&fingerprint, event);
+            for reply in exchanged.replies {
+                let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = reply.into();
+                let _ = conn.send(&buf[..len]).await;
+            }
+            if let Some(code) = exchanged.code {
+                let fingerprint = fingerprint.clone();
+                dtls_tx
+                    .send(ListenEvent::PairingRequest {
+                        addr,
+                        fingerprint,
+                        code,
+                    })
+                    .expect("channel closed");
+            }
+            continue;
+        }
+        // connections accepted in pairing mode are ignored until authorized
+        if !authorized_keys
+            .read()
+            .expect("lock")
+            .contains_key(&fingerprint)
+        {
+            log::trace!("ignoring event from unauthorized peer {addr}");
+            continue;
+        }
+        match event {
             Ok(event) => dtls_tx
                 .send(ListenEvent::Msg { event, addr })
                 .expect("channel closed"),
@@ -5,6 +5,7 @@ use lan_mouse::{
     capture_test,
     config::{self, Command, Config, ConfigError},
     emulation_test,
+    recording::{self, RecordingError},
     service::{Service, ServiceError},
 };
 use lan_mouse_cli::CliError;
@@ -38,6 +39,8 @@ enum LanMouseError {
     Gtk(#[from] GtkError),
     #[error(transparent)]
     Cli(#[from] CliError),
+    #[error(transparent)]
+    Recording(#[from] RecordingError),
 }
 
 fn main() {
@@ -58,6 +61,8 @@ fn run() -> Result<(), LanMouseError> {
             Command::TestEmulation(args) => run_async(emulation_test::run(config, args))?,
             Command::TestCapture(args) => run_async(capture_test::run(config, args))?,
             Command::Cli(cli_args) => run_async(lan_mouse_cli::run(cli_args))?,
+            Command::Record(args) => run_async(recording::record(config, args))?,
+            Command::Replay(args) => run_async(recording::replay(config, args))?,
             Command::Daemon => {
                 // if daemon is specified we run the service
                 match run_async(run_service(config)) {
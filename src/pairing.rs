@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use lan_mouse_proto::ProtoEvent;
+use tokio::sync::Notify;
+
+use crate::crypto;
+
+/// how long unknown certificates are accepted after pairing was started
+pub(crate) const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Pairing mode, shared between the service, the listener and outgoing connections.
+/// While active, the listener accepts connections with unknown
+/// certificates. Events from these connections are discarded until
+/// the fingerprint has been authorized by confirming the pairing code.
+///
+/// The pairing code is derived from the fingerprints and a random nonce
+/// of both devices, exchanged with [`ProtoEvent::PairingCommit`] and
+/// [`ProtoEvent::PairingNonce`] (see [`Pairing::exchange`]).
+#[derive(Clone)]
+pub(crate) struct Pairing {
+    /// fingerprint of the local certificate
+    fingerprint: Arc<str>,
+    state: Arc<Mutex<State>>,
+    /// notified when pairing mode is started or stopped
+    changed: Arc<Notify>,
+}
+
+#[derive(Default)]
+struct State {
+    deadline: Option<Instant>,
+    /// code exchanges by fingerprint of the other device
+    exchanges: HashMap<String, Exchange>,
+}
+
+/// exchange of the pairing code with a single device
+struct Exchange {
+    nonce: [u8; 32],
+    /// commitment of the other device
+    commitment: Option<[u8; 32]>,
+    /// the code was derived or the other device did not keep its commitment
+    done: bool,
+}
+
+impl Exchange {
+    fn new() -> Self {
+        Self {
+            nonce: crypto::pairing_nonce(),
+            commitment: None,
+            done: false,
+        }
+    }
+
+    fn commit(&self) -> ProtoEvent {
+        ProtoEvent::PairingCommit {
+            commitment: crypto::pairing_commitment(&self.nonce),
+        }
+    }
+}
+
+/// result of handling a pairing event, see [`Pairing::exchange`]
+#[derive(Default)]
+pub(crate) struct Exchanged {
+    /// events to send back to the device
+    pub(crate) replies: Vec<ProtoEvent>,
+    /// the pairing code, once both nonces are known
+    pub(crate) code: Option<String>,
+}
+
+impl Pairing {
+    pub(crate) fn new(fingerprint: String) -> Self {
+        Self {
+            fingerprint: fingerprint.into(),
+            state: Default::default(),
+            changed: Default::default(),
+        }
+    }
+
+    /// start pairing mode, ending after [`PAIRING_TIMEOUT`]
+    pub(crate) fn start(&self) {
+        let mut state = self.state.lock().expect("lock");
+        state.deadline.replace(Instant::now() + PAIRING_TIMEOUT);
+        state.exchanges.clear();
+        self.changed.notify_waiters();
+    }
+
+    /// end pairing mode
+    pub(crate) fn stop(&self) {
+        let mut state = self.state.lock().expect("lock");
+        state.deadline.take();
+        state.exchanges.clear();
+        self.changed.notify_waiters();
+    }
+
+    /// whether unknown certificates are currently accepted
+    pub(crate) fn active(&self) -> bool {
+        self.deadline().is_some_and(|d| Instant::now() < d)
+    }
+
+    pub(crate) fn deadline(&self) -> Option<Instant> {
+        self.state.lock().expect("lock").deadline
+    }
+
+    /// wait until pairing mode ends, i.e. it is stopped or expires
+    pub(crate) async fn ended(&self) {
+        loop {
+            let changed = self.changed.notified();
+            let Some(deadline) = self.deadline() else {
+                return;
+            };
+            tokio::select! {
+                _ = tokio::time::sleep_until(deadline.into()) => {
+                    if !self.active() {
+                        return;
+                    }
+                }
+                _ = changed => {}
+            }
+        }
+    }
+
+    /// The commitment to send to a connected device, `None` if not pairing.
+    /// It is sent repeatedly, as datagrams may get lost.
+    pub(crate) fn commit(&self, fingerprint: &str) -> Option<ProtoEvent> {
+        if !self.active() {
+            return None;
+        }
+        let mut state = self.state.lock().expect("lock");
+        let exchange = state
+            .exchanges
+            .entry(fingerprint.to_owned())
+            .or_insert_with(Exchange::new);
+        Some(exchange.commit())
+    }
+
+    /// Handle a pairing event of the device with the given fingerprint.
+    ///
+    /// A commitment is answered with the local nonce, which is safe to reveal
+    /// once the other device can no longer choose its own.
+    /// Until the code is known, it is also answered with the local commitment,
+    /// as the other device reveals its nonce only after receiving it.
+    /// A nonce not matching the commitment fails the exchange.
+    pub(crate) fn exchange(&self, fingerprint: &str, event: &ProtoEvent) -> Exchanged {
+        let mut exchanged = Exchanged::default();
+        if !self.active() {
+            return exchanged;
+        }
+        let mut state = self.state.lock().expect("lock");
+        let exchange = state
+            .exchanges
+            .entry(fingerprint.to_owned())
+            .or_insert_with(Exchange::new);
+        match *event {
+            ProtoEvent::PairingCommit { commitment } => {
+                // the local nonce may already be revealed, a new commitment is not accepted
+                if *exchange.commitment.get_or_insert(commitment) != commitment {
+                    return exchanged;
+                }
+                if !exchange.done {
+                    exchanged.replies.push(exchange.commit());
+                }
+                exchanged.replies.push(ProtoEvent::PairingNonce {
+                    nonce: exchange.nonce,
+                });
+            }
+            ProtoEvent::PairingNonce { nonce } => {
+                let Some(commitment) = exchange.commitment else {
+                    return exchanged;
+                };
+                if exchange.done {
+                    return exchanged;
+                }
+                exchange.done = true;
+                if crypto::pairing_commitment(&nonce) != commitment {
+                    log::warn!("pairing: {fingerprint} did not keep its commitment");
+                    return exchanged;
+                }
+                let local = (self.fingerprint.as_ref(), &exchange.nonce);
+                exchanged.code = Some(crypto::pairing_code(local, (fingerprint, &nonce)));
+            }
+            _ => {}
+        }
+        exchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const A: &str = "aa:aa";
+    const B: &str = "bb:bb";
+
+    /// deliver events between two devices until no more replies are sent
+    fn run(a: &Pairing, b: &Pairing, commit: ProtoEvent) -> (Option<String>, Option<String>) {
+        let (mut code_a, mut code_b) = (None, None);
+        let mut to_b = vec![commit];
+        let mut to_a = vec![];
+        for _ in 0..10 {
+            for event in std::mem::take(&mut to_b) {
+                let exchanged = b.exchange(A, &event);
+                to_a.extend(exchanged.replies);
+                code_b = code_b.or(exchanged.code);
+            }
+            for event in std::mem::take(&mut to_a) {
+                let exchanged = a.exchange(B, &event);
+                to_b.extend(exchanged.replies);
+                code_a = code_a.or(exchanged.code);
+            }
+            if to_a.is_empty() && to_b.is_empty() {
+                return (code_a, code_b);
+            }
+        }
+        panic!("pairing events are sent back and forth");
+    }
+
+    fn pairing(fingerprint: &str) -> Pairing {
+        let pairing = Pairing::new(fingerprint.to_owned());
+        pairing.start();
+        pairing
+    }
+
+    #[test]
+    fn both_devices_derive_the_same_code() {
+        let (a, b) = (pairing(A), pairing(B));
+        let commit = a.commit(B).expect("commit");
+        let (code_a, code_b) = run(&a, &b, commit.clone());
+        assert!(code_a.is_some());
+        assert_eq!(code_a, code_b);
+        // a repeated commitment does not change the code
+        assert_eq!(run(&a, &b, commit), (None, None));
+    }
+
+    #[test]
+    fn nonce_must_match_commitment() {
+        let b = pairing(B);
+        let commitment = crypto::pairing_commitment(&[1; 32]);
+        let exchanged = b.exchange(A, &ProtoEvent::PairingCommit { commitment });
+        assert_eq!(exchanged.replies.len(), 2);
+        let exchanged = b.exchange(A, &ProtoEvent::PairingNonce { nonce: [2; 32] });
+        assert!(exchanged.code.is_none());
+        // the exchange failed, the correct nonce is not accepted anymore
+        let exchanged = b.exchange(A, &ProtoEvent::PairingNonce { nonce: [1; 32] });
+        assert!(exchanged.code.is_none());
+    }
+
+    #[test]
+    fn commitment_can_not_be_replaced() {
+        let b = pairing(B);
+        let commitment = crypto::pairing_commitment(&[1; 32]);
+        b.exchange(A, &ProtoEvent::PairingCommit { commitment });
+        let commitment = crypto::pairing_commitment(&[2; 32]);
+        let exchanged = b.exchange(A, &ProtoEvent::PairingCommit { commitment });
+        assert!(exchanged.replies.is_empty());
+        let exchanged = b.exchange(A, &ProtoEvent::PairingNonce { nonce: [2; 32] });
+        assert!(exchanged.code.is_none());
+    }
+
+    #[test]
+    fn nonce_is_revealed_only_after_commitment() {
+        let b = pairing(B);
+        let exchanged = b.exchange(A, &ProtoEvent::PairingNonce { nonce: [1; 32] });
+        assert!(exchanged.replies.is_empty() && exchanged.code.is_none());
+        let commitment = crypto::pairing_commitment(&[1; 32]);
+        let exchanged = b.exchange(A, &ProtoEvent::PairingCommit { commitment });
+        assert!(matches!(
+            exchanged.replies[..],
+            [
+                ProtoEvent::PairingCommit { .. },
+                ProtoEvent::PairingNonce { .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn inactive() {
+        let a = Pairing::new(A.to_owned());
+        assert!(a.commit(B).is_none());
+        let b = pairing(B);
+        let commit = b.commit(A).expect("commit");
+        assert!(a.exchange(B, &commit).replies.is_empty());
+        b.stop();
+        assert!(b.commit(A).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn ended() {
+        let pairing = pairing(A);
+        let ended = pairing.ended();
+        tokio::pin!(ended);
+        tokio::select! {
+            _ = &mut ended => panic!("ended early"),
+            _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+        }
+        pairing.stop();
+        ended.await;
+    }
+}
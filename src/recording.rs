@@ -0,0 +1,452 @@
+//! Record the events a device sends during a session and replay them
+//! with any input emulation backend.
+//!
+//! Recordings contain the events exactly as they are sent on the wire
+//! (see `lan-mouse-proto`): a header (`LMREC` and the protocol version as u16)
+//! followed by one record per event, consisting of the microseconds since the
+//! first event (u64), the length of the encoded event (u8) and the encoded event.
+//! All integers are big endian, like in the protocol.
+
+use std::{
+    collections::HashSet,
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    pin::pin,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use clap::Args;
+use futures::StreamExt;
+use input_emulation::{EmulationCreationError, EmulationError, InputEmulation};
+use input_event::{Event, PointerEvent};
+use lan_mouse_proto::{Capabilities, MAX_EVENT_SIZE, PROTOCOL_VERSION, ProtoEvent, ProtocolError};
+use thiserror::Error;
+use tokio::time::Instant;
+
+use crate::{
+    config::{Config, local_commit},
+    crypto,
+    emulation::to_emulation_pos,
+    listen::{LanMouseListener, ListenEvent, ListenerCreationError},
+    pairing::Pairing,
+};
+
+const MAGIC: &[u8; 5] = b"LMREC";
+
+/// emulation handle used for the replayed device
+const HANDLE: u64 = 0;
+
+#[derive(Args, Clone, Debug, PartialEq)]
+pub struct RecordArgs {
+    /// file the recording is written to
+    file: PathBuf,
+}
+
+#[derive(Args, Clone, Debug, PartialEq)]
+pub struct ReplayArgs {
+    /// recording to replay
+    file: PathBuf,
+    /// playback speed, e.g. 2 to replay twice as fast
+    #[arg(long, default_value_t = 1.0)]
+    speed: f64,
+}
+
+#[derive(Debug, Error)]
+pub enum RecordingError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("not a lan-mouse recording")]
+    InvalidHeader,
+    #[error("recording uses protocol version {0}, this build supports version {PROTOCOL_VERSION}")]
+    UnsupportedVersion(u16),
+    #[error("invalid playback speed: {0}")]
+    InvalidSpeed(f64),
+    #[error("invalid event in recording: `{0}`")]
+    Protocol(#[from] ProtocolError),
+    #[error("failed to load certificate: `{0}`")]
+    Certificate(#[from] crypto::Error),
+    #[error(transparent)]
+    Listen(#[from] ListenerCreationError),
+    #[error("error creating input-emulation: `{0}`")]
+    EmulationCreation(#[from] EmulationCreationError),
+    #[error("error emulating input: `{0}`")]
+    Emulation(#[from] EmulationError),
+}
+
+/// Act as the receiving device and record the enter, leave and input events
+/// of the first authorized device entering this one, until interrupted.
+pub async fn record(config: Config, args: RecordArgs) -> Result<(), RecordingError> {
+    let cert = crypto::load_or_generate_key_and_cert(config.cert_path())?;
+    let authorized_keys = Arc::new(RwLock::new(config.authorized_fingerprints()));
+    let pairing = Pairing::new(crypto::certificate_fingerprint(&cert));
+    let mut listener = LanMouseListener::new(
+        config.listen_addresses(),
+        config.configured_listen_addresses().is_some(),
+        cert,
+        authorized_keys,
+        pairing,
+    )
+    .await?;
+    let mut recording = RecordingWriter::create(&args.file)?;
+    log::info!(
+        "recording into {}, waiting for a device on port {} ...",
+        args.file.display(),
+        config.port()
+    );
+
+    let mut capabilities = Capabilities::HIGH_RES_SCROLL;
+    capabilities.insert(Capabilities::ABSOLUTE_MOTION);
+    let mut rejected = HashSet::new();
+    let mut peer: Option<SocketAddr> = None;
+    let mut start = None;
+    let mut count = 0usize;
+    let mut interrupted = pin!(tokio::signal::ctrl_c());
+    let result = loop {
+        let event = tokio::select! {
+            event = listener.next() => event,
+            _ = &mut interrupted => break Ok(()),
+        };
+        let (event, addr) = match event {
+            Some(ListenEvent::Msg { event, addr }) => (event, addr),
+            Some(ListenEvent::Accept { addr, fingerprint }) => {
+                log::info!("{addr} ({fingerprint}) connected");
+                continue;
+            }
+            Some(ListenEvent::Rejected { fingerprint }) => {
+                if rejected.insert(fingerprint.clone()) {
+                    log::warn!("rejected unauthorized device: {fingerprint}");
+                }
+                continue;
+            }
+            Some(ListenEvent::PairingRequest { .. }) => continue,
+            None => break Ok(()),
+        };
+
+        // answer like the service would, so the device keeps sending
+        match event {
+            ProtoEvent::Enter(..) | ProtoEvent::Leave(_) => {
+                listener.reply(addr, ProtoEvent::Ack(0)).await
+            }
+            ProtoEvent::Ping => listener.reply(addr, ProtoEvent::Pong(true)).await,
+            ProtoEvent::Hello { .. } => {
+                let hello = ProtoEvent::Hello {
+                    commit: local_commit(),
+                    version: PROTOCOL_VERSION,
+                    capabilities,
+                };
+                listener.reply(addr, hello).await
+            }
+            _ => {}
+        }
+
+        if !matches!(
+            event,
+            ProtoEvent::Enter(..) | ProtoEvent::Leave(_) | ProtoEvent::Input(_)
+        ) {
+            continue;
+        }
+        match peer {
+            None if matches!(event, ProtoEvent::Enter(..)) => {
+                log::info!("recording events of {addr}");
+                peer.replace(addr);
+            }
+            Some(peer) if peer == addr => {}
+            _ => continue,
+        }
+        let time = start.get_or_insert_with(Instant::now).elapsed();
+        if let Err(e) = recording.write(time, event) {
+            break Err(e.into());
+        }
+        count += 1;
+    };
+    listener.terminate().await;
+    log::info!("recorded {count} events");
+    result
+}
+
+/// Replay a recording with the configured input emulation backend.
+/// Keys and buttons still held at the end are released.
+pub async fn replay(config: Config, args: ReplayArgs) -> Result<(), RecordingError> {
+    if !(args.speed > 0. && args.speed.is_finite()) {
+        return Err(RecordingError::InvalidSpeed(args.speed));
+    }
+    let mut recording = RecordingReader::open(&args.file)?;
+    let emulation = InputEmulation::new(config.emulation_backend()).await?;
+    log::info!("replaying {}", args.file.display());
+
+    let mut player = Player::new(emulation);
+    let result = tokio::select! {
+        result = player.play(&mut recording, args.speed) => result,
+        _ = tokio::signal::ctrl_c() => Ok(()),
+    };
+    player.terminate().await;
+    result
+}
+
+/// emulates the events of a recording
+struct Player {
+    emulation: InputEmulation,
+    /// buttons held by the replayed device
+    /// (keys are released by [`InputEmulation`] itself)
+    buttons: HashSet<u32>,
+}
+
+impl Player {
+    fn new(emulation: InputEmulation) -> Self {
+        Self {
+            emulation,
+            buttons: Default::default(),
+        }
+    }
+
+    async fn play(
+        &mut self,
+        recording: &mut RecordingReader,
+        speed: f64,
+    ) -> Result<(), RecordingError> {
+        let start = Instant::now();
+        while let Some((time, event)) = recording.next()? {
+            tokio::time::sleep_until(start + time.div_f64(speed)).await;
+            self.emulate(event).await?;
+        }
+        log::info!("replay finished");
+        Ok(())
+    }
+
+    async fn emulate(&mut self, event: ProtoEvent) -> Result<(), EmulationError> {
+        match event {
+            ProtoEvent::Enter(pos, offset) => {
+                self.emulation.create(HANDLE).await;
+                if let Some(offset) = offset {
+                    self.emulation
+                        .warp_to_edge(to_emulation_pos(pos), offset, HANDLE)
+                        .await?;
+                }
+            }
+            ProtoEvent::Leave(_) => {
+                self.release_buttons().await?;
+                self.emulation.destroy(HANDLE).await;
+            }
+            ProtoEvent::Input(event) => {
+                if let Event::Pointer(PointerEvent::Button { button, state, .. }) = event {
+                    match state {
+                        0 => self.buttons.remove(&button),
+                        _ => self.buttons.insert(button),
+                    };
+                }
+                self.emulation.create(HANDLE).await;
+                self.emulation.consume(event, HANDLE).await?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    async fn release_buttons(&mut self) -> Result<(), EmulationError> {
+        for button in self.buttons.drain().collect::<Vec<_>>() {
+            let event = Event::Pointer(PointerEvent::Button {
+                time: 0,
+                button,
+                state: 0,
+            });
+            self.emulation.consume(event, HANDLE).await?;
+        }
+        Ok(())
+    }
+
+    async fn terminate(mut self) {
+        if let Err(e) = self.release_buttons().await {
+            log::warn!("failed to release buttons: {e}");
+        }
+        self.emulation.terminate().await;
+    }
+}
+
+struct RecordingWriter {
+    writer: BufWriter<File>,
+}
+
+impl RecordingWriter {
+    fn create(path: &Path) -> io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        writer.write_all(&PROTOCOL_VERSION.to_be_bytes())?;
+        Ok(Self { writer })
+    }
+
+    fn write(&mut self, time: Duration, event: ProtoEvent) -> io::Result<()> {
+        let (buf, len): ([u8; MAX_EVENT_SIZE], usize) = event.into();
+        self.writer
+            .write_all(&(time.as_micros() as u64).to_be_bytes())?;
+        self.writer.write_all(&[len as u8])?;
+        self.writer.write_all(&buf[..len])?;
+        // keep the recording usable if the process is killed
+        self.writer.flush()
+    }
+}
+
+struct RecordingReader {
+    reader: BufReader<File>,
+}
+
+impl RecordingReader {
+    fn open(path: &Path) -> Result<Self, RecordingError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut magic = [0u8; MAGIC.len()];
+        let mut version = [0u8; 2];
+        reader
+            .read_exact(&mut magic)
+            .and_then(|_| reader.read_exact(&mut version))
+            .map_err(|_| RecordingError::InvalidHeader)?;
+        if &magic != MAGIC {
+            return Err(RecordingError::InvalidHeader);
+        }
+        match u16::from_be_bytes(version) {
+            version if version > PROTOCOL_VERSION => {
+                Err(RecordingError::UnsupportedVersion(version))
+            }
+            _ => Ok(Self { reader }),
+        }
+    }
+
+    /// the next event and the time it was recorded at,
+    /// `None` at the end of the recording
+    fn next(&mut self) -> Result<Option<(Duration, ProtoEvent)>, RecordingError> {
+        let mut time = [0u8; 8];
+        let mut len = [0u8; 1];
+        let mut buf = [0u8; MAX_EVENT_SIZE];
+        let read = self
+            .reader
+            .read_exact(&mut time)
+            .and_then(|_| self.reader.read_exact(&mut len))
+            .and_then(|_| match len[0] as usize {
+                len if len <= MAX_EVENT_SIZE => self.reader.read_exact(&mut buf[..len]),
+                _ => Err(ErrorKind::InvalidData.into()),
+            });
+        match read {
+            Ok(()) => {}
+            // a recording that was interrupted may end with a partial record
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+        let time = Duration::from_micros(u64::from_be_bytes(time));
+        Ok(Some((time, ProtoEvent::try_from(buf)?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use input_emulation::{Backend, DummySink, Emulated, Recorder};
+    use input_event::{BTN_LEFT, KeyboardEvent};
+    use lan_mouse_proto::Position;
+
+    use super::*;
+
+    fn path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("lan-mouse-{name}-{}.lmrec", std::process::id()))
+    }
+
+    fn button(state: u32) -> ProtoEvent {
+        ProtoEvent::Input(Event::Pointer(PointerEvent::Button {
+            time: 0,
+            button: BTN_LEFT,
+            state,
+        }))
+    }
+
+    fn key(state: u8) -> ProtoEvent {
+        ProtoEvent::Input(Event::Keyboard(KeyboardEvent::Key {
+            time: 0,
+            key: 30,
+            state,
+        }))
+    }
+
+    fn write(path: &Path, events: &[(u64, ProtoEvent)]) {
+        let mut writer = RecordingWriter::create(path).expect("create");
+        for (ms, event) in events {
+            writer
+                .write(Duration::from_millis(*ms), event.clone())
+                .expect("write");
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let path = path("round-trip");
+        let events = [
+            (0, ProtoEvent::Enter(Position::Left, Some(0.25))),
+            (10, button(1)),
+            (20, button(0)),
+            (1500, ProtoEvent::Leave(0)),
+        ];
+        write(&path, &events);
+        // an interrupted recording ends with a partial record
+        let len = std::fs::metadata(&path).expect("metadata").len();
+        let file = std::fs::OpenOptions::new().write(true).open(&path);
+        file.expect("open").set_len(len - 1).expect("truncate");
+
+        let mut reader = RecordingReader::open(&path).expect("open");
+        for (ms, event) in &events[..3] {
+            let (time, read) = reader.next().expect("read").expect("event");
+            assert_eq!(time, Duration::from_millis(*ms));
+            assert_eq!(&read, event);
+        }
+        assert!(reader.next().expect("read").is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn invalid_header() {
+        let path = path("invalid-header");
+        std::fs::write(&path, b"LMRE").expect("write");
+        assert!(matches!(
+            RecordingReader::open(&path),
+            Err(RecordingError::InvalidHeader)
+        ));
+        let mut header = MAGIC.to_vec();
+        header.extend((PROTOCOL_VERSION + 1).to_be_bytes());
+        std::fs::write(&path, header).expect("write");
+        assert!(matches!(
+            RecordingReader::open(&path),
+            Err(RecordingError::UnsupportedVersion(_))
+        ));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn held_input_is_released() {
+        let path = path("held-input");
+        write(
+            &path,
+            &[
+                (0, ProtoEvent::Enter(Position::Left, None)),
+                (10, key(1)),
+                (20, button(1)),
+            ],
+        );
+        let recorder = Recorder::new();
+        let backend = Backend::Dummy(DummySink::Recorder(recorder.clone()));
+        let emulation = InputEmulation::new(Some(backend)).await.expect("emulation");
+        let mut recording = RecordingReader::open(&path).expect("open");
+        let mut player = Player::new(emulation);
+        player.play(&mut recording, 1.).await.expect("replay");
+        player.terminate().await;
+
+        let released = |event: &ProtoEvent| {
+            let ProtoEvent::Input(event) = event else {
+                unreachable!()
+            };
+            recorder
+                .recorded()
+                .contains(&Emulated::Event(HANDLE, *event))
+        };
+        assert!(released(&button(0)), "{:?}", recorder.recorded());
+        assert!(released(&key(0)), "{:?}", recorder.recorded());
+        assert_eq!(recorder.recorded().last(), Some(&Emulated::Destroy(HANDLE)));
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -2,26 +2,35 @@ use crate::{
     capture::{Capture, CaptureType, ICaptureEvent},
     client::ClientManager,
     config::{Config, ConfigClient},
-    connect::LanMouseConnection,
+    connect::{ConnectionEvent, LanMouseConnection},
     crypto,
+    dbus::{DBus, Responder},
+    discovery::{Discovery, DiscoveryEvent},
     dns::{DnsEvent, DnsResolver},
     emulation::{Emulation, EmulationEvent},
+    hooks::{self, Hook, HookEnv, HookQueue, Hooks},
     listen::{LanMouseListener, ListenerCreationError},
+    pairing::{PAIRING_TIMEOUT, Pairing},
 };
 use futures::StreamExt;
 use lan_mouse_ipc::{
-    AsyncFrontendListener, ClientHandle, FrontendEvent, FrontendRequest, IpcError,
-    IpcListenerCreationError, Position, Status,
+    AsyncFrontendListener, ClientHandle, DiscoveredPeer, EdgeSegment, FrontendEvent,
+    FrontendRequest, HookEvent, IncomingRequest, IpcError, IpcListenerCreationError,
+    PointerSettings, Position, RequestError, Response, ResponseToken, Status,
 };
+use lan_mouse_proto::Capabilities;
+use local_channel::mpsc::{Receiver, Sender, channel};
 use log;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     io,
     net::{IpAddr, SocketAddr},
+    rc::Rc,
     sync::{Arc, RwLock},
+    time::Duration,
 };
 use thiserror::Error;
-use tokio::{process::Command, signal, sync::Notify};
+use tokio::{signal, sync::Notify, task::spawn_local};
 
 #[derive(Debug, Error)]
 pub enum ServiceError {
@@ -44,10 +53,29 @@ pub struct Service {
     emulation: Emulation,
     /// dns resolver
     resolver: DnsResolver,
+    /// events from outgoing connections
+    connection_events: Receiver<ConnectionEvent>,
+    /// hooks not belonging to a client
+    hooks: Hooks,
+    /// results of finished hook commands
+    hook_tx: Sender<FrontendEvent>,
+    hook_results: Receiver<FrontendEvent>,
+    /// hooks waiting to be run, by peer
+    hook_queues: HashMap<HookQueue, Sender<Hook>>,
+    /// mdns service discovery (if available)
+    discovery: Option<Discovery>,
+    /// d-bus interface (if enabled and available)
+    dbus: Option<DBus>,
+    /// peers discovered on the local network
+    discovered_peers: HashMap<String, DiscoveredPeer>,
     /// frontend listener
     frontend_listener: AsyncFrontendListener,
     /// authorized public key sha256 fingerprints
     authorized_keys: Arc<RwLock<HashMap<String, String>>>,
+    /// pairing mode (shared with the listener)
+    pairing: Pairing,
+    /// devices encountered while pairing (fingerprint -> description)
+    pairing_peers: HashMap<String, String>,
     /// (outgoing) client information
     client_manager: ClientManager,
     /// current port
@@ -58,6 +86,10 @@ pub struct Service {
     frontend_event_pending: Notify,
     /// frontend events queued for sending
     pending_frontend_events: VecDeque<FrontendEvent>,
+    /// responses sent after the pending events
+    pending_responses: VecDeque<(ResponseToken, Result<Response, RequestError>)>,
+    /// requests waiting for the outcome of a port change, in order
+    port_change_requests: VecDeque<Requester>,
     /// status of input capture (enabled / disabled)
     capture_status: Status,
     /// status of input emulation (enabled / disabled)
@@ -78,6 +110,27 @@ struct Incoming {
 
 impl Service {
     pub async fn new(config: Config) -> Result<Self, ServiceError> {
+        // create frontend communication adapter, exit if already running
+        let frontend_listener = AsyncFrontendListener::new().await?;
+        let capture_backend = config.capture_backend();
+        let emulation_backend = config.emulation_backend();
+        Self::with_backends(
+            config,
+            frontend_listener,
+            capture_backend,
+            emulation_backend,
+        )
+        .await
+    }
+
+    /// Service using the given frontend listener and backends instead of
+    /// the ones selected by the config, e.g. to run several instances in one process.
+    pub async fn with_backends(
+        config: Config,
+        frontend_listener: AsyncFrontendListener,
+        capture_backend: Option<input_capture::Backend>,
+        emulation_backend: Option<input_emulation::Backend>,
+    ) -> Result<Self, ServiceError> {
         let client_manager = ClientManager::default();
         for client in config.clients() {
             client_manager.add_with_config(client);
@@ -87,24 +140,104 @@ impl Service {
         let cert = crypto::load_or_generate_key_and_cert(config.cert_path())?;
         let public_key_fingerprint = crypto::certificate_fingerprint(&cert);
 
-        // create frontend communication adapter, exit if already running
-        let frontend_listener = AsyncFrontendListener::new().await?;
-
         let authorized_keys = Arc::new(RwLock::new(config.authorized_fingerprints()));
+        let pairing = Pairing::new(public_key_fingerprint.clone());
         // listener + connection
-        let listener =
-            LanMouseListener::new(config.port(), cert.clone(), authorized_keys.clone()).await?;
-        let conn = LanMouseConnection::new(cert.clone(), client_manager.clone());
+        let listener = LanMouseListener::new(
+            config.listen_addresses(),
+            config.configured_listen_addresses().is_some(),
+            cert.clone(),
+            authorized_keys.clone(),
+            pairing.clone(),
+        )
+        .await?;
+
+        // clipboard shared with capture + emulation
+        let clipboard_backend = config.clipboard_backend().map(|b| b.into());
+        let clipboard = match config.clipboard() {
+            true => match lan_mouse_clipboard::create(clipboard_backend).await {
+                Ok(c) => Some(Rc::from(c)),
+                Err(e) => {
+                    log::warn!("clipboard sharing unavailable: {e}");
+                    None
+                }
+            },
+            false => None,
+        };
+
+        // optional protocol features supported by this instance
+        let mut capabilities = Capabilities::HIGH_RES_SCROLL;
+        // entry points are always sent, but only announced to devices connecting
+        // to this one if the emulation backend can warp the pointer to them
+        capabilities.insert(Capabilities::ABSOLUTE_MOTION);
+        if clipboard.is_some() {
+            capabilities.insert(Capabilities::CLIPBOARD);
+        }
+
+        let (connection_event_tx, connection_events) = channel();
+        let conn = LanMouseConnection::new(
+            cert.clone(),
+            client_manager.clone(),
+            capabilities,
+            pairing.clone(),
+            connection_event_tx,
+        );
 
         // input capture + emulation
-        let capture_backend = config.capture_backend().map(|b| b.into());
-        let capture = Capture::new(capture_backend, conn, config.release_bind());
-        let emulation_backend = config.emulation_backend().map(|b| b.into());
-        let emulation = Emulation::new(emulation_backend, listener);
+        let capture = Capture::new(
+            capture_backend,
+            conn,
+            client_manager.clone(),
+            config.release_bind(),
+            config.edge_guard(),
+            clipboard.clone(),
+        );
+        let emulation = Emulation::new(
+            emulation_backend,
+            listener,
+            clipboard,
+            capabilities,
+            config.permissions(),
+        );
 
         // create dns resolver
         let resolver = DnsResolver::new()?;
 
+        // advertise this instance + browse for others
+        let discovery = match config.discovery() {
+            true => {
+                let addrs = config
+                    .configured_listen_addresses()
+                    .map(|a| a.iter().map(|a| a.ip()).collect());
+                match Discovery::new(config.port(), public_key_fingerprint.clone(), addrs) {
+                    Ok(d) => Some(d),
+                    Err(e) => {
+                        log::warn!("peer discovery unavailable: {e}");
+                        None
+                    }
+                }
+            }
+            false => None,
+        };
+
+        // export the d-bus interface
+        #[cfg(dbus)]
+        let dbus = match config.dbus() {
+            true => match DBus::new().await {
+                Ok(d) => Some(d),
+                Err(e) => {
+                    log::warn!("d-bus interface unavailable: {e}");
+                    None
+                }
+            },
+            false => None,
+        };
+        #[cfg(not(dbus))]
+        let dbus = None;
+
+        let hooks = config.hooks();
+        let (hook_tx, hook_results) = channel();
+
         let port = config.port();
         let service = Self {
             config,
@@ -112,12 +245,24 @@ impl Service {
             emulation,
             frontend_listener,
             resolver,
+            connection_events,
+            hooks,
+            hook_tx,
+            hook_results,
+            hook_queues: Default::default(),
+            discovery,
+            dbus,
+            discovered_peers: Default::default(),
             authorized_keys,
+            pairing,
+            pairing_peers: Default::default(),
             public_key_fingerprint,
             client_manager,
             frontend_event_pending: Default::default(),
             port,
             pending_frontend_events: Default::default(),
+            pending_responses: Default::default(),
+            port_change_requests: Default::default(),
             capture_status: Default::default(),
             emulation_status: Default::default(),
             incoming_conn_info: Default::default(),
@@ -127,6 +272,11 @@ impl Service {
         Ok(service)
     }
 
+    /// sha256 fingerprint of the certificate of this instance
+    pub fn public_key_fingerprint(&self) -> &str {
+        &self.public_key_fingerprint
+    }
+
     pub async fn run(&mut self) -> Result<(), ServiceError> {
         let active = self.client_manager.active_clients();
         for handle in active.iter() {
@@ -142,11 +292,16 @@ impl Service {
 
         loop {
             tokio::select! {
-                request = self.frontend_listener.next() => self.handle_frontend_request(request),
+                request = self.frontend_listener.next() => self.handle_incoming_request(request),
+                (request, responder) = dbus_request(&mut self.dbus) => self.handle_dbus_request(request, responder),
                 _ = self.frontend_event_pending.notified() => self.handle_frontend_pending().await,
                 event = self.emulation.event() => self.handle_emulation_event(event),
                 event = self.capture.event() => self.handle_capture_event(event),
                 event = self.resolver.event() => self.handle_resolver_event(event),
+                event = self.connection_events.recv() => self.handle_connection_event(event.expect("channel closed")),
+                event = self.hook_results.recv() => self.notify_frontend(event.expect("channel closed")),
+                event = discovery_event(&mut self.discovery) => self.handle_discovery_event(event),
+                _ = pairing_expired(&self.pairing) => self.stop_pairing(),
                 _ = self.config.changed() => self.handle_config_change(),
                 r = signal::ctrl_c() => break r.expect("failed to wait for CTRL+C"),
             }
@@ -159,15 +314,84 @@ impl Service {
         self.emulation.terminate().await;
         log::debug!("terminating dns resolver ...");
         self.resolver.terminate().await;
+        if let Some(discovery) = self.discovery.as_mut() {
+            log::debug!("terminating discovery ...");
+            discovery.terminate();
+        }
+        if let Some(dbus) = self.dbus.as_mut() {
+            log::debug!("terminating d-bus interface ...");
+            dbus.terminate();
+        }
 
         Ok(())
     }
 
-    fn handle_frontend_request(&mut self, request: Option<Result<FrontendRequest, IpcError>>) {
-        let request = match request.expect("frontend listener closed") {
+    fn handle_incoming_request(&mut self, request: Option<Result<IncomingRequest, IpcError>>) {
+        let IncomingRequest { token, request } = match request.expect("frontend listener closed") {
             Ok(r) => r,
             Err(e) => return log::error!("error receiving request: {e}"),
         };
+        self.handle_request(request, Requester::Frontend(token));
+    }
+
+    fn handle_dbus_request(&mut self, request: FrontendRequest, responder: Responder) {
+        self.handle_request(Ok(request), Requester::DBus(responder));
+    }
+
+    fn handle_request(
+        &mut self,
+        request: Result<FrontendRequest, RequestError>,
+        requester: Requester,
+    ) {
+        // a port change is answered once the listener is bound to the new port
+        let pending = matches!(request, Ok(FrontendRequest::ChangePort(port)) if port != self.port);
+        let result = request.and_then(|r| self.handle_frontend_request(r));
+        if pending && result.is_ok() {
+            self.port_change_requests.push_back(requester);
+        } else {
+            self.respond(requester, result);
+        }
+    }
+
+    fn respond(&mut self, requester: Requester, result: Result<Response, RequestError>) {
+        let token = match requester {
+            Requester::Frontend(token) => token,
+            Requester::DBus(responder) => {
+                let _ = responder.send(result);
+                return;
+            }
+        };
+        match (token, result) {
+            (Some(token), result) => {
+                self.pending_responses.push_back((token, result));
+                self.frontend_event_pending.notify_one();
+            }
+            (None, Ok(_)) => {}
+            // already announced by `FrontendEvent::PortChanged`
+            (None, Err(RequestError::PortChange(_))) => {}
+            // frontends without request ids are notified through events
+            (None, Err(RequestError::NoSuchClient(handle))) => {
+                self.notify_frontend(FrontendEvent::NoSuchClient(handle))
+            }
+            (
+                None,
+                Err(e @ (RequestError::InvalidRequest(_) | RequestError::UnsupportedVersion(_))),
+            ) => {
+                log::warn!("{e}")
+            }
+            (None, Err(e)) => self.notify_frontend(FrontendEvent::Error(e.to_string())),
+        }
+    }
+
+    fn handle_frontend_request(
+        &mut self,
+        request: FrontendRequest,
+    ) -> Result<Response, RequestError> {
+        if let Some(handle) = request.client() {
+            if self.client_manager.get_state(handle).is_none() {
+                return Err(RequestError::NoSuchClient(handle));
+            }
+        }
         match request {
             FrontendRequest::Activate(handle, active) => {
                 self.set_client_active(handle, active);
@@ -179,13 +403,15 @@ impl Service {
             }
             FrontendRequest::ChangePort(port) => self.change_port(port),
             FrontendRequest::Create => {
-                self.add_client();
+                let handle = self.add_client();
                 self.save_config();
+                return Ok(Response::Created(handle));
             }
             FrontendRequest::Delete(handle) => {
                 self.remove_client(handle);
                 self.save_config();
             }
+            FrontendRequest::Capture(handle) => self.capture_client(handle)?,
             FrontendRequest::EnableCapture => self.capture.reenable(),
             FrontendRequest::EnableEmulation => self.emulation.reenable(),
             FrontendRequest::Enumerate() => self.enumerate(),
@@ -193,6 +419,22 @@ impl Service {
                 self.update_fix_ips(handle, fix_ips);
                 self.save_config();
             }
+            FrontendRequest::UpdateMotionMultiplier(handle, multiplier) => {
+                self.update_pointer_settings(handle, |p| p.motion_multiplier = multiplier.0)?;
+                self.save_config();
+            }
+            FrontendRequest::UpdateAcceleration(handle, acceleration) => {
+                self.update_pointer_settings(handle, |p| p.acceleration = acceleration.0)?;
+                self.save_config();
+            }
+            FrontendRequest::UpdateScrollMultiplier(handle, multiplier) => {
+                self.update_pointer_settings(handle, |p| p.scroll_multiplier = multiplier.0)?;
+                self.save_config();
+            }
+            FrontendRequest::UpdateNaturalScroll(handle, natural_scroll) => {
+                self.update_pointer_settings(handle, |p| p.natural_scroll = natural_scroll)?;
+                self.save_config();
+            }
             FrontendRequest::UpdateHostname(handle, host) => {
                 self.update_hostname(handle, host);
                 self.save_config();
@@ -205,17 +447,37 @@ impl Service {
                 self.update_pos(handle, pos);
                 self.save_config();
             }
+            FrontendRequest::UpdateSegment(handle, segment) => {
+                self.update_segment(handle, segment);
+                self.save_config();
+            }
             FrontendRequest::ResolveDns(handle) => self.resolve(handle),
             FrontendRequest::Sync => self.sync_frontend(),
             FrontendRequest::RemoveAuthorizedKey(key) => {
                 self.remove_authorized_key(key);
                 self.save_config();
             }
-            FrontendRequest::UpdateEnterHook(handle, enter_hook) => {
-                self.update_enter_hook(handle, enter_hook)
+            FrontendRequest::UpdateHook(handle, event, cmd) => {
+                self.update_hook(handle, event, cmd)?
+            }
+            FrontendRequest::UpdateEnterHook(handle, cmd) => {
+                self.update_hook(handle, HookEvent::Enter, cmd)?
+            }
+            FrontendRequest::UpdateFingerprint(handle, fingerprint) => {
+                self.update_fingerprint(handle, fingerprint);
+                self.save_config();
+            }
+            FrontendRequest::EnumeratePeers => self.enumerate_peers(),
+            FrontendRequest::AddDiscoveredPeer(name, pos) => {
+                let handle = self.add_discovered_peer(name, pos)?;
+                self.save_config();
+                return Ok(Response::Created(handle));
             }
             FrontendRequest::SaveConfiguration => self.save_config(),
+            FrontendRequest::StartPairing => self.start_pairing(),
+            FrontendRequest::ConfirmPairing(fingerprint) => self.confirm_pairing(fingerprint)?,
         }
+        Ok(Response::Done)
     }
 
     fn save_config(&mut self) {
@@ -227,8 +489,17 @@ impl Service {
                 hostname: c.hostname,
                 port: c.port,
                 pos: c.pos,
+                segment: c.segment,
                 active: s.active,
                 enter_hook: c.cmd,
+                leave_hook: c.leave_hook,
+                connect_hook: c.connect_hook,
+                disconnect_hook: c.disconnect_hook,
+                fingerprint: c.fingerprint,
+                key_map: c.key_map,
+                button_map: c.button_map,
+                pointer: s.pointer,
+                hotkey: c.hotkey,
             })
             .collect();
         self.config.set_clients(clients);
@@ -255,18 +526,27 @@ impl Service {
         }
         let release_bind = self.config.release_bind();
         self.capture.set_release_bind(release_bind);
+        self.capture.set_edge_guard(self.config.edge_guard());
+        self.hooks = self.config.hooks();
         let authorized_keys = self.config.authorized_fingerprints();
         self.authorized_keys
             .write()
             .unwrap()
             .clone_from(&authorized_keys);
+        self.emulation.set_permissions(self.config.permissions());
         self.sync_frontend();
     }
 
     async fn handle_frontend_pending(&mut self) {
         while let Some(event) = self.pending_frontend_events.pop_front() {
+            if let Some(dbus) = &self.dbus {
+                dbus.notify(event.clone());
+            }
             self.frontend_listener.broadcast(event).await;
         }
+        while let Some((token, response)) = self.pending_responses.pop_front() {
+            self.frontend_listener.respond(token, response).await;
+        }
     }
 
     fn handle_emulation_event(&mut self, event: EmulationEvent) {
@@ -274,6 +554,13 @@ impl Service {
             EmulationEvent::ConnectionAttempt { fingerprint } => {
                 self.notify_frontend(FrontendEvent::ConnectionAttempt { fingerprint });
             }
+            EmulationEvent::PairingRequest {
+                addr,
+                fingerprint,
+                code,
+            } => {
+                self.pairing_request(fingerprint, addr.ip().to_string(), code);
+            }
             EmulationEvent::Entered {
                 addr,
                 pos,
@@ -292,18 +579,36 @@ impl Service {
                 }
             }
             EmulationEvent::Disconnected { addr } => {
+                let incoming = self.incoming_conn_info.values().find(|i| i.addr == addr);
+                let (pos, fingerprint) = incoming.map(|i| (i.pos, i.fingerprint.clone())).unzip();
+                self.spawn_incoming_hook(HookEvent::IncomingDisconnect, addr, pos, fingerprint);
                 if let Some(addr) = self.remove_incoming(addr) {
                     self.notify_frontend(FrontendEvent::IncomingDisconnected(addr));
                 }
             }
-            EmulationEvent::PortChanged(port) => match port {
-                Ok(port) => {
-                    self.port = port;
-                    self.notify_frontend(FrontendEvent::PortChanged(port, None));
+            EmulationEvent::PortChanged(port) => {
+                let result = match port {
+                    Ok(port) => {
+                        self.port = port;
+                        if let Some(Err(e)) = self.discovery.as_mut().map(|d| d.advertise(port)) {
+                            log::warn!("failed to advertise new port: {e}");
+                        }
+                        self.notify_frontend(FrontendEvent::PortChanged(port, None));
+                        Ok(Response::Done)
+                    }
+                    Err(e) => {
+                        let e = e.to_string();
+                        self.notify_frontend(FrontendEvent::PortChanged(
+                            self.port,
+                            Some(e.clone()),
+                        ));
+                        Err(RequestError::PortChange(e))
+                    }
+                };
+                if let Some(requester) = self.port_change_requests.pop_front() {
+                    self.respond(requester, result);
                 }
-                Err(e) => self
-                    .notify_frontend(FrontendEvent::PortChanged(self.port, Some(format!("{e}")))),
-            },
+            }
             EmulationEvent::EmulationDisabled => {
                 self.emulation_status = Status::Disabled;
                 self.notify_frontend(FrontendEvent::EmulationStatus(self.emulation_status));
@@ -314,9 +619,16 @@ impl Service {
             }
             EmulationEvent::ReleaseNotify => self.capture.release(),
             EmulationEvent::Connected { addr, fingerprint } => {
+                let hook_fingerprint = Some(fingerprint.clone());
+                self.spawn_incoming_hook(HookEvent::IncomingConnect, addr, None, hook_fingerprint);
                 self.notify_frontend(FrontendEvent::DeviceConnected { addr, fingerprint });
             }
-            EmulationEvent::PeerHello { addr, commit } => {
+            EmulationEvent::PeerHello {
+                addr,
+                commit,
+                version,
+                capabilities,
+            } => {
                 // Map the peer's source addr back to its client handle
                 // and stamp the commit. Skip if we don't have an
                 // outgoing client configured for this peer (incoming-
@@ -324,6 +636,8 @@ impl Service {
                 // in that case anyway.
                 if let Some(handle) = self.client_manager.get_client(addr) {
                     self.client_manager.set_peer_commit(handle, Some(commit));
+                    self.client_manager
+                        .set_capabilities(handle, Some((version, capabilities)));
                     self.broadcast_client(handle);
                 }
             }
@@ -349,7 +663,11 @@ impl Service {
             }
             ICaptureEvent::ClientEntered(handle) => {
                 log::info!("entering client {handle} ...");
-                self.spawn_hook_command(handle);
+                self.spawn_client_hook(HookEvent::Enter, handle, None);
+            }
+            ICaptureEvent::ClientLeft(handle) => {
+                log::info!("leaving client {handle} ...");
+                self.spawn_client_hook(HookEvent::Leave, handle, None);
             }
         }
     }
@@ -373,6 +691,64 @@ impl Service {
         self.broadcast_client(handle);
     }
 
+    fn handle_connection_event(&mut self, event: ConnectionEvent) {
+        match event {
+            ConnectionEvent::Connected { handle, addr } => {
+                self.spawn_client_hook(HookEvent::Connect, handle, Some(addr));
+            }
+            ConnectionEvent::PairingCode {
+                handle,
+                fingerprint,
+                code,
+            } => {
+                let description = self
+                    .client_manager
+                    .get_hostname(handle)
+                    .unwrap_or_else(|| format!("client {handle}"));
+                self.pairing_request(fingerprint, description, code);
+            }
+            ConnectionEvent::Disconnected { handle, addr } => {
+                self.spawn_client_hook(HookEvent::Disconnect, handle, Some(addr));
+            }
+            ConnectionEvent::FingerprintPinned(handle) => {
+                self.save_config();
+                self.broadcast_client(handle);
+            }
+            ConnectionEvent::FingerprintMismatch {
+                handle,
+                expected,
+                actual,
+            } => self.notify_frontend(FrontendEvent::FingerprintMismatch {
+                handle,
+                expected,
+                actual,
+            }),
+        }
+    }
+
+    fn handle_discovery_event(&mut self, event: DiscoveryEvent) {
+        match event {
+            DiscoveryEvent::Discovered(peer) => {
+                log::info!("discovered peer {} ({:?})", peer.name, peer.ips);
+                // addresses are usually resolved one after another,
+                // only notify the frontend about new peers
+                if self
+                    .discovered_peers
+                    .insert(peer.name.clone(), peer.clone())
+                    .is_none()
+                {
+                    self.notify_frontend(FrontendEvent::PeerDiscovered(peer));
+                }
+            }
+            DiscoveryEvent::Removed(name) => {
+                log::info!("peer {name} disappeared");
+                if self.discovered_peers.remove(&name).is_some() {
+                    self.notify_frontend(FrontendEvent::PeerRemoved(name));
+                }
+            }
+        }
+    }
+
     fn resolve(&self, handle: ClientHandle) {
         if let Some(hostname) = self.client_manager.get_hostname(handle) {
             self.resolver.resolve(handle, hostname);
@@ -389,6 +765,7 @@ impl Service {
         ));
         let keys = self.authorized_keys.read().expect("lock").clone();
         self.notify_frontend(FrontendEvent::AuthorizedUpdated(keys));
+        self.notify_frontend(FrontendEvent::PairingStatus(self.pairing_status()));
     }
 
     const ENTER_HANDLE_BEGIN: u64 = u64::MAX / 2 + 1;
@@ -396,7 +773,8 @@ impl Service {
     fn add_incoming(&mut self, addr: SocketAddr, pos: Position, fingerprint: String) {
         let handle = Self::ENTER_HANDLE_BEGIN + self.next_trigger_handle;
         self.next_trigger_handle += 1;
-        self.capture.create(handle, pos, CaptureType::EnterOnly);
+        self.capture
+            .create(handle, pos, None, CaptureType::EnterOnly);
         self.incoming_conns.insert(addr);
         self.incoming_conn_info.insert(
             handle,
@@ -466,16 +844,116 @@ impl Service {
         self.notify_frontend(FrontendEvent::AuthorizedUpdated(keys));
     }
 
+    fn pairing_status(&self) -> Status {
+        if self.pairing.active() {
+            Status::Enabled
+        } else {
+            Status::Disabled
+        }
+    }
+
+    fn start_pairing(&mut self) {
+        log::info!(
+            "pairing: accepting unknown certificates for {}s",
+            PAIRING_TIMEOUT.as_secs()
+        );
+        self.pairing.start();
+        self.pairing_peers.clear();
+        self.notify_frontend(FrontendEvent::PairingStatus(Status::Enabled));
+    }
+
+    fn stop_pairing(&mut self) {
+        log::info!("pairing finished");
+        self.pairing.stop();
+        self.pairing_peers.clear();
+        self.notify_frontend(FrontendEvent::PairingStatus(Status::Disabled));
+    }
+
+    /// the pairing code was exchanged with a device with an unknown certificate,
+    /// that connected (incoming) or was connected to (outgoing) => show the code
+    fn pairing_request(&mut self, fingerprint: String, description: String, code: String) {
+        if !self.pairing.active()
+            || self
+                .authorized_keys
+                .read()
+                .expect("lock")
+                .contains_key(&fingerprint)
+        {
+            return;
+        }
+        // the device reconnects repeatedly until the pairing is confirmed
+        if self
+            .pairing_peers
+            .insert(fingerprint.clone(), description)
+            .is_some()
+        {
+            return;
+        }
+        log::info!("pairing request from {fingerprint}, code: {code}");
+        self.notify_frontend(FrontendEvent::PairingCode { fingerprint, code });
+    }
+
+    fn confirm_pairing(&mut self, fingerprint: String) -> Result<(), RequestError> {
+        let Some(description) = self.pairing_peers.remove(&fingerprint) else {
+            return Err(RequestError::NoPairingRequest(fingerprint));
+        };
+        log::info!("paired with {description} ({fingerprint})");
+        self.add_authorized_key(description, fingerprint);
+        self.save_config();
+        self.stop_pairing();
+        Ok(())
+    }
+
     fn enumerate(&mut self) {
         let clients = self.client_manager.get_client_states();
         self.notify_frontend(FrontendEvent::Enumerate(clients));
     }
 
-    fn add_client(&mut self) {
+    fn enumerate_peers(&mut self) {
+        let peers = self.discovered_peers.values().cloned().collect();
+        self.notify_frontend(FrontendEvent::EnumeratePeers(peers));
+    }
+
+    fn add_discovered_peer(
+        &mut self,
+        name: String,
+        pos: Position,
+    ) -> Result<ClientHandle, RequestError> {
+        let Some(peer) = self.discovered_peers.get(&name).cloned() else {
+            return Err(RequestError::NoSuchPeer(name));
+        };
+        let client = ConfigClient {
+            ips: HashSet::from_iter(peer.ips),
+            hostname: Some(peer.hostname),
+            port: peer.port,
+            pos,
+            segment: None,
+            active: false,
+            enter_hook: None,
+            leave_hook: None,
+            connect_hook: None,
+            disconnect_hook: None,
+            // the advertised fingerprint is unauthenticated,
+            // it is pinned on the first connection instead
+            fingerprint: None,
+            key_map: Default::default(),
+            button_map: Default::default(),
+            pointer: Default::default(),
+            hotkey: Default::default(),
+        };
+        let handle = self.client_manager.add_with_config(client);
+        log::info!("added client {handle} from discovered peer {name}");
+        let (c, s) = self.client_manager.get_state(handle).unwrap();
+        self.notify_frontend(FrontendEvent::Created(handle, c, s));
+        Ok(handle)
+    }
+
+    fn add_client(&mut self) -> ClientHandle {
         let handle = self.client_manager.add_client();
         log::info!("added client {handle}");
         let (c, s) = self.client_manager.get_state(handle).unwrap();
         self.notify_frontend(FrontendEvent::Created(handle, c, s));
+        handle
     }
 
     fn set_client_active(&mut self, handle: ClientHandle, active: bool) {
@@ -501,26 +979,37 @@ impl Service {
         /* resolve dns on activate */
         self.resolve(handle);
 
-        /* deactivate potential other client at this position */
+        /* deactivate potential other clients at this part of the edge */
         let Some(pos) = self.client_manager.get_pos(handle) else {
             return;
         };
 
-        if let Some(other) = self.client_manager.client_at(pos) {
-            if other != handle {
-                self.deactivate_client(other);
-            }
+        for other in self.client_manager.overlapping_clients(handle) {
+            self.deactivate_client(other);
         }
 
         /* activate the client */
         if self.client_manager.activate_client(handle) {
             /* notify capture and frontends */
-            self.capture.create(handle, pos, CaptureType::Default);
+            let segment = self.client_manager.get_segment(handle);
+            self.capture
+                .create(handle, pos, segment, CaptureType::Default);
             self.broadcast_client(handle);
             log::info!("activated client {handle} ({pos})");
         }
     }
 
+    fn capture_client(&mut self, handle: ClientHandle) -> Result<(), RequestError> {
+        match self.client_manager.get_state(handle) {
+            Some((_, s)) if s.active => {
+                self.capture.capture(handle);
+                Ok(())
+            }
+            Some(_) => Err(RequestError::ClientNotActive(handle)),
+            None => Err(RequestError::NoSuchClient(handle)),
+        }
+    }
+
     fn change_port(&mut self, port: u16) {
         if self.port != port {
             self.emulation.request_port_change(port);
@@ -538,6 +1027,8 @@ impl Service {
         {
             self.capture.destroy(handle);
         }
+        // hooks already queued still run
+        self.hook_queues.remove(&HookQueue::Client(handle));
         self.notify_frontend(FrontendEvent::Deleted(handle));
     }
 
@@ -568,8 +1059,55 @@ impl Service {
         self.broadcast_client(handle);
     }
 
-    fn update_enter_hook(&mut self, handle: ClientHandle, enter_hook: Option<String>) {
-        self.client_manager.set_enter_hook(handle, enter_hook);
+    fn update_segment(&mut self, handle: ClientHandle, segment: Option<EdgeSegment>) {
+        // update state in input capture
+        if self.client_manager.set_segment(handle, segment) {
+            self.deactivate_client(handle);
+            self.activate_client(handle);
+        }
+        self.broadcast_client(handle);
+    }
+
+    fn update_hook(
+        &mut self,
+        handle: ClientHandle,
+        event: HookEvent,
+        cmd: Option<String>,
+    ) -> Result<(), RequestError> {
+        if !event.is_client_hook() {
+            return Err(RequestError::InvalidArgument(format!(
+                "{event} hooks are configured in the [hooks] section of the config file"
+            )));
+        }
+        self.client_manager.set_hook(handle, event, cmd);
+        self.broadcast_client(handle);
+        Ok(())
+    }
+
+    fn update_pointer_settings(
+        &mut self,
+        handle: ClientHandle,
+        update: impl FnOnce(&mut PointerSettings),
+    ) -> Result<(), RequestError> {
+        let Some(mut pointer) = self.client_manager.get_pointer_settings(handle) else {
+            return Err(RequestError::NoSuchClient(handle));
+        };
+        update(&mut pointer);
+        let result = if pointer.is_valid() {
+            self.client_manager.set_pointer_settings(handle, pointer);
+            Ok(())
+        } else {
+            Err(RequestError::InvalidArgument(format!(
+                "invalid pointer settings: {pointer:?}"
+            )))
+        };
+        // reset the frontends to the current settings
+        self.broadcast_client(handle);
+        result
+    }
+
+    fn update_fingerprint(&mut self, handle: ClientHandle, fingerprint: Option<String>) {
+        self.client_manager.set_fingerprint(handle, fingerprint);
         self.broadcast_client(handle);
     }
 
@@ -582,29 +1120,134 @@ impl Service {
         self.notify_frontend(event);
     }
 
-    fn spawn_hook_command(&self, handle: ClientHandle) {
-        let Some(cmd) = self.client_manager.get_enter_cmd(handle) else {
+    /// run the hook of a client, if it has one for the given event
+    fn spawn_client_hook(
+        &mut self,
+        event: HookEvent,
+        handle: ClientHandle,
+        addr: Option<SocketAddr>,
+    ) {
+        let Some(cmd) = self.client_manager.get_hook(handle, event) else {
             return;
         };
-        tokio::task::spawn_local(async move {
-            log::info!("spawning command!");
-            let mut child = match Command::new("sh").arg("-c").arg(cmd.as_str()).spawn() {
-                Ok(c) => c,
-                Err(e) => {
-                    log::warn!("could not execute cmd: {e}");
-                    return;
-                }
-            };
-            match child.wait().await {
-                Ok(s) => {
-                    if s.success() {
-                        log::info!("{cmd} exited successfully");
-                    } else {
-                        log::warn!("{cmd} exited with {s}");
-                    }
-                }
-                Err(e) => log::warn!("{cmd}: {e}"),
-            }
+        let env = HookEnv {
+            handle: Some(handle),
+            hostname: self.client_manager.get_hostname(handle),
+            addr: addr.or_else(|| self.client_manager.active_addr(handle)),
+            pos: self.client_manager.get_pos(handle),
+            fingerprint: self.client_manager.get_fingerprint(handle),
+        };
+        let hook = Hook {
+            event,
+            handle: Some(handle),
+            command: cmd,
+            env,
+        };
+        self.spawn_hook(HookQueue::Client(handle), hook);
+    }
+
+    /// run the configured hook for an incoming connection
+    fn spawn_incoming_hook(
+        &mut self,
+        event: HookEvent,
+        addr: SocketAddr,
+        pos: Option<Position>,
+        fingerprint: Option<String>,
+    ) {
+        let cmd = match event {
+            HookEvent::IncomingConnect => self.hooks.incoming_connect.clone(),
+            HookEvent::IncomingDisconnect => self.hooks.incoming_disconnect.clone(),
+            _ => None,
+        };
+        let Some(cmd) = cmd else {
+            return;
+        };
+        // the device may also be configured as a client
+        let handle = self.client_manager.get_client(addr);
+        let env = HookEnv {
+            handle,
+            hostname: handle.and_then(|h| self.client_manager.get_hostname(h)),
+            addr: Some(addr),
+            pos,
+            fingerprint,
+        };
+        let queue = match handle {
+            Some(handle) => HookQueue::Client(handle),
+            None => HookQueue::Incoming(addr.ip()),
+        };
+        let hook = Hook {
+            event,
+            handle: None,
+            command: cmd,
+            env,
+        };
+        self.spawn_hook(queue, hook);
+    }
+
+    /// run the hook after the previous hooks of the same queue finished
+    fn spawn_hook(&mut self, queue: HookQueue, hook: Hook) {
+        let tx = self.hook_queues.entry(queue).or_insert_with(|| {
+            let (tx, rx) = channel();
+            spawn_local(run_hooks(rx, self.hooks.timeout, self.hook_tx.clone()));
+            tx
+        });
+        if let Err(e) = tx.send(hook) {
+            log::warn!("failed to queue hook: {e}");
+        }
+    }
+}
+
+/// run the hooks of a queue one after another, until the queue is closed
+async fn run_hooks(mut queue: Receiver<Hook>, timeout: Duration, hook_tx: Sender<FrontendEvent>) {
+    while let Some(hook) = queue.recv().await {
+        let Hook {
+            event,
+            handle,
+            command,
+            env,
+        } = hook;
+        log::info!("running {event} hook `{command}`");
+        let status = hooks::run(event, &command, &env, timeout).await;
+        if status.success() {
+            log::info!("{event} hook `{command}` {status}");
+        } else {
+            log::warn!("{event} hook `{command}` {status}");
+        }
+        let _ = hook_tx.send(FrontendEvent::HookFinished {
+            event,
+            handle,
+            command,
+            status,
         });
     }
 }
+
+/// where the response to a request is sent
+enum Requester {
+    /// a frontend connected to the ipc socket,
+    /// `None` if it does not expect a response
+    Frontend(Option<ResponseToken>),
+    /// a d-bus method call
+    DBus(Responder),
+}
+
+async fn pairing_expired(pairing: &Pairing) {
+    match pairing.deadline() {
+        Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn dbus_request(dbus: &mut Option<DBus>) -> (FrontendRequest, Responder) {
+    match dbus {
+        Some(dbus) => dbus.request().await,
+        None => std::future::pending().await,
+    }
+}
+
+async fn discovery_event(discovery: &mut Option<Discovery>) -> DiscoveryEvent {
+    match discovery {
+        Some(discovery) => discovery.event().await,
+        None => std::future::pending().await,
+    }
+}
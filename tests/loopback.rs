@@ -0,0 +1,341 @@
+//! End-to-end tests running two instances of the service in one process,
+//! connected over loopback with dummy capture and emulation backends:
+//! events injected into the capture of one instance
+//! are recorded by the emulation of the other one.
+#![cfg(unix)]
+
+use std::{
+    fs,
+    future::Future,
+    net::UdpSocket,
+    path::PathBuf,
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use input_capture::{CaptureEvent, DummySource, Injector, Position};
+use input_emulation::{DummySink, Emulated, Recorder};
+use input_event::{Event, KeyboardEvent, PointerEvent};
+use lan_mouse::{config::Config, service::Service};
+use lan_mouse_ipc::AsyncFrontendListener;
+use tokio::{sync::oneshot, task::LocalSet, time};
+
+const KEY_A: u32 = 30;
+
+/// a service running in its own thread and runtime
+struct Instance {
+    injector: Injector,
+    recorder: Recorder,
+    fingerprint: String,
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Instance {
+    async fn start(name: &str, port: u16, config: &str) -> Self {
+        let pid = std::process::id();
+        let dir = std::env::temp_dir().join(format!("lan-mouse-loopback-{pid}-{name}-{port}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("create test directory");
+        fs::write(dir.join("config.toml"), config).expect("write config");
+
+        let injector = Injector::new();
+        let recorder = Recorder::new();
+        let (fingerprint_tx, fingerprint_rx) = oneshot::channel();
+        let (stop, stop_rx) = oneshot::channel();
+        let thread = thread::spawn({
+            let injector = injector.clone();
+            let recorder = recorder.clone();
+            move || run(dir, port, injector, recorder, fingerprint_tx, stop_rx)
+        });
+        let fingerprint = fingerprint_rx.await.expect("service failed to start");
+        Self {
+            injector,
+            recorder,
+            fingerprint,
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// emit an event from the capture of this instance
+    fn inject(&self, pos: Position, event: CaptureEvent) {
+        self.injector.inject(pos, event);
+    }
+
+    /// stop the service without saying goodbye to its peers
+    fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            thread.join().expect("service thread panicked");
+        }
+    }
+}
+
+impl Drop for Instance {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn run(
+    dir: PathBuf,
+    port: u16,
+    injector: Injector,
+    recorder: Recorder,
+    fingerprint_tx: oneshot::Sender<String>,
+    stop_rx: oneshot::Receiver<()>,
+) {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("runtime");
+    LocalSet::new().block_on(&runtime, async move {
+        let config = Config::from_args([
+            "lan-mouse".into(),
+            "--config".into(),
+            dir.join("config.toml").into_os_string(),
+            "--cert-path".into(),
+            dir.join("lan-mouse.pem").into_os_string(),
+            "--port".into(),
+            port.to_string().into(),
+            "--listen-addresses".into(),
+            format!("127.0.0.1:{port}").into(),
+            "--clipboard-backend".into(),
+            "memory".into(),
+            // keep the instances to themselves
+            "--no-discovery".into(),
+            "--no-dbus".into(),
+        ])
+        .expect("config");
+        let frontend_listener = AsyncFrontendListener::bind(dir.join("lan-mouse.sock"))
+            .await
+            .expect("frontend listener");
+        let mut service = Service::with_backends(
+            config,
+            frontend_listener,
+            Some(input_capture::Backend::Dummy(DummySource::Injector(
+                injector,
+            ))),
+            Some(input_emulation::Backend::Dummy(DummySink::Recorder(
+                recorder,
+            ))),
+        )
+        .await
+        .expect("service");
+        let _ = fingerprint_tx.send(service.public_key_fingerprint().to_owned());
+        tokio::select! {
+            result = service.run() => result.expect("service"),
+            _ = stop_rx => {}
+        }
+    });
+}
+
+/// two distinct ports that are currently unused
+fn free_ports() -> (u16, u16) {
+    // both sockets are bound at the same time, so the ports differ
+    let a = UdpSocket::bind("127.0.0.1:0").expect("bind");
+    let b = UdpSocket::bind("127.0.0.1:0").expect("bind");
+    let port = |s: &UdpSocket| s.local_addr().expect("local addr").port();
+    (port(&a), port(&b))
+}
+
+/// `a` with `b` on its right, `b` accepting `a` unless `authorize` is false
+async fn start_pair(authorize: bool) -> (Instance, Instance) {
+    let (port_a, port_b) = free_ports();
+    let a = Instance::start(
+        "a",
+        port_a,
+        &format!(
+            r#"
+            [[clients]]
+            position = "right"
+            hostname = "b"
+            ips = ["127.0.0.1"]
+            port = {port_b}
+            activate_on_startup = true
+            "#
+        ),
+    )
+    .await;
+    let authorized = match authorize {
+        true => format!(r#""{}" = "a""#, a.fingerprint),
+        false => String::new(),
+    };
+    let b = Instance::start(
+        "b",
+        port_b,
+        &format!("[authorized_fingerprints]\n{authorized}\n"),
+    )
+    .await;
+    (a, b)
+}
+
+fn motion(dx: f64) -> CaptureEvent {
+    CaptureEvent::Input(Event::Pointer(PointerEvent::Motion {
+        time: 0,
+        dx,
+        dy: 0.,
+    }))
+}
+
+fn key(key: u32, state: u8) -> CaptureEvent {
+    CaptureEvent::Input(Event::Keyboard(KeyboardEvent::Key {
+        time: 0,
+        key,
+        state,
+    }))
+}
+
+fn is_motion(e: &Emulated) -> bool {
+    matches!(
+        e,
+        Emulated::Event(_, Event::Pointer(PointerEvent::Motion { .. }))
+    )
+}
+
+fn is_key(e: &Emulated, key: u32, state: u8) -> bool {
+    match e {
+        Emulated::Event(
+            _,
+            Event::Keyboard(KeyboardEvent::Key {
+                key: k, state: s, ..
+            }),
+        ) => *k == key && *s == state,
+        _ => false,
+    }
+}
+
+async fn within<F: Future>(secs: u64, what: &str, f: F) -> F::Output {
+    match time::timeout(Duration::from_secs(secs), f).await {
+        Ok(output) => output,
+        Err(_) => panic!("timed out waiting for {what}"),
+    }
+}
+
+/// cross from `a` into `b`: the connection is established lazily,
+/// so keep crossing until `b` emulates the motion
+async fn enter(a: &Instance, b: &Instance) {
+    let start = b.recorder.recorded().len();
+    within(20, "entering b", async {
+        loop {
+            a.inject(Position::Right, CaptureEvent::Begin(None));
+            a.inject(Position::Right, motion(1.));
+            let entered = time::timeout(
+                Duration::from_millis(500),
+                b.recorder.wait_for(|r| r[start..].iter().any(is_motion)),
+            );
+            if entered.await.is_ok() {
+                break;
+            }
+        }
+    })
+    .await;
+}
+
+#[tokio::test]
+async fn enter_and_leave() {
+    let (a, b) = start_pair(true).await;
+    enter(&a, &b).await;
+
+    // hitting the edge leading back to `a` releases the capture of `a`
+    b.inject(Position::Left, CaptureEvent::Begin(None));
+    within(
+        10,
+        "b to release its handle of a",
+        b.recorder
+            .wait_for(|r| r.iter().any(|e| matches!(e, Emulated::Destroy(_)))),
+    )
+    .await;
+
+    // input on `a` is no longer sent to `b`
+    let recorded = b.recorder.recorded().len();
+    a.inject(Position::Right, motion(1.));
+    time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(
+        b.recorder.recorded().len(),
+        recorded,
+        "{:?}",
+        b.recorder.recorded()
+    );
+}
+
+/// entering again after leaving resumes the emulation
+#[tokio::test]
+async fn enter_ack_leave() {
+    let (a, b) = start_pair(true).await;
+    for round in 1..=2 {
+        // input follows the acknowledged enter
+        enter(&a, &b).await;
+        a.inject(Position::Right, key(KEY_A, 1));
+        within(
+            5,
+            "key press",
+            b.recorder
+                .wait_for(|r| r.iter().filter(|e| is_key(e, KEY_A, 1)).count() == round),
+        )
+        .await;
+
+        // leaving releases the held key, then the handle of `a`
+        b.inject(Position::Left, CaptureEvent::Begin(None));
+        within(
+            10,
+            "leaving b",
+            b.recorder.wait_for(|r| {
+                let press = r.iter().rposition(|e| is_key(e, KEY_A, 1));
+                let release = r.iter().rposition(|e| is_key(e, KEY_A, 0));
+                let destroy = r.iter().rposition(|e| matches!(e, Emulated::Destroy(_)));
+                matches!(
+                    (press, release, destroy),
+                    (Some(p), Some(r), Some(d)) if p < r && r < d
+                )
+            }),
+        )
+        .await;
+    }
+}
+
+#[tokio::test]
+async fn keys_released_on_disconnect() {
+    let (mut a, b) = start_pair(true).await;
+    enter(&a, &b).await;
+
+    a.inject(Position::Right, key(KEY_A, 1));
+    within(
+        5,
+        "key press",
+        b.recorder
+            .wait_for(|r| r.iter().any(|e| is_key(e, KEY_A, 1))),
+    )
+    .await;
+
+    // `b` notices that `a` is gone and releases the key
+    a.stop();
+    within(
+        15,
+        "key release",
+        b.recorder.wait_for(|r| {
+            let release = r.iter().position(|e| is_key(e, KEY_A, 0));
+            let destroy = r.iter().rposition(|e| matches!(e, Emulated::Destroy(_)));
+            matches!((release, destroy), (Some(release), Some(destroy)) if release < destroy)
+        }),
+    )
+    .await;
+}
+
+#[tokio::test]
+async fn unauthorized_fingerprint_is_rejected() {
+    let (a, b) = start_pair(false).await;
+    for _ in 0..20 {
+        a.inject(Position::Right, CaptureEvent::Begin(None));
+        a.inject(Position::Right, motion(1.));
+        time::sleep(Duration::from_millis(100)).await;
+    }
+    assert!(
+        b.recorder.recorded().is_empty(),
+        "{:?}",
+        b.recorder.recorded()
+    );
+}